@@ -0,0 +1,67 @@
+//! Evaluation of a deep expression on the path the walker threads take: the tree is
+//! prepared once and every entry is evaluated against it by reference, so no filter
+//! is cloned or allocated per entry.
+//!
+//! `cargo bench --bench evaluate`
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use fgr_rs::search::prepare;
+use fgr_rs::walk::vfs::VfsTree;
+use fgr_rs::{parse_root, Evaluate};
+
+/// Counts the allocations of the whole process, the benchmark runs on one thread
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// 50 nested `and`s of an `or` each, every one of them reached for the entry
+fn deep_expression() -> String {
+    (0..50)
+        .map(|depth| format!("(depth != {depth} or name = *f{depth}*)"))
+        .collect::<Vec<_>>()
+        .join(" and (")
+        + &")".repeat(49)
+}
+
+fn evaluate(c: &mut Criterion) {
+    let root = Arc::new(prepare(parse_root(&deep_expression()).unwrap()));
+    let mut tree = VfsTree::new("/tmp");
+    let entry = tree.add_file("src/f2.rs", 4096).clone();
+
+    // the first evaluation sets up the matchers of the name patterns, once per thread
+    assert!(root.evaluate(&entry).unwrap());
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    assert!(root.evaluate(&entry).unwrap());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(allocations, 0, "evaluating an entry must not allocate");
+
+    c.bench_function("evaluate deep expression", |b| {
+        b.iter(|| black_box(&root).evaluate(black_box(&entry)).unwrap())
+    });
+    // what every entry cost when the tree was cloned for it
+    c.bench_function("clone and evaluate deep expression", |b| {
+        b.iter(|| black_box(&root).as_ref().clone().evaluate(black_box(&entry)).unwrap())
+    });
+}
+
+criterion_group!(benches, evaluate);
+criterion_main!(benches);
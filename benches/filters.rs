@@ -0,0 +1,54 @@
+//! Timings of the parser and the evaluator, a baseline for performance changes.
+//!
+//! `cargo bench --bench filters`
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use fgr_rs::evaluate::execution_manager::ExecutionManager;
+use fgr_rs::walk::vfs::VfsTree;
+use fgr_rs::{parse_root, Evaluate};
+
+/// The complex expression of the execution manager tests
+const EXPRESSION: &str = r#"
+    name = lol* and (
+        name = *lol and
+        size >= 100K
+    ) or not(
+        contains != *penguins* or
+        type = vid or not (
+            perms >= 777 or
+            user > 0
+        )
+    )
+"#;
+
+fn parse(c: &mut Criterion) {
+    c.bench_function("parse", |b| b.iter(|| parse_root(black_box(EXPRESSION)).unwrap()));
+}
+
+fn transform(c: &mut Criterion) {
+    // to_nnf and the transform consume the tree, so the clones are made untimed
+    let root = parse_root(EXPRESSION).unwrap();
+    c.bench_function("to_nnf", |b| {
+        b.iter_batched(|| root.clone(), |root| root.to_nnf(), BatchSize::SmallInput)
+    });
+
+    let nnf = root.to_nnf();
+    c.bench_function("tseitin", |b| {
+        b.iter_batched(|| nnf.clone(), ExecutionManager::new, BatchSize::SmallInput)
+    });
+}
+
+fn evaluate(c: &mut Criterion) {
+    let root =
+        parse_root("name = *.rs and (size > 10K or depth < 3) and ext != txt").unwrap();
+    let mut tree = VfsTree::new("/tmp");
+    let entry = tree.add_file("src/main.rs", 4096).clone();
+    c.bench_function("evaluate", |b| {
+        b.iter(|| root.evaluate(black_box(&entry)).unwrap())
+    });
+}
+
+criterion_group!(benches, parse, transform, evaluate);
+criterion_main!(benches);
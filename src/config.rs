@@ -1,11 +1,38 @@
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::io::IsTerminal;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{ArgAction, Command, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use itertools::Itertools;
 use nnf::parse_tree::ExpressionNode;
+use nnf::{e_and, e_leaf, e_not, e_or};
+use nom::combinator::all_consuming;
 
+use crate::evaluate::decode::ContentEncoding;
+use crate::parse::comparison::Comparison;
+use crate::parse::contains_skip::ContainsSkip;
+use crate::parse::diagnostics::parse_root_all;
+use crate::parse::entry_kind::EntryKind;
 use crate::parse::filter::Filter;
+use crate::parse::lint::{lint_query, QueryLint};
+use crate::parse::primitives::{compile_glob, escape_glob, parse_size, parse_time_span};
+use crate::printf::PrintfTemplate;
+use crate::run::ExecCommand;
+use crate::search::prepare;
 use crate::{parse_root, GenericError};
 
+/// More threads than this is a mistake rather than a tuning choice
+const MAX_THREADS: usize = 1024;
+
+/// The environment variable with arguments to put before the command line ones
+const DEFAULT_ARGS_VAR: &str = "FGR_DEFAULT_ARGS";
+
 #[derive(Parser, Debug)]
 #[command(
     author,
@@ -19,16 +46,77 @@ You can build complex query expressions in the form of:
 FILTER syntax is:
     NAME eq_op VALUE
 
-NAME can be any of: name, extension, mtime, atime, size, contains, depth, permissions, group, user, type.
+`not` binds tighter than `and`, and `and` binds tighter than `or`:
+    not a and b or c    is    ((not a) and b) or c
+
+permissions match the mode exactly, all of its bits with a `-` prefix or any of them
+with a `/` prefix, like -perm in find: perm=644, perm=-111, perm=/222.
+Symbolic modes are accepted too: perm=u+x needs all the bits, perm=go-w none of them
+
+depth counts from 0: a start directory is at depth 0 and its children are at
+depth 1, so `depth < 2` is the same as `find -maxdepth 1`
+
+size, depth, atime and mtime also accept the reversed form:
+    VALUE eq_op NAME
+
+Offsets from now can be chained: `mtime > now - 1w + 3d` is 4 days ago.
+
+atime and mtime also accept a window ending now:
+    mtime within 2h    is    mtime >= now - 2h and mtime <= now
+Times after the start of the search are not clamped, so `mtime > now - 1d` also
+matches files from the future; --no-future skips them.
+
+atime, mtime and btime (creation time) can be compared with each other:
+    atime < mtime    matches files not read since they were last modified
+
+path matches the path below the start directory: `*` stops at `/` and `**` crosses
+it, so in `fgr . -e "path = 'src/*.rs'"` it matches ./src/a.rs but not ./src/a/b.rs.
+Use --glob-literal-separator false to let `*` cross `/` too.
+
+kind matches the structure of an entry rather than its contents: dir, file, symlink,
+socket, fifo, block or char (d, f, l, s, p, b, c like find -type). Symlinks to
+existing entries are of the kind of their target unless --no-follow-metadata is set
+
+size also accepts an inclusive range, either end of which can be left out:
+    size = 1Mb..10Mb    is    size >= 1Mb and size <= 10Mb
+A size without a unit is a number of bytes; fgr warns about it and --strict-query
+rejects it.
+
+NAME can be any of: name, path, extension, stem, parent, mtime, atime, size, dirsize,
+contains, depth, permissions, group, user, type, kind, empty, children, newer, older,
+inode, dev, xattr, target, binary, text, readable, writable, executable, mine,
+my-group.
 NAME supports aliases. Run fgr with --syntax to get more information.
 VALUE can be a number, a number with a qualifier (Mb, hour), or a PATTERN.
 PATTERN can be either a glob (sample*) or regex: r"sample.+" or r'sample.+'.
 PATTERN can be either a plain expression (*glob*) or it can be wtapped in quotes: ('*glob') or ("*glob*").
+A `!` before a quoted PATTERN negates it: name = !'*.rs' is the same as name != '*.rs'.
+`~` matches a plain substring ignoring case, `!~` is its negation: name ~ config.
+
+Environment:
+    FGR_DEFAULT_ARGS    arguments to put before the command line ones, split like a
+                        shell would: FGR_DEFAULT_ARGS="--threads 4 --exclude '*.lock'"
+                        An option on the command line replaces the default one and
+                        the defaults conflicting with it; the values of options that
+                        can be repeated, like --exclude, add up
+
+Exit status:
+    0    the search completed
+    1    the search was interrupted (Ctrl-C, --timeout), a command of --exec failed
+         or the arguments are invalid
+    2    the search completed, but some files could not be read in time, so matches
+         may be missing
+    3    the search completed with fewer matches than --min-results
+    With -Q: 0 if anything matched, otherwise 2 after read timeouts and 1 if not.
+    With --invert-exit: 1 if anything matched, 0 if a completed search found nothing.
 
 Examples:
     Find all files with name equal to 'sample' under the current directory:
     fgr -e name=sample
 
+    Without -e, the first argument is a name substring to search the other ones for:
+    fgr sample /home /bin
+
     Find files containing 's' in name and 777 permissions:
     fgr /home /bin -e 'name=*s* and perm=777'
 
@@ -47,15 +135,40 @@ Examples:
     Find files under the /bin directory not owned by root:
     fgr /bin -e 'user > 0'
 
-    Find files under the /bin directory having suid bit (but not limited to):
-    fgr /bin -e 'perms>4000'
+    Find files you can execute:
+    fgr ~/bin -e 'executable and type != text'
+
+    Find files under the /bin directory having the suid bit set:
+    fgr /bin -e 'perms=-4000'
 
     Find recently accessed files (but not in future):
-    fgr /home -e 'atime > now - 1h and atime < now'
+    fgr /home -e 'atime within 1h'
+
+    Find files modified before 2023:
+    fgr /home -e 'mtime < 2023-01-01'
+
+    Find files modified during the last two weeks:
+    fgr /home -e 'mtime > "2 weeks ago"'
+
+    Find files modified after the reference file:
+    fgr /home -e 'newer = /home/user/.last-backup'
+
+    Find only directories:
+    fgr /home -e 'kind = dir and name = *cache*'
+    fgr /home -e 'name = *cache*' --file-type d
+
+    Find directories holding more than 1 gigabyte of files:
+    fgr /home -e 'dirsize > 1Gb'
 
     Find stuff in files:
     fgr /home -e 'type=text and contains=*stuff*'
 
+    Print the commands that would remove week old logs, then drop --dry-run:
+    fgr /tmp -e 'ext=log and mtime < now - 1w' --exec rm -- {} \; --dry-run
+
+    Make all scripts executable with as few chmod runs as possible:
+    fgr ~/bin -e 'ext=sh' --exec-batch chmod +x {} +
+
     Other examples:
     fgr /home /bin -e 'name=*s* and perm=777 or (name=*rs and contains=r".+user.is_birthday.*")'
     fgr /home /bin -e 'name=*s* and perm=777 or (name=*rs and contains=*birth*)'
@@ -64,17 +177,55 @@ Examples:
 "###
 )]
 pub struct Args {
-    /// A list of directories where to search
+    /// A list of directories where to search; `DIR:N` does not descend more than N
+    /// levels below DIR, unless --literal-dirs is given. Without -e, the first one is a name substring to search for
+    /// instead: `fgr foo DIR` is `fgr DIR -e 'name ~ foo'`
     start_dirs: Option<Vec<String>>,
 
-    /// Expression to evaluate on each file
-    #[arg(short)]
-    expression: String,
+    /// Expression to evaluate on each file; when repeated, every expression has to
+    /// match, as if they were joined with `and`
+    #[arg(short, required_unless_present = "start_dirs")]
+    expression: Vec<String>,
+
+    /// Take the start directories as they are, so `foo:3` is a directory with that
+    /// name rather than `foo` searched 3 levels deep
+    #[arg(long, default_value_t = false)]
+    literal_dirs: bool,
+
+    /// Do not descend into directories matching this expression, like -prune in find
+    #[arg(long, value_name = "EXPRESSION")]
+    prune_expr: Option<String>,
+
+    /// Skip entries with a name matching this glob; can be repeated
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
 
-    /// Print expression tree graphviz schema and exit
+    /// Never descend into directories with a name matching these globs, e.g.
+    /// `node_modules,.git,target`; can be repeated. Unlike --exclude, nothing under
+    /// them is even walked
+    #[arg(long, value_name = "GLOB", value_delimiter = ',')]
+    exclude_dir: Vec<String>,
+
+    /// Match only entries named exactly like one of the newline-separated (NUL with
+    /// -z) names listed in FILE, like a long `name = a or name = b ...` but much faster
+    #[arg(long, value_name = "FILE")]
+    names_from: Option<PathBuf>,
+
+    /// Evaluate the newline-separated (NUL with -z) paths listed in FILE instead of
+    /// walking the start directories; `-` reads the list from stdin
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
+    /// Print the parsed expression tree and its graphviz schema and exit
     #[arg(short = 'q', long, default_value_t = false)]
     print_expression_tree: bool,
 
+    /// Print the leaf filters with their weights in the order they are evaluated,
+    /// and the expression they are evaluated in, and exit
+    #[arg(long, visible_alias = "print-filters", default_value_t = false)]
+    explain: bool,
+
+    /// Number of threads to use, 0 picks one per CPU
     #[arg(short, long, default_value_t = num_cpus::get())]
     threads: usize,
 
@@ -82,10 +233,113 @@ pub struct Args {
     #[arg(short = 'p')]
     print0: bool,
 
+    /// Use NUL instead of newlines both to separate the results, like -p, and to
+    /// read the --files-from and --names-from lists, like `xargs -0`
+    #[arg(short = 'z', long, default_value_t = false)]
+    null: bool,
+
+    /// Print each match using a template instead of the path: %p path, %s size,
+    /// %m octal mode, %u uid, %g gid, %t mtime (RFC 3339), %% percent sign.
+    /// Each record is still followed by a newline, or by NUL with -p
+    #[arg(long, value_name = "TEMPLATE")]
+    printf: Option<String>,
+
+    /// Print absolute paths, made from the current directory without resolving
+    /// symlinks, instead of the paths as they were walked
+    #[arg(short = 'A', long, default_value_t = false)]
+    absolute_paths: bool,
+
+    /// Print each match once even if it is reached from several start directories,
+    /// e.g. nested ones or symlinks. Every printed path is kept in memory
+    #[arg(long, default_value_t = false)]
+    unique: bool,
+
+    /// Print at most N matches from each immediate subdirectory of a start
+    /// directory, e.g. to get a sample of a large tree
+    #[arg(long, value_name = "N")]
+    max_results_per_dir: Option<usize>,
+
+    /// Print the matches sorted by path, the same for every run however the walk or
+    /// `--files-from` is spread over the threads. Nothing is printed until the
+    /// search is over
+    #[arg(long, default_value_t = false)]
+    sort: bool,
+
+    /// Print shallower matches before deeper ones, sorted by path within a depth.
+    /// Nothing is printed until the search is over
+    #[arg(long, default_value_t = false)]
+    breadth_first: bool,
+
+    /// Walk the start directories one after another, so all the matches of the first
+    /// one are printed before any of the second. Each is still walked in parallel
+    #[arg(long, default_value_t = false)]
+    ordered_roots: bool,
+
+    /// Run COMMAND for each match instead of printing it, like -exec in find: every
+    /// `{}` in its arguments is replaced by the path, which is appended when there is
+    /// no `{}`. The command ends at a `;` argument or at the end of the line
+    #[arg(
+        long,
+        num_args = 1..,
+        value_terminator = ";",
+        allow_hyphen_values = true,
+        value_name = "COMMAND",
+        group = "exec_mode",
+        conflicts_with = "find_hardlinks"
+    )]
+    exec: Option<Vec<String>>,
+
+    /// Run COMMAND once for many matches, like -exec ... + in find: an argument that
+    /// is exactly `{}` is replaced by all of their paths, which are appended when
+    /// there is none. Matches are split over several runs to fit into ARG_MAX.
+    /// The command ends at a `+` argument or at the end of the line
+    #[arg(
+        long,
+        num_args = 1..,
+        value_terminator = "+",
+        allow_hyphen_values = true,
+        value_name = "COMMAND",
+        group = "exec_mode",
+        conflicts_with = "find_hardlinks"
+    )]
+    exec_batch: Option<Vec<String>>,
+
+    /// Print the --exec or --exec-batch command lines, quoted for a shell, instead
+    /// of running them
+    #[arg(long, default_value_t = false, requires = "exec_mode")]
+    dry_run: bool,
+
+    /// Print the lines the `contains` filters matched as `path:lineno:line`, like
+    /// grep -n, instead of the path. Only the filters that made the entry match
+    /// count. Matches of other filters and of binary files are printed as paths
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["printf", "long", "exec_mode", "find_hardlinks"]
+    )]
+    show_match_lines: bool,
+
+    /// Print the mode, owner, group, size and mtime before each path, like `ls -l`
+    #[arg(short, long, default_value_t = false, conflicts_with = "printf")]
+    long: bool,
+
+    /// Color the matched paths by their type. `auto` colors only when stdout is a
+    /// terminal and NO_COLOR is not set; -p and --printf output is never colored
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
     /// Enable all standard filters (all filters below)
     #[arg(short, long, default_value_t = false)]
     all: bool,
 
+    /// Disable all standard filters, overriding every filter below
+    #[arg(long, default_value_t = false, conflicts_with = "all")]
+    no_ignore: bool,
+
+    /// Include hidden files and directories; same as `--ignore-hidden false`
+    #[arg(short = 'H', long, default_value_t = false)]
+    hidden: bool,
+
     /// Ignore hidden files
     #[arg(long)]
     ignore_hidden: Option<bool>,
@@ -110,15 +364,216 @@ pub struct Args {
     #[arg(long)]
     read_git_exclude: Option<bool>,
 
-    /// Same filesystem
+    /// Also read ignore rules from files with this name, e.g. `.fgrignore`; can be
+    /// repeated. They take precedence over .ignore and .gitignore files
+    #[arg(long, value_name = "NAME")]
+    ignore_file: Vec<String>,
+
+    /// Do not descend into directories on other file systems. Use the `dev` filter to
+    /// match entries by device instead
     #[arg(long)]
     same_filesystem: Option<bool>,
+
+    /// Same as `--same-filesystem true`, spelled like in find, tar and rsync
+    #[arg(
+        short = 'x',
+        long,
+        default_value_t = false,
+        conflicts_with = "same_filesystem"
+    )]
+    one_file_system: bool,
+
+    /// Resolve symlinked start directories before walking, so they are searched and
+    /// printed as the directories they point to. Symlinks found while walking are
+    /// still not followed
+    #[arg(long, default_value_t = false)]
+    follow_roots: bool,
+
+    /// Read the metadata of symlinks themselves instead of their targets, like
+    /// lstat: a symlink is of the symlink type, its size is the length of the
+    /// target path, and its mtime, owner and mode are those of the link
+    #[arg(long, default_value_t = false)]
+    no_follow_metadata: bool,
+
+    /// Report entries that could not be evaluated, e.g. files `contains` cannot
+    /// read, instead of silently treating them as not matching
+    #[arg(long, default_value_t = false)]
+    show_errors: bool,
+
+    /// How the reported errors are written: `text` is the path followed by the
+    /// error on the next line, `json` one object with the path, kind and message per
+    /// line
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = ErrorFormat::Text)]
+    errors: ErrorFormat,
+
+    /// Stop the search after this long, e.g. `30s` or `5min`, printing whatever
+    /// matched so far and exiting with 1
+    #[arg(long, value_name = "DURATION")]
+    timeout: Option<String>,
+
+    /// Print nothing and stop at the first match; exit with 0 if anything matched
+    /// and with 1 otherwise. `--stats` are still printed to stderr
+    #[arg(short = 'Q', long, default_value_t = false, conflicts_with = "find_hardlinks")]
+    quiet: bool,
+
+    /// Exit with 3 if fewer than N entries matched, e.g. to check that a build
+    /// produced all of its artifacts
+    #[arg(long, value_name = "N", conflicts_with = "quiet")]
+    min_results: Option<usize>,
+
+    /// Exit with 1 if anything matched and with 0 otherwise, e.g. to fail a check
+    /// when a forbidden file is found. An interrupted search still exits with 1,
+    /// one that matched nothing but had read timeouts with 2
+    #[arg(long, default_value_t = false, conflicts_with = "min_results")]
+    invert_exit: bool,
+
+    /// Keep a line with the number of visited and matched entries updated on stderr
+    /// while walking; shown only when stderr is a terminal
+    #[arg(long, default_value_t = false)]
+    progress: bool,
+
+    /// Print the number of visited, matched and failed entries and the elapsed time
+    /// to stderr when done
+    #[arg(long = "stats", default_value_t = false)]
+    print_stats: bool,
+
+    /// Print how many times each kind of filter was evaluated and how long that took
+    /// to stderr when done, the slowest first
+    #[arg(long, default_value_t = false)]
+    profile: bool,
+
+    /// Stop at the first error in the expression; `--fail-fast false` reports every
+    /// error that can be found along with its offset
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    fail_fast: bool,
+
+    /// Reject queries that are likely mistakes instead of printing warnings
+    #[arg(long, default_value_t = false)]
+    strict_query: bool,
+
+    /// Require `name`, `path`, `stem`, `parent` and `extension` regexes to match the whole
+    /// value, e.g. `name = r'foo'` no longer matches `foobar`
+    #[arg(long, default_value_t = false)]
+    regex_full_match: bool,
+
+    /// Whether `*` and `?` in `path` globs stop at `/`, so `src/*.rs` does not match
+    /// `src/a/b.rs` and only `**` crosses directories. `name`, `stem` and `ext` see
+    /// a single component, where it makes no difference
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    glob_literal_separator: bool,
+
+    /// Match extensions ignoring case, e.g. `ext = jpg` matches `photo.JPG`
+    #[arg(long, default_value_t = false)]
+    ignore_ext_case: bool,
+
+    /// Stop a `contains` scan after this many bytes; anything past the cap does not
+    /// match
+    #[arg(long, value_name = "N")]
+    contains_max_bytes: Option<u64>,
+
+    /// Never read files matching this glob in `contains`; can be repeated. Replaces
+    /// the default `/proc/**/pagemap` and `/proc/kcore`, and `--contains-skip ''`
+    /// reads everything
+    #[arg(long, value_name = "GLOB")]
+    contains_skip: Vec<String>,
+
+    /// How long a single read may block while `type`, `contains`, `binary` or `text`
+    /// look into a file, or `--search-archives` lists an archive, e.g. `5s` on slow
+    /// network file systems; defaults to 1s
+    #[arg(long, value_name = "DURATION")]
+    read_timeout: Option<String>,
+
+    /// Open and read a file again up to N times, waiting a little longer each time,
+    /// when `type` or `contains` hits an interrupted or timed out read, e.g. on
+    /// flaky network file systems
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    io_retries: u32,
+
+    /// Decode files from this encoding before `contains` matches them: `utf16le`,
+    /// `utf16be`, or `auto` to pick UTF-16 by its byte order mark and UTF-8 otherwise
+    #[arg(long, value_enum, value_name = "ENCODING", default_value_t = ContentEncoding::Utf8)]
+    contains_encoding: ContentEncoding,
+
+    /// Classify `type` by the file extension instead of reading the file contents
+    #[arg(long, default_value_t = false)]
+    type_by_extension: bool,
+
+    /// Match only files of at least this size, e.g. `1Mb`; same as `size >= 1Mb`
+    #[arg(long, value_name = "SIZE")]
+    min_size: Option<String>,
+
+    /// Match only files of at most this size, e.g. `1Gb`; same as `size <= 1Gb`
+    #[arg(long, value_name = "SIZE")]
+    max_size: Option<String>,
+
+    /// Match only files of this size, like -size in find: `+1M` is at least 1Mb,
+    /// `-10k` is at most 10Kb and `1M` is exactly 1Mb; can be repeated. Units are
+    /// those of queries, also accepted in lower case here
+    #[arg(long, value_name = "[+-]SIZE", allow_hyphen_values = true)]
+    size: Vec<String>,
+
+    /// Match only entries of this type, like -type in find: f file, d directory,
+    /// l symlink, s socket, p fifo, b block and c char device; can be repeated to
+    /// match any of them. Same as `kind = f`
+    #[arg(long, value_name = "TYPE")]
+    file_type: Vec<String>,
+
+    /// Match only entries modified within this long, e.g. `2d`; same as
+    /// `mtime >= now - 2d`
+    #[arg(long, value_name = "DURATION")]
+    changed_within: Option<String>,
+
+    /// Match only entries modified at least this long ago, e.g. `1w`; same as
+    /// `mtime <= now - 1w`
+    #[arg(long, value_name = "DURATION")]
+    changed_before: Option<String>,
+
+    /// Skip entries with an atime or mtime after the search started, e.g. from clock
+    /// skew, when the query compares them: adds `atime <= now` or `mtime <= now`.
+    /// Otherwise a future time matches `mtime > now - 1d`
+    #[arg(long, default_value_t = false)]
+    no_future: bool,
+
+    /// Skip zero-byte files (empty directories are kept)
+    #[arg(long, default_value_t = false)]
+    exclude_empty: bool,
+
+    /// Experimental: also match the members of archives named `.zip`, `.tar`, `.tar.gz`
+    /// or `.tgz`, printed as `ARCHIVE::MEMBER`. Only the name, extension, size and
+    /// depth of a member are known, any other filter does not match it. Members
+    /// have no inode and cannot be opened by a command
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["exec_mode", "find_hardlinks"]
+    )]
+    search_archives: bool,
+
+    /// Print only matches that are hard links to the same file, grouped by inode
+    /// and separated by empty lines
+    #[arg(long, default_value_t = false)]
+    find_hardlinks: bool,
 }
 
 #[derive(Debug)]
 pub struct Config {
     pub start_dirs: Vec<PathBuf>,
+    /// Start directories given as `DIR:N` with their depth limits
+    pub depth_limits: Vec<(PathBuf, usize)>,
+    /// The deepest entry the query can match, so the walk never goes deeper
+    pub max_depth: Option<usize>,
+    /// The names of the directories the walk never descends into
+    pub exclude_dirs: Option<GlobSet>,
+    pub files_from: Option<PathBuf>,
+    /// What separates the paths of `files_from`, a newline or NUL
+    pub input_separator: u8,
+    /// The expression as it was parsed, before the NNF transformation
+    pub expression: ExpressionNode<Filter>,
     pub root: ExpressionNode<Filter>,
+    /// Directories matching this expression are not descended into
+    pub prune: Option<ExpressionNode<Filter>>,
+    /// Likely mistakes found in the query
+    pub lints: Vec<QueryLint>,
 
     pub threads: usize,
 
@@ -129,32 +584,276 @@ pub struct Config {
     pub git_ignore: Option<bool>,
     pub git_global: Option<bool>,
     pub git_exclude: Option<bool>,
+    /// Names of additional ignore files with the .gitignore syntax
+    pub ignore_files: Vec<String>,
 
+    /// Whether to stay on the file systems of the start directories: a directory
+    /// that is a mount point of another file system is still matched, but it is
+    /// not descended into
     pub same_filesystem: Option<bool>,
+    /// Whether the metadata of symlinks describes their targets
+    pub follow_metadata: bool,
 
     pub print_expression_tree: bool,
+    pub explain: bool,
     pub print0: bool,
+    pub printf: Option<PrintfTemplate>,
+    pub long: bool,
+    pub show_match_lines: bool,
+    pub absolute_paths: bool,
+    pub unique: bool,
+    pub max_results_per_dir: Option<usize>,
+    pub sort: bool,
+    pub breadth_first: bool,
+    /// Whether every start directory gets a walk of its own, run in order
+    pub ordered_roots: bool,
+    pub exec: Option<ExecCommand>,
+    pub dry_run: bool,
+    pub color: bool,
+    pub quiet: bool,
+    /// The fewest matches a completed search must find to exit with 0
+    pub min_results: Option<usize>,
+    /// Whether a completed search exits with 1 when anything matched
+    pub invert_exit: bool,
+    pub show_errors: bool,
+    pub error_format: ErrorFormat,
+    pub timeout: Option<Duration>,
+    /// How long a single read of an archive may block with `search_archives`
+    pub read_timeout: Option<Duration>,
+    pub progress: bool,
+    pub print_stats: bool,
+    pub profile: bool,
+    pub find_hardlinks: bool,
+    pub search_archives: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
 }
 
 impl Config {
     pub fn build() -> Result<Self, GenericError> {
-        let args: Args = Args::parse();
+        let defaults = default_args(std::env::var_os(DEFAULT_ARGS_VAR))?;
+        Self::from_args(parse_args(std::env::args_os().collect(), &defaults))
+    }
+
+    pub fn from_args(mut args: Args) -> Result<Self, GenericError> {
+        // without -e, clap guarantees at least one positional: the name substring
+        let name_substring = if args.expression.is_empty() {
+            let mut positionals = args.start_dirs.take().unwrap_or_default();
+            let pattern = positionals.remove(0);
+            args.start_dirs = (!positionals.is_empty()).then_some(positionals);
+            Some(pattern)
+        } else {
+            None
+        };
 
-        let start_dirs = if let Some(dirs) = args.start_dirs {
-            dirs.into_iter().map(PathBuf::from).collect()
+        let mut start_dirs = vec![];
+        let mut depth_limits = vec![];
+        if let Some(dirs) = &args.start_dirs {
+            for dir in dirs {
+                let (mut dir, max_depth) = match args.literal_dirs {
+                    true => (PathBuf::from(dir), None),
+                    false => parse_start_dir(dir),
+                };
+                if args.follow_roots {
+                    dir = std::fs::canonicalize(&dir)
+                        .map_err(|err| GenericError::StartDirError(dir, err))?;
+                }
+                check_start_dir(&dir)?;
+                if let Some(max_depth) = max_depth {
+                    depth_limits.push((dir.clone(), max_depth));
+                }
+                start_dirs.push(dir);
+            }
         } else {
-            vec![std::env::current_dir()?]
+            start_dirs.push(std::env::current_dir()?);
+        }
+
+        let threads = match args.threads {
+            0 => num_cpus::get(),
+            threads if threads > MAX_THREADS => {
+                return Err(GenericError::InvalidThreadCount(threads, MAX_THREADS));
+            }
+            threads => threads,
+        };
+
+        let mut root = match &name_substring {
+            Some(pattern) => {
+                let glob = format!("*{}*", escape_glob(pattern));
+                let value = compile_glob(&glob, false)
+                    .map_err(|err| GenericError::InvalidGlob(glob, err))?;
+                e_leaf!(Filter::Name { value, comparison: Comparison::Eq })
+            }
+            None => parse_expressions(&args.expression, args.fail_fast)?,
+        };
+        for glob in &args.exclude {
+            let value = compile_glob(glob, false)
+                .map_err(|err| GenericError::InvalidGlob(glob.clone(), err))?;
+            let excluded = Filter::Name { value, comparison: Comparison::Eq };
+            root = e_and!(root, e_not!(e_leaf!(excluded)));
+        }
+        for (size, comparison) in
+            [(&args.min_size, Comparison::Gte), (&args.max_size, Comparison::Lte)]
+        {
+            if let Some(size) = size {
+                let value = parse_size_arg(size)?;
+                root = e_and!(
+                    root,
+                    e_leaf!(Filter::Size { value, comparison, unitless: false })
+                );
+            }
+        }
+        for size in &args.size {
+            let (comparison, value) = parse_signed_size_arg(size)?;
+            root = e_and!(
+                root,
+                e_leaf!(Filter::Size { value, comparison, unitless: false })
+            );
+        }
+        let kinds = args
+            .file_type
+            .iter()
+            .map(|file_type| {
+                let value = EntryKind::from_find_letter(file_type)
+                    .ok_or_else(|| GenericError::InvalidFileType(file_type.clone()))?;
+                Ok(e_leaf!(Filter::Kind { value, comparison: Comparison::Eq }))
+            })
+            .collect::<Result<Vec<_>, GenericError>>()?;
+        let kinds = kinds.into_iter().reduce(|kinds, kind| e_or!(kinds, kind));
+        if let Some(kinds) = kinds {
+            // the type is known without a stat, so it goes first and spares the
+            // other filters every entry of another type
+            root = e_and!(kinds, root);
+        }
+        let changed = [
+            (&args.changed_within, Comparison::Gte),
+            (&args.changed_before, Comparison::Lte),
+        ];
+        for (span, comparison) in changed {
+            if let Some(span) = span {
+                let value = (-parse_span_arg(span)?).into();
+                root =
+                    e_and!(root, e_leaf!(Filter::ModificationTime { value, comparison }));
+            }
+        }
+        if args.no_future {
+            let (mut atime, mut mtime) = (false, false);
+            for_each_filter_mut(&mut root, &mut |filter| match filter {
+                Filter::AccessTime { .. } => atime = true,
+                Filter::ModificationTime { .. } => mtime = true,
+                _ => {}
+            });
+
+            let now = || chrono::Duration::zero().into();
+            if atime {
+                let not_future =
+                    Filter::AccessTime { value: now(), comparison: Comparison::Lte };
+                root = e_and!(root, e_leaf!(not_future));
+            }
+            if mtime {
+                let not_future = Filter::ModificationTime {
+                    value: now(),
+                    comparison: Comparison::Lte,
+                };
+                root = e_and!(root, e_leaf!(not_future));
+            }
+        }
+        if let Some(path) = args.names_from.take() {
+            let separator = if args.null { b'\0' } else { b'\n' };
+            let names = read_names(&path, separator)
+                .map_err(|err| GenericError::ReferenceFileError(path.clone(), err))?;
+            let in_set = Filter::NameInSet { names, path, comparison: Comparison::Eq };
+            root = e_and!(root, e_leaf!(in_set));
+        }
+        if args.exclude_empty {
+            let non_empty = Filter::Empty { dirs: false, comparison: Comparison::Neq };
+            root = e_and!(root, e_leaf!(non_empty));
+        }
+        let read_timeout =
+            args.read_timeout.as_deref().map(parse_timeout_arg).transpose()?;
+        apply_filter_options(&mut root, &args, read_timeout)?;
+
+        let expression = root.clone();
+        root = prepare(root);
+
+        let prune = match args.prune_expr.as_deref() {
+            Some(prune) => {
+                let mut prune = parse_root(prune)?;
+                apply_filter_options(&mut prune, &args, read_timeout)?;
+                Some(prepare(prune))
+            }
+            None => None,
         };
 
-        let mut root = parse_root(&args.expression)?;
-        root = root.to_nnf();
-        root.sort_by_key(|filter| filter.weight());
+        let lints = lint_query(&root);
+        if args.strict_query && !lints.is_empty() {
+            let explanation = lints.iter().map(|lint| lint.to_string()).join("; ");
+            return Err(GenericError::StrictQueryError(explanation));
+        }
+
+        if args.hidden {
+            if args.ignore_hidden == Some(true) {
+                return Err(GenericError::ConflictingFlags(
+                    "--hidden and --ignore-hidden true".to_string(),
+                ));
+            }
+            args.ignore_hidden = Some(false);
+        }
+
+        if args.no_ignore {
+            for flag in [
+                &mut args.ignore_hidden,
+                &mut args.read_parents,
+                &mut args.read_ignore,
+                &mut args.read_git_ignore,
+                &mut args.read_git_global,
+                &mut args.read_git_exclude,
+            ] {
+                *flag = Some(false);
+            }
+        }
+
+        let max_depth = max_depth_bound(&root);
+        let exclude_dirs = (!args.exclude_dir.is_empty())
+            .then(|| build_exclude_dirs(&args.exclude_dir))
+            .transpose()?;
 
         Ok(Config {
             start_dirs,
+            depth_limits,
+            max_depth,
+            exclude_dirs,
+            files_from: args.files_from,
+            input_separator: if args.null { b'\0' } else { b'\n' },
+            expression,
             root,
+            prune,
+            lints,
 
-            threads: args.threads,
+            threads,
 
             standard_filters: args.all,
             hidden: args.ignore_hidden,
@@ -163,11 +862,1642 @@ impl Config {
             git_ignore: args.read_git_ignore,
             git_global: args.read_git_global,
             git_exclude: args.read_git_exclude,
+            ignore_files: args.ignore_file,
 
-            same_filesystem: args.same_filesystem,
+            same_filesystem: if args.one_file_system {
+                Some(true)
+            } else {
+                args.same_filesystem
+            },
+            follow_metadata: !args.no_follow_metadata,
 
             print_expression_tree: args.print_expression_tree,
-            print0: args.print0,
+            explain: args.explain,
+            print0: args.print0 || args.null,
+            printf: args.printf.as_deref().map(PrintfTemplate::parse).transpose()?,
+            long: args.long,
+            show_match_lines: args.show_match_lines,
+            absolute_paths: args.absolute_paths,
+            unique: args.unique,
+            max_results_per_dir: args.max_results_per_dir,
+            sort: args.sort,
+            breadth_first: args.breadth_first,
+            ordered_roots: args.ordered_roots,
+            exec: args
+                .exec
+                .map(ExecCommand::new)
+                .or(args.exec_batch.map(ExecCommand::batched)),
+            dry_run: args.dry_run,
+            color: args.color.enabled()
+                && !args.print0
+                && !args.null
+                && args.printf.is_none(),
+            quiet: args.quiet,
+            min_results: args.min_results,
+            invert_exit: args.invert_exit,
+            show_errors: args.show_errors,
+            error_format: args.errors,
+            timeout: args.timeout.as_deref().map(parse_timeout_arg).transpose()?,
+            read_timeout,
+            progress: args.progress,
+            print_stats: args.print_stats,
+            profile: args.profile,
+            find_hardlinks: args.find_hardlinks,
+            search_archives: args.search_archives,
+        })
+    }
+
+    /// Creates a walker over every start directory with the configured ignore rules
+    pub fn walk_builder(&self) -> WalkBuilder {
+        self.walk_builder_for(&self.start_dirs)
+    }
+
+    /// The walks to run one after another: a walk per start directory with
+    /// `ordered_roots`, a single walk of all of them otherwise
+    pub fn walk_builders(&self) -> Vec<WalkBuilder> {
+        if self.ordered_roots {
+            self.start_dirs
+                .iter()
+                .map(|dir| self.walk_builder_for(std::slice::from_ref(dir)))
+                .collect()
+        } else {
+            vec![self.walk_builder()]
+        }
+    }
+
+    fn walk_builder_for(&self, start_dirs: &[PathBuf]) -> WalkBuilder {
+        let mut dir_iter = start_dirs.iter();
+        let mut builder = WalkBuilder::new(dir_iter.next().unwrap());
+        for path in dir_iter {
+            builder.add(path);
+        }
+
+        builder.standard_filters(self.standard_filters);
+        self.hidden.map(|yes| builder.hidden(yes));
+        self.parents.map(|yes| builder.parents(yes));
+        self.ignore.map(|yes| builder.ignore(yes));
+        self.git_ignore.map(|yes| builder.git_ignore(yes));
+        self.git_global.map(|yes| builder.git_global(yes));
+        self.git_exclude.map(|yes| builder.git_exclude(yes));
+        self.same_filesystem.map(|yes| builder.same_file_system(yes));
+        for name in &self.ignore_files {
+            builder.add_custom_ignore_filename(name);
+        }
+
+        builder.threads(self.threads);
+        builder.max_depth(self.max_depth);
+
+        if !self.depth_limits.is_empty() || self.exclude_dirs.is_some() {
+            // every start directory, limited or not, with its depth limit
+            let depth_limits = self
+                .start_dirs
+                .iter()
+                .map(|dir| {
+                    let limit =
+                        self.depth_limits.iter().find(|(limited, _)| limited == dir);
+                    (dir.clone(), limit.map(|(_, max_depth)| *max_depth))
+                })
+                .collect::<Vec<_>>();
+            let exclude_dirs = self.exclude_dirs.clone();
+            builder.filter_entry(move |entry| {
+                // start directories are walked even if their name is excluded
+                let is_excluded = |exclude_dirs: &GlobSet| {
+                    entry.depth() > 0
+                        && entry.file_type().is_some_and(|file_type| file_type.is_dir())
+                        && exclude_dirs.is_match(entry.file_name())
+                };
+                if exclude_dirs.as_ref().is_some_and(is_excluded) {
+                    return false;
+                }
+
+                // an entry belongs to the most specific start directory containing it,
+                // which is not necessarily the one it is walked from
+                let limit = depth_limits
+                    .iter()
+                    .filter(|(dir, _)| entry.path().starts_with(dir))
+                    .max_by_key(|(dir, _)| dir.components().count());
+
+                match limit {
+                    Some((dir, Some(max_depth))) => {
+                        let relative = entry.path().strip_prefix(dir).unwrap();
+                        relative.components().count() <= *max_depth
+                    }
+                    _ => true,
+                }
+            });
+        }
+
+        builder
+    }
+}
+
+/// Parses the command line after `defaults` like `Args::parse_from`, but exits with 1
+/// on invalid arguments, as the help says, instead of clap's 2, which means that some
+/// files could not be read in time. `--help` and `--version` still exit with 0
+fn parse_args(args: Vec<OsString>, defaults: &[String]) -> Args {
+    try_parse_args(args, defaults).unwrap_or_else(|error| {
+        let code = usage_exit_code(&error);
+        if code == 0 {
+            error.exit();
+        }
+        let _ = error.print();
+        std::process::exit(code)
+    })
+}
+
+/// The exit status for a clap error: 0 for the output of `--help` and `--version`
+fn usage_exit_code(error: &clap::Error) -> i32 {
+    if error.use_stderr() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Splits the value of `FGR_DEFAULT_ARGS`, which has to be UTF-8 if it is set
+fn default_args(value: Option<OsString>) -> Result<Vec<String>, GenericError> {
+    let Some(value) = value else {
+        return Ok(vec![]);
+    };
+    let value = value.into_string().map_err(|value| {
+        let value = value.to_string_lossy();
+        GenericError::InvalidDefaultArgs(format!("not valid UTF-8: {value}"))
+    })?;
+    split_default_args(&value)
+}
+
+/// Parses the command line after the arguments from `FGR_DEFAULT_ARGS`, letting
+/// clap override the defaults: an option given in both takes the command line value,
+/// and a default is dropped when the command line gives an option conflicting with
+/// it. Conflicts within the command line itself are still errors.
+fn try_parse_args(args: Vec<OsString>, defaults: &[String]) -> Result<Args, clap::Error> {
+    let Some((program, given)) = args.split_first().filter(|_| !defaults.is_empty())
+    else {
+        return Args::try_parse_from(args);
+    };
+
+    // the overriding command would also let the command line override itself
+    if let Err(error) = Args::try_parse_from(&args) {
+        if error.kind() == ErrorKind::ArgumentConflict {
+            return Err(error);
+        }
+    }
+
+    let args = [program.clone()]
+        .into_iter()
+        .chain(defaults.iter().map(OsString::from))
+        .chain(given.iter().cloned());
+    let mut command = overriding_command();
+    let matches = command.try_get_matches_from_mut(args)?;
+    Args::from_arg_matches(&matches).map_err(|error| error.format(&mut command))
+}
+
+/// The command of `Args` in which a later argument replaces an earlier one that is
+/// the same or conflicts with it, instead of failing
+fn overriding_command() -> Command {
+    let mut command = Args::command().args_override_self(true);
+    command.build();
+
+    let overrides = command
+        .get_arguments()
+        .map(|arg| {
+            let id = arg.get_id();
+            let mut others = command
+                .get_arg_conflicts_with(arg)
+                .into_iter()
+                .map(|other| other.get_id().clone())
+                .collect_vec();
+            // the options of a group conflict with each other
+            for group in command.get_groups() {
+                if !group.clone().is_multiple() && group.get_args().any(|other| other == id) {
+                    others.extend(group.get_args().filter(|other| *other != id).cloned());
+                }
+            }
+            (id.clone(), others)
+        })
+        .collect_vec();
+
+    overrides.into_iter().fold(command, |command, (id, others)| {
+        command.mut_arg(id, |arg| arg.overrides_with_all(others))
+    })
+}
+
+/// Splits arguments like a shell without expansions: whitespace separates them,
+/// quotes group them, and a backslash escapes the next character outside of
+/// single quotes
+fn split_default_args(value: &str) -> Result<Vec<String>, GenericError> {
+    let mut args = vec![];
+    let mut current: Option<String> = None;
+    let mut quote = None;
+
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (Some('\''), ch) => current.get_or_insert_default().push(ch),
+            (_, '\\') => match chars.next() {
+                Some(escaped) => current.get_or_insert_default().push(escaped),
+                None => return Err(GenericError::InvalidDefaultArgs(value.to_string())),
+            },
+            (None, '\'' | '"') => {
+                quote = Some(ch);
+                current.get_or_insert_default();
+            }
+            (None, ch) if ch.is_whitespace() => args.extend(current.take()),
+            (_, ch) => current.get_or_insert_default().push(ch),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(GenericError::InvalidDefaultArgs(value.to_string()));
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+/// Applies the options changing how filters match, like --regex-full-match or
+/// --read-timeout, to every filter of `root`, so a query matches the same in -e and
+/// in --prune-expr
+fn apply_filter_options(
+    root: &mut ExpressionNode<Filter>,
+    args: &Args,
+    read_timeout: Option<Duration>,
+) -> Result<(), GenericError> {
+    if args.regex_full_match {
+        try_for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Name { value, .. }
+            | Filter::Path { value, .. }
+            | Filter::Stem { value, .. }
+            | Filter::Parent { value, .. }
+            | Filter::Extension { value, .. } = filter
+            {
+                *value = value.to_full_match()?;
+            }
+            Ok(())
+        })?;
+    }
+    if !args.glob_literal_separator {
+        try_for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Path { value, .. } = filter {
+                *value = value.with_literal_separator(false)?;
+            }
+            Ok(())
+        })?;
+    }
+    if args.ignore_ext_case {
+        try_for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Extension { value, .. } = filter {
+                *value = value.to_case_insensitive()?;
+            }
+            Ok(())
+        })?;
+    }
+
+    if let Some(limit) = args.contains_max_bytes {
+        for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Contains { max_bytes, .. } = filter {
+                *max_bytes = Some(limit);
+            }
+        });
+    }
+
+    if let Some(timeout) = read_timeout {
+        for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Type { read_timeout, .. }
+            | Filter::Contains { read_timeout, .. }
+            | Filter::Binary { read_timeout, .. } = filter
+            {
+                *read_timeout = Some(timeout);
+            }
+        });
+    }
+
+    if args.io_retries > 0 {
+        for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Type { io_retries, .. }
+            | Filter::Contains { io_retries, .. } = filter
+            {
+                *io_retries = args.io_retries;
+            }
+        });
+    }
+
+    if args.contains_encoding != ContentEncoding::Utf8 {
+        for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Contains { encoding, .. } = filter {
+                *encoding = args.contains_encoding;
+            }
+        });
+    }
+
+    if !args.contains_skip.is_empty() {
+        let contains_skip = ContainsSkip::new(&args.contains_skip)?;
+        for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Contains { skip, .. } = filter {
+                *skip = contains_skip.clone();
+            }
+        });
+    }
+
+    if args.type_by_extension {
+        for_each_filter_mut(root, &mut |filter| {
+            if let Filter::Type { value, comparison, .. } = filter {
+                *filter = Filter::TypeByExtension {
+                    value: value.clone(),
+                    comparison: comparison.clone(),
+                };
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Reads the non-empty lines of `path`, or NUL-separated records, as entry names
+fn read_names(path: &Path, separator: u8) -> std::io::Result<HashSet<OsString>> {
+    let content = std::fs::read(path)?;
+    Ok(content
+        .split(|&byte| byte == separator)
+        // CRLF lists from Windows editors, NUL-separated names are taken as they are
+        .map(|line| match separator {
+            b'\n' => line.strip_suffix(b"\r").unwrap_or(line),
+            _ => line,
         })
+        .filter(|line| !line.is_empty())
+        .map(|line| OsStr::from_bytes(line).to_os_string())
+        .collect())
+}
+
+/// Fails if `dir` does not exist or is not a directory, so a mistyped start
+/// directory is reported instead of silently matching nothing
+fn check_start_dir(dir: &Path) -> Result<(), GenericError> {
+    match std::fs::metadata(dir) {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err(GenericError::StartDirNotADirectory(dir.to_path_buf())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            Err(GenericError::StartDirNotFound(dir.to_path_buf()))
+        }
+        Err(err) => Err(GenericError::StartDirError(dir.to_path_buf(), err)),
+    }
+}
+
+/// Parses every `-e` expression and joins them with `and`. Without `fail_fast`, the
+/// errors of all expressions are reported together.
+fn parse_expressions(
+    expressions: &[String],
+    fail_fast: bool,
+) -> Result<ExpressionNode<Filter>, GenericError> {
+    let mut nodes = vec![];
+    let mut errors = vec![];
+    for expression in expressions {
+        if fail_fast {
+            nodes.push(parse_root(expression)?);
+        } else {
+            match parse_root_all(expression) {
+                Ok(node) => nodes.push(node),
+                Err(expression_errors) => errors.extend(expression_errors),
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(GenericError::MultipleErrors(errors));
+    }
+
+    // `and` is right associative in queries, so `-e a -e b -e c` is `a and (b and c)`
+    let root = nodes.into_iter().rev().reduce(|rest, node| e_and!(node, rest));
+    Ok(root.expect("clap requires at least one expression"))
+}
+
+/// Parses a size flag the same way `size` values are parsed in queries
+fn parse_size_arg(arg: &str) -> Result<usize, GenericError> {
+    match all_consuming(parse_size)(arg) {
+        Ok((_, size)) => Ok(size),
+        Err(_) => Err(GenericError::InvalidSize(arg.to_string())),
+    }
+}
+
+/// Parses a `--size` value: a leading `+` means at least, `-` at most, and no sign
+/// exactly that size
+fn parse_signed_size_arg(arg: &str) -> Result<(Comparison, usize), GenericError> {
+    let (comparison, size) = if let Some(size) = arg.strip_prefix('+') {
+        (Comparison::Gte, size)
+    } else if let Some(size) = arg.strip_prefix('-') {
+        (Comparison::Lte, size)
+    } else {
+        (Comparison::Eq, arg)
+    };
+
+    // find and fd spell the units in lower case, queries do not
+    let size = match size.strip_suffix(['k', 'm', 'g', 't']) {
+        Some(number) => format!("{number}{}", &size[number.len()..].to_uppercase()),
+        None => size.to_string(),
+    };
+
+    let parsed = all_consuming(parse_size)(&size).map(|(_, size)| size);
+    match parsed {
+        Ok(size) => Ok((comparison, size)),
+        Err(_) => Err(GenericError::InvalidSize(arg.to_string())),
+    }
+}
+
+/// Parses the span of `--changed-within` and `--changed-before`, e.g. `2d` or `1 week`
+fn parse_span_arg(arg: &str) -> Result<chrono::Duration, GenericError> {
+    match all_consuming(parse_time_span)(arg) {
+        Ok((_, span)) => Ok(span),
+        Err(_) => Err(GenericError::InvalidDuration(arg.to_string())),
+    }
+}
+
+/// Parses a duration flag with the time units used in queries
+fn parse_timeout_arg(arg: &str) -> Result<Duration, GenericError> {
+    parse_span_arg(arg)?
+        .to_std()
+        .map_err(|_| GenericError::InvalidDuration(arg.to_string()))
+}
+
+/// Splits `DIR:N` into the directory and its depth limit. Anything that does not end
+/// with `:` and a number is a plain directory.
+fn parse_start_dir(arg: &str) -> (PathBuf, Option<usize>) {
+    if let Some((dir, max_depth)) = arg.rsplit_once(':') {
+        if let (false, Ok(max_depth)) = (dir.is_empty(), max_depth.parse()) {
+            return (PathBuf::from(dir), Some(max_depth));
+        }
+    }
+
+    (PathBuf::from(arg), None)
+}
+
+fn for_each_filter_mut(
+    root: &mut ExpressionNode<Filter>,
+    f: &mut impl FnMut(&mut Filter),
+) {
+    match root {
+        ExpressionNode::Leaf(filter) => f(filter),
+        ExpressionNode::And(left, right) | ExpressionNode::Or(left, right) => {
+            for_each_filter_mut(left, f);
+            for_each_filter_mut(right, f);
+        }
+        ExpressionNode::Not(node) => for_each_filter_mut(node, f),
+    }
+}
+
+/// Like [`for_each_filter_mut`], stopping at the first filter `f` fails on
+fn try_for_each_filter_mut(
+    root: &mut ExpressionNode<Filter>,
+    f: &mut impl FnMut(&mut Filter) -> Result<(), GenericError>,
+) -> Result<(), GenericError> {
+    match root {
+        ExpressionNode::Leaf(filter) => f(filter),
+        ExpressionNode::And(left, right) | ExpressionNode::Or(left, right) => {
+            try_for_each_filter_mut(left, f)?;
+            try_for_each_filter_mut(right, f)
+        }
+        ExpressionNode::Not(node) => try_for_each_filter_mut(node, f),
+    }
+}
+
+/// Compiles the `--exclude-dir` globs, matched against directory names
+fn build_exclude_dirs(globs: &[String]) -> Result<GlobSet, GenericError> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let compiled = Glob::new(glob)
+            .map_err(|err| GenericError::InvalidGlob(glob.clone(), err))?;
+        builder.add(compiled);
+    }
+    builder.build().map_err(|err| GenericError::InvalidGlob(globs.join(","), err))
+}
+
+/// Finds a `depth` bound every match has to satisfy: one that is not under `or` or
+/// `not`. The tightest one wins if there are several.
+fn max_depth_bound(root: &ExpressionNode<Filter>) -> Option<usize> {
+    match root {
+        ExpressionNode::Leaf(Filter::Depth { value, comparison }) => match comparison {
+            Comparison::Lte | Comparison::Eq => Some(*value),
+            // depth < 0 matches nothing, not going below the start is as good
+            Comparison::Lt => Some(value.saturating_sub(1)),
+            _ => None,
+        },
+        ExpressionNode::And(left, right) => {
+            match (max_depth_bound(left), max_depth_bound(right)) {
+                (Some(left), Some(right)) => Some(left.min(right)),
+                (left, right) => left.or(right),
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::ffi::{OsStr, OsString};
+    use std::fs::File;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use clap::Parser;
+    use nnf::parse_tree::ExpressionNode;
+    use nnf::traits::Render;
+
+    use crate::config::{
+        default_args, parse_start_dir, split_default_args, try_parse_args,
+        usage_exit_code, Args, Config,
+    };
+    use crate::errors::GenericError;
+    use crate::evaluate::decode::ContentEncoding;
+    use crate::evaluate::NOW;
+    use crate::parse::contains_skip::ContainsSkip;
+    use crate::parse::filter::Filter;
+    use crate::parse::lint::QueryLint;
+    use crate::test_utils::DirEntryMock;
+    use crate::walk::entry_type::EntryType;
+    use crate::walk::WalkedEntry;
+    use crate::Evaluate;
+
+    fn config_from(args: &[&str]) -> Config {
+        let args = Args::try_parse_from(["fgr"].iter().chain(args)).unwrap();
+        Config::from_args(args).unwrap()
+    }
+
+    #[test]
+    fn test_walk_all_start_dirs() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        File::create(first.path().join("first.txt")).unwrap();
+        File::create(second.path().join("second.txt")).unwrap();
+
+        let config = config_from(&[
+            first.path().to_str().unwrap(),
+            second.path().to_str().unwrap(),
+            "-e",
+            "name=*",
+        ]);
+
+        let paths: Vec<PathBuf> = config
+            .walk_builder()
+            .build()
+            .map(|entry| entry.unwrap().into_path())
+            .collect();
+
+        assert!(paths.contains(&first.path().join("first.txt")));
+        assert!(paths.contains(&second.path().join("second.txt")));
+    }
+
+    fn walked_files(dir: &Path, flags: &[&str]) -> BTreeSet<String> {
+        let mut args = vec![dir.to_str().unwrap(), "-e", "name=*"];
+        args.extend(flags);
+
+        config_from(&args)
+            .walk_builder()
+            .build()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_ignore_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "git_ignored.txt").unwrap();
+        std::fs::write(dir.path().join(".ignore"), "ignored.txt").unwrap();
+        for name in [".hidden", "git_ignored.txt", "ignored.txt", "kept.txt"] {
+            File::create(dir.path().join(name)).unwrap();
+        }
+        let files = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        let everything = files(&[
+            ".gitignore",
+            ".ignore",
+            ".hidden",
+            "git_ignored.txt",
+            "ignored.txt",
+            "kept.txt",
+        ]);
+        assert_eq!(walked_files(dir.path(), &[]), everything);
+        assert_eq!(walked_files(dir.path(), &["--no-ignore"]), everything);
+        assert_eq!(
+            walked_files(dir.path(), &["--no-ignore", "--read-ignore", "true"]),
+            everything
+        );
+
+        assert_eq!(walked_files(dir.path(), &["--all"]), files(&["kept.txt"]));
+        assert_eq!(
+            walked_files(dir.path(), &["--all", "--read-git-ignore", "false"]),
+            files(&["git_ignored.txt", "kept.txt"])
+        );
+        assert_eq!(
+            walked_files(dir.path(), &["--all", "--read-ignore", "false"]),
+            files(&["ignored.txt", "kept.txt"])
+        );
+        assert_eq!(
+            walked_files(dir.path(), &["--all", "--ignore-hidden", "false"]),
+            files(&[".gitignore", ".ignore", ".hidden", "kept.txt"])
+        );
+        assert_eq!(
+            walked_files(dir.path(), &["--ignore-hidden", "true"]),
+            files(&["git_ignored.txt", "ignored.txt", "kept.txt"])
+        );
+
+        assert!(Args::try_parse_from(["fgr", "-e", "name=*", "--all", "--no-ignore"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_usage_exit_code() {
+        let code = |args: &[&str]| {
+            let error = Args::try_parse_from(["fgr"].iter().chain(args)).unwrap_err();
+            usage_exit_code(&error)
+        };
+
+        // 2 is left for searches that may have missed files after read timeouts
+        assert_eq!(code(&["--bogus"]), 1);
+        assert_eq!(code(&["-e", "name=*", "--exec"]), 1);
+        assert_eq!(code(&["-e", "name=*", "-Q", "--find-hardlinks"]), 1);
+        assert_eq!(code(&["--help"]), 0);
+        assert_eq!(code(&["--version"]), 0);
+    }
+
+    #[test]
+    fn test_exclude() {
+        let config =
+            config_from(&["-e", "size > 1", "--exclude", "*.log", "--exclude", "*.tmp"]);
+        let inline =
+            config_from(&["-e", "(size > 1 and not name = *.log) and not name = *.tmp"]);
+        assert_eq!(config.expression, inline.expression);
+
+        let file = |name: &str| {
+            DirEntryMock::default()
+                .set_file(name.into())
+                .set_entry_type(EntryType::File)
+                .set_size(2)
+        };
+        assert!(config.root.evaluate(&file("kept.txt")).unwrap());
+        assert!(!config.root.evaluate(&file("excluded.log")).unwrap());
+        assert!(!config.root.evaluate(&file("excluded.tmp")).unwrap());
+
+        let args =
+            Args::try_parse_from(["fgr", "-e", "size > 1", "--exclude", "[a"]).unwrap();
+        let error = Config::from_args(args).unwrap_err();
+        assert!(matches!(error, GenericError::InvalidGlob(..)), "{error:?}");
+    }
+
+    #[test]
+    fn test_exclude_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in ["node_modules/left-pad", "src/target", ".git/objects"] {
+            std::fs::create_dir_all(dir.path().join(path)).unwrap();
+        }
+        File::create(dir.path().join("node_modules/left-pad/sentinel")).unwrap();
+        File::create(dir.path().join("src/main.rs")).unwrap();
+        // a file is not a directory, so its name is never excluded
+        File::create(dir.path().join("src/node_modules")).unwrap();
+
+        let dir_arg = dir.path().to_str().unwrap();
+        let config = config_from(&[
+            dir_arg,
+            "-e",
+            "name=*",
+            "--exclude-dir",
+            "node_modules,targ*",
+            "--exclude-dir",
+            ".git",
+        ]);
+        let mut visited = vec![];
+        let walk = config.walk_builder().sort_by_file_name(Ord::cmp).build();
+        for entry in walk {
+            let entry = entry.unwrap();
+            assert!(entry.file_name() != "sentinel", "descended into node_modules");
+            visited.push(entry.path().strip_prefix(dir.path()).unwrap().to_path_buf());
+        }
+        assert_eq!(
+            visited,
+            ["", "src", "src/main.rs", "src/node_modules"].map(PathBuf::from)
+        );
+
+        // the start directory itself is walked
+        let start = dir.path().join("node_modules");
+        let config = config_from(&[
+            start.to_str().unwrap(),
+            "-e",
+            "name=*",
+            "--exclude-dir",
+            "node_modules",
+        ]);
+        assert_eq!(config.walk_builder().build().count(), 3);
+
+        let args =
+            Args::try_parse_from(["fgr", "-e", "name=*", "--exclude-dir", "[a"]).unwrap();
+        let error = Config::from_args(args).unwrap_err();
+        assert!(matches!(error, GenericError::InvalidGlob(..)), "{error:?}");
+    }
+
+    #[test]
+    fn test_follow_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        std::fs::create_dir(&target).unwrap();
+        File::create(target.join("file.txt")).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let link_arg = format!("{}:1", link.display());
+        let config = config_from(&[&link_arg, "-e", "name=*", "--follow-roots"]);
+        let target = target.canonicalize().unwrap();
+        assert_eq!(config.start_dirs, std::slice::from_ref(&target));
+        assert_eq!(config.depth_limits, [(target.clone(), 1)]);
+
+        let paths: BTreeSet<PathBuf> = config
+            .walk_builder()
+            .build()
+            .map(|entry| entry.unwrap().into_path())
+            .collect();
+        assert_eq!(paths, BTreeSet::from([target.clone(), target.join("file.txt")]));
+
+        let config = config_from(&[link.to_str().unwrap(), "-e", "name=*"]);
+        assert_eq!(config.start_dirs, [link]);
+
+        let missing = dir.path().join("missing");
+        let args = [missing.to_str().unwrap(), "-e", "name=*", "--follow-roots"];
+        let error =
+            Config::from_args(Args::try_parse_from(["fgr"].iter().chain(&args)).unwrap())
+                .unwrap_err();
+        assert!(matches!(error, GenericError::StartDirError(path, _) if path == missing));
+    }
+
+    #[test]
+    fn test_name_substring_without_expression() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["foo.txt", "my_foo_bar.rs", "bar.txt", "f*o"] {
+            File::create(dir.path().join(name)).unwrap();
+        }
+
+        let matched = |args: &[&str]| {
+            let config = config_from(args);
+            let names = config
+                .walk_builder()
+                .build()
+                .map(|entry| entry.unwrap())
+                .filter(|entry| {
+                    config.root.evaluate(&WalkedEntry::new(entry, true)).unwrap()
+                })
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect::<BTreeSet<_>>();
+            (config.start_dirs, names)
+        };
+
+        let start_dir = dir.path().to_str().unwrap();
+        assert_eq!(
+            matched(&["foo", start_dir]),
+            (
+                vec![dir.path().to_path_buf()],
+                BTreeSet::from(["foo.txt".to_string(), "my_foo_bar.rs".to_string()])
+            )
+        );
+        // glob characters are literal
+        assert_eq!(matched(&["*", start_dir]).1, BTreeSet::from(["f*o".to_string()]));
+
+        // alone, the pattern is searched for in the current directory
+        let config = config_from(&["foo"]);
+        assert_eq!(config.start_dirs, [std::env::current_dir().unwrap()]);
+
+        // with -e, every positional is a directory
+        let config = config_from(&[start_dir, "-e", "name = foo.txt"]);
+        assert_eq!(config.start_dirs, [dir.path()]);
+    }
+
+    #[test]
+    fn test_default_args() {
+        assert_eq!(
+            split_default_args(
+                r#" --threads 2  --exclude '*.log' -e "name = a b" x\ y "" "#
+            )
+            .unwrap(),
+            ["--threads", "2", "--exclude", "*.log", "-e", "name = a b", "x y", ""]
+        );
+        assert!(split_default_args("--exclude '*.log").is_err());
+        assert!(split_default_args("trailing\\").is_err());
+
+        let value = r"--threads 3 --exclude '*.log' --stats -lz";
+        let defaults = default_args(Some(value.into())).unwrap();
+        assert!(default_args(Some(OsStr::from_bytes(b"--stats \xff").into())).is_err());
+        assert!(default_args(None).unwrap().is_empty());
+
+        let parse = |args: &[&str], defaults: &str| {
+            let args = ["fgr", "-e", "name=*"].iter().chain(args).map(OsString::from);
+            try_parse_args(args.collect(), &split_default_args(defaults).unwrap())
+        };
+        let config = |args: &[&str], defaults: &[String]| {
+            let args = ["fgr", "-e", "name=*"].iter().chain(args).map(OsString::from);
+            Config::from_args(try_parse_args(args.collect(), defaults).unwrap()).unwrap()
+        };
+
+        let config_with_defaults = config(&[], &defaults);
+        assert_eq!(config_with_defaults.threads, 3);
+        assert!(config_with_defaults.print_stats);
+        assert!(config_with_defaults.long && config_with_defaults.print0);
+        assert!(matches!(config_with_defaults.root, ExpressionNode::And(..)));
+
+        // the command line wins, also over the defaults conflicting with it
+        let overridden = config(&["-t5", "--printf", "%p"], &defaults);
+        assert_eq!(overridden.threads, 5);
+        assert!(overridden.printf.is_some() && !overridden.long && overridden.print0);
+        assert!(overridden.print_stats);
+
+        // options given many times add up
+        let args = parse(&["--exclude", "*.tmp"], "--exclude '*.log' -t 2").unwrap();
+        assert_eq!(args.exclude, ["*.log", "*.tmp"]);
+        assert_eq!(args.threads, 2);
+        let args = parse(&["--exec-batch", "rm", "+"], "--exec echo {} ; -H").unwrap();
+        assert_eq!(args.exec, None);
+        assert_eq!(args.exec_batch.unwrap(), ["rm"]);
+        assert!(args.hidden);
+
+        // conflicts and repeats on the command line are still errors
+        assert!(parse(&["-t", "2", "-t", "3"], "--stats").is_err());
+        assert!(parse(&["-l", "--printf", "%p"], "--stats").is_err());
+        assert!(parse(&["-Q", "--find-hardlinks"], "-t 2").is_err());
+        assert!(parse(&["--bogus"], "-t 2").is_err());
+
+        assert_eq!(config(&[], &[]).threads, num_cpus::get());
+    }
+
+    #[test]
+    fn test_missing_start_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        let file = dir.path().join("file.txt");
+        File::create(&file).unwrap();
+
+        let error = |start_dir: &Path| {
+            let args = ["fgr", start_dir.to_str().unwrap(), "-e", "name=*"];
+            Config::from_args(Args::try_parse_from(args).unwrap()).unwrap_err()
+        };
+        assert!(
+            matches!(error(&missing), GenericError::StartDirNotFound(path) if path == missing)
+        );
+        assert!(
+            matches!(error(&file), GenericError::StartDirNotADirectory(path) if path == file)
+        );
+        // with a depth limit, the path without it is checked
+        let error = error(Path::new(&format!("{}:1", missing.display())));
+        assert!(matches!(error, GenericError::StartDirNotFound(path) if path == missing));
+
+        // no start directory is still the current one
+        let config = config_from(&["-e", "name=*"]);
+        assert_eq!(config.start_dirs, [std::env::current_dir().unwrap()]);
+    }
+
+    #[test]
+    fn test_hidden() {
+        let hidden = |args: &[&str]| config_from(args).hidden;
+
+        assert_eq!(hidden(&["-e", "name=*"]), None);
+        assert_eq!(hidden(&["-e", "name=*", "--hidden"]), Some(false));
+        assert_eq!(
+            hidden(&["-e", "name=*", "-H", "--ignore-hidden", "false"]),
+            Some(false)
+        );
+        assert_eq!(hidden(&["-e", "name=*", "--ignore-hidden", "true"]), Some(true));
+        assert_eq!(hidden(&["-e", "name=*", "-a", "-H"]), Some(false));
+
+        let args = ["fgr", "-e", "name=*", "-H", "--ignore-hidden", "true"];
+        let error = Config::from_args(Args::try_parse_from(args).unwrap()).unwrap_err();
+        assert!(matches!(error, GenericError::ConflictingFlags(_)), "{error:?}");
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+        let walked = |args: &[&str]| {
+            let root = dir.path().to_str().unwrap();
+            let args =
+                ["fgr", root, "-e", "name=*"].into_iter().chain(args.iter().copied());
+            let config = Config::from_args(Args::try_parse_from(args).unwrap()).unwrap();
+            config.walk_builder().build().count()
+        };
+        assert_eq!(walked(&["--all"]), 1);
+        assert_eq!(walked(&["--all", "--hidden"]), 2);
+    }
+
+    #[test]
+    fn test_one_file_system() {
+        let same_filesystem = |args: &[&str]| config_from(args).same_filesystem;
+
+        assert_eq!(same_filesystem(&["-e", "name=*"]), None);
+        assert_eq!(
+            same_filesystem(&["-e", "name=*", "--same-filesystem", "true"]),
+            Some(true)
+        );
+        assert_eq!(same_filesystem(&["-e", "name=*", "--one-file-system"]), Some(true));
+        assert_eq!(same_filesystem(&["-e", "name=*", "-x"]), Some(true));
+        assert_eq!(
+            same_filesystem(&["-e", "name=*", "--same-filesystem", "false"]),
+            Some(false)
+        );
+
+        let args = ["fgr", "-e", "name=*", "-x", "--same-filesystem", "false"];
+        assert!(Args::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_ignore_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".fgrignore"), "*.log\n").unwrap();
+        File::create(dir.path().join("kept.txt")).unwrap();
+        File::create(dir.path().join("ignored.log")).unwrap();
+
+        let names = |args: &[&str]| {
+            let start_dir = dir.path().to_str().unwrap();
+            let config = config_from(&[&[start_dir, "-e", "name=*"], args].concat());
+            config
+                .walk_builder()
+                .build()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".log") || name.ends_with(".txt"))
+                .collect::<BTreeSet<_>>()
+        };
+
+        assert_eq!(names(&[]), BTreeSet::from(["ignored.log".into(), "kept.txt".into()]));
+        assert_eq!(
+            names(&["--ignore-file", ".fgrignore"]),
+            BTreeSet::from(["kept.txt".into()])
+        );
+    }
+
+    #[test]
+    fn test_parse_start_dir() {
+        assert_eq!(parse_start_dir("/var/log:1"), ("/var/log".into(), Some(1)));
+        assert_eq!(parse_start_dir("projects:0"), ("projects".into(), Some(0)));
+        assert_eq!(parse_start_dir("/var/log"), ("/var/log".into(), None));
+        assert_eq!(parse_start_dir("a:b"), ("a:b".into(), None));
+        assert_eq!(parse_start_dir("a:b:2"), ("a:b".into(), Some(2)));
+        assert_eq!(parse_start_dir(":2"), (":2".into(), None));
+        assert_eq!(parse_start_dir("a:-1"), ("a:-1".into(), None));
+    }
+
+    #[test]
+    fn test_depth_limits() {
+        let shallow = tempfile::tempdir().unwrap();
+        let deep = tempfile::tempdir().unwrap();
+        for dir in [shallow.path(), deep.path()] {
+            std::fs::create_dir_all(dir.join("a").join("b")).unwrap();
+            File::create(dir.join("a").join("b").join("file.txt")).unwrap();
+        }
+
+        let shallow_arg = format!("{}:1", shallow.path().display());
+        let config =
+            config_from(&[&shallow_arg, deep.path().to_str().unwrap(), "-e", "name=*"]);
+        assert_eq!(config.start_dirs, [shallow.path(), deep.path()]);
+
+        let paths: BTreeSet<PathBuf> = config
+            .walk_builder()
+            .build()
+            .map(|entry| entry.unwrap().into_path())
+            .collect();
+
+        assert!(paths.contains(&shallow.path().join("a")));
+        assert!(!paths.contains(&shallow.path().join("a").join("b")));
+        assert!(paths.contains(&deep.path().join("a").join("b").join("file.txt")));
+    }
+
+    #[test]
+    fn test_nested_depth_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, c) = (dir.path().join("a"), dir.path().join("a/b/c"));
+        std::fs::create_dir_all(c.join("d")).unwrap();
+        File::create(c.join("d/file.txt")).unwrap();
+
+        let paths = |args: &[&str]| {
+            let config = config_from(&[args, &["-e", "name=*"]].concat());
+            config
+                .walk_builder()
+                .build()
+                .map(|entry| entry.unwrap().into_path())
+                .collect::<BTreeSet<_>>()
+        };
+
+        // a is also walked from its parent, yet limited relative to itself
+        let a_arg = format!("{}:1", a.display());
+        let walked = paths(&[dir.path().to_str().unwrap(), &a_arg]);
+        assert!(walked.contains(&a.join("b")));
+        assert!(!walked.contains(&c));
+
+        // c is under the limited one, but the most specific start dir has no limit
+        let root_arg = format!("{}:1", dir.path().display());
+        let walked = paths(&[&root_arg, c.to_str().unwrap()]);
+        assert!(walked.contains(&c.join("d/file.txt")));
+        assert!(!walked.contains(&a.join("b")));
+    }
+
+    #[test]
+    fn test_literal_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let literal = dir.path().join("foo:3");
+        std::fs::create_dir(&literal).unwrap();
+        let literal_arg = literal.to_str().unwrap();
+
+        let config = config_from(&[literal_arg, "--literal-dirs", "-e", "name=*"]);
+        assert_eq!(config.start_dirs, std::slice::from_ref(&literal));
+        assert!(config.depth_limits.is_empty());
+
+        let args = Args::try_parse_from(["fgr", literal_arg, "-e", "name=*"]).unwrap();
+        assert!(matches!(
+            Config::from_args(args),
+            Err(GenericError::StartDirNotFound(..))
+        ));
+    }
+
+    #[test]
+    fn test_size_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("small"), [0; 500]).unwrap();
+        std::fs::write(dir.path().join("large"), [0; 2000]).unwrap();
+
+        let matched = |flags: &[&str]| {
+            let mut args = vec![dir.path().to_str().unwrap(), "-e", "name=*"];
+            args.extend(flags);
+            let config = config_from(&args);
+
+            config
+                .walk_builder()
+                .build()
+                .map(|entry| entry.unwrap())
+                .filter(|entry| {
+                    config.root.evaluate(&WalkedEntry::new(entry, true)).unwrap_or(false)
+                })
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matched(&["--min-size", "1Kb"]), vec!["large"]);
+        assert_eq!(matched(&["--max-size", "1K"]), vec!["small"]);
+        assert_eq!(matched(&["--min-size", "1Kb", "--max-size", "1Mb"]), vec!["large"]);
+
+        let args =
+            Args::try_parse_from(["fgr", "-e", "name=*", "--min-size", "1 lightyear"])
+                .unwrap();
+        assert!(matches!(Config::from_args(args), Err(GenericError::InvalidSize(_))));
+    }
+
+    #[test]
+    fn test_signed_size_flag() {
+        let matches = |flags: &[&str], size| {
+            let mut args = vec!["-e", "name=*"];
+            args.extend(flags);
+            let entry = DirEntryMock::default()
+                .set_file("file".into())
+                .set_entry_type(EntryType::File)
+                .set_size(size);
+            config_from(&args).root.evaluate(&entry).unwrap()
+        };
+
+        assert!(matches(&["--size", "+1M"], 1_000_000));
+        assert!(matches(&["--size", "+1M"], 5_000_000));
+        assert!(!matches(&["--size", "+1M"], 999_999));
+
+        assert!(matches(&["--size", "-10k"], 10_000));
+        assert!(!matches(&["--size", "-10k"], 10_001));
+
+        assert!(matches(&["--size", "1M"], 1_000_000));
+        assert!(!matches(&["--size", "1M"], 1_000_001));
+
+        let range = ["--size", "+1k", "--size", "-2k"];
+        assert!(matches(&range, 1500));
+        assert!(!matches(&range, 2500));
+
+        let args = Args::try_parse_from(["fgr", "-e", "name=*", "--size", "+1 parsec"]);
+        let error = Config::from_args(args.unwrap()).unwrap_err();
+        assert!(matches!(error, GenericError::InvalidSize(size) if size == "+1 parsec"));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let max_depth = |expression: &str| config_from(&["-e", expression]).max_depth;
+
+        assert_eq!(max_depth("depth <= 2 and name=foo"), Some(2));
+        assert_eq!(max_depth("name=foo and (depth < 3 and size > 1)"), Some(2));
+        assert_eq!(max_depth("depth <= 4 and depth = 3"), Some(3));
+        assert_eq!(max_depth("depth < 0"), Some(0));
+
+        assert_eq!(max_depth("depth <= 2 or name=foo"), None);
+        assert_eq!(max_depth("name=foo and not (depth > 2 and size > 1)"), None);
+        // negations are pushed down to the leaves first
+        assert_eq!(max_depth("name=foo and not (depth > 2 or size > 1)"), Some(2));
+        assert_eq!(max_depth("depth >= 2 and name=foo"), None);
+        assert_eq!(max_depth("name=foo"), None);
+
+        // the walk stops at the bound
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        let config = config_from(&["-e", "depth <= 2", dir.path().to_str().unwrap()]);
+        let depths = config
+            .walk_builder()
+            .build()
+            .map(|entry| entry.unwrap().depth())
+            .collect::<Vec<_>>();
+        assert_eq!(depths, [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_file_type() {
+        let entry_types = [
+            ("f", EntryType::File),
+            ("d", EntryType::Dir),
+            ("l", EntryType::Symlink),
+            ("s", EntryType::Socket),
+            ("p", EntryType::FIFO),
+            ("b", EntryType::BlockDevice),
+            ("c", EntryType::CharDevice),
+        ];
+        let matches = |flags: &[&str], entry_type: &EntryType| {
+            let mut args = vec!["-e", "name=*"];
+            args.extend(flags);
+            let entry = DirEntryMock::default()
+                .set_file("entry".into())
+                .set_entry_type(entry_type.clone());
+            config_from(&args).root.evaluate(&entry).unwrap()
+        };
+
+        for (letter, entry_type) in &entry_types {
+            for (other_letter, other_type) in &entry_types {
+                let flags = ["--file-type", other_letter];
+                assert_eq!(matches(&flags, entry_type), letter == other_letter);
+                assert!(matches(&flags, other_type));
+            }
+        }
+
+        let either = ["--file-type", "f", "--file-type", "l"];
+        assert!(matches(&either, &EntryType::File));
+        assert!(matches(&either, &EntryType::Symlink));
+        assert!(!matches(&either, &EntryType::Dir));
+
+        let args = Args::try_parse_from(["fgr", "-e", "name=*", "--file-type", "x"]);
+        let error = Config::from_args(args.unwrap()).unwrap_err();
+        assert!(matches!(error, GenericError::InvalidFileType(letter) if letter == "x"));
+    }
+
+    #[test]
+    fn test_null() {
+        let config = config_from(&["-e", "name=*"]);
+        assert!(!config.print0);
+        assert_eq!(config.input_separator, b'\n');
+
+        // -p only changes the output
+        let config = config_from(&["-e", "name=*", "-p"]);
+        assert!(config.print0);
+        assert_eq!(config.input_separator, b'\n');
+
+        for flag in ["-z", "--null"] {
+            let config = config_from(&["-e", "name=*", flag]);
+            assert!(config.print0);
+            assert_eq!(config.input_separator, b'\0');
+            assert!(!config.color);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let names = dir.path().join("names");
+        std::fs::write(&names, "with\nnewline\0plain\0").unwrap();
+        let config =
+            config_from(&["-e", "name=*", "-z", "--names-from", names.to_str().unwrap()]);
+        let entry = |name: &str| DirEntryMock::default().set_file(name.into());
+        assert!(config.root.evaluate(&entry("with\nnewline")).unwrap());
+        assert!(config.root.evaluate(&entry("plain")).unwrap());
+        assert!(!config.root.evaluate(&entry("with")).unwrap());
+    }
+
+    #[test]
+    fn test_names_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        std::fs::create_dir_all(tree.join("nested")).unwrap();
+        for path in ["Cargo.toml", "nested/main.rs", "nested/lib.rs", "nested/Cargo.lock"]
+        {
+            File::create(tree.join(path)).unwrap();
+        }
+        let names = dir.path().join("names.txt");
+        std::fs::write(&names, "main.rs\r\n\nCargo.toml\nmissing.txt\n").unwrap();
+
+        let config = config_from(&[
+            tree.to_str().unwrap(),
+            "-e",
+            "name=*",
+            "--names-from",
+            names.to_str().unwrap(),
+        ]);
+        let matched = config
+            .walk_builder()
+            .build()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| config.root.evaluate(&WalkedEntry::new(entry, true)).unwrap())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(matched, BTreeSet::from(["Cargo.toml".into(), "main.rs".into()]));
+
+        let missing = dir.path().join("missing.txt");
+        let args = ["fgr", "-e", "name=*", "--names-from", missing.to_str().unwrap()];
+        let error = Config::from_args(Args::try_parse_from(args).unwrap()).unwrap_err();
+        assert!(
+            matches!(error, GenericError::ReferenceFileError(path, _) if path == missing)
+        );
+    }
+
+    #[test]
+    fn test_no_future() {
+        let hour = Duration::from_secs(60 * 60);
+        let entry = |mtime| DirEntryMock::default().set_file("a".into()).set_mtime(mtime);
+        let matches =
+            |args: &[&str], mtime| config_from(args).root.evaluate(&entry(mtime));
+
+        let query = ["-e", "mtime > now - 1d"];
+        let no_future = ["-e", "mtime > now - 1d", "--no-future"];
+        assert!(matches(&query, *NOW + hour).unwrap());
+        assert!(!matches(&no_future, *NOW + hour).unwrap());
+        assert!(matches(&no_future, *NOW - hour).unwrap());
+        assert!(matches(
+            &["-e", "name=*", "--changed-within", "1d", "--no-future"],
+            *NOW
+        )
+        .unwrap());
+
+        // no bound is added for times the query does not compare, so they are not read
+        let config = config_from(&["-e", "name=*", "--no-future"]);
+        assert_eq!(config.root, config_from(&["-e", "name=*"]).root);
+        let config = config_from(&["-e", "atime >= now - 1d", "--no-future"]);
+        assert_eq!(config.root, config_from(&["-e", "atime within 1d"]).root);
+    }
+
+    #[test]
+    fn test_changed_flags() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let entry = |age: u32| DirEntryMock::default().set_mtime(*NOW - day * age);
+        let matches = |flags: &[&str], age| {
+            let mut args = vec!["-e", "name=*"];
+            args.extend(flags);
+            config_from(&args).root.evaluate(&entry(age).set_file("a".into())).unwrap()
+        };
+
+        assert!(matches(&["--changed-within", "2d"], 1));
+        assert!(matches(&["--changed-within", "2d"], 2));
+        assert!(!matches(&["--changed-within", "2d"], 3));
+
+        assert!(!matches(&["--changed-before", "1w"], 6));
+        assert!(matches(&["--changed-before", "1w"], 7));
+        assert!(matches(&["--changed-before", "1 week"], 30));
+
+        let both = ["--changed-within", "30d", "--changed-before", "1w"];
+        assert!(!matches(&both, 1));
+        assert!(matches(&both, 10));
+        assert!(!matches(&both, 31));
+
+        let args = Args::try_parse_from(["fgr", "-e", "name=*", "--changed-within", "2"]);
+        assert!(matches!(
+            Config::from_args(args.unwrap()),
+            Err(GenericError::InvalidDuration(_))
+        ));
+    }
+
+    #[test]
+    fn test_multiple_expressions() {
+        let config = config_from(&["-e", "size > 1Mb", "-e", "type = vid"]);
+        let inline = config_from(&["-e", "size > 1Mb and type = vid"]);
+        assert_eq!(config.expression, inline.expression);
+        assert_eq!(config.root, inline.root);
+
+        let config = config_from(&[
+            "-e",
+            "name = a or name = b",
+            "-e",
+            "ext = rs",
+            "-e",
+            "depth < 2",
+        ]);
+        let inline =
+            config_from(&["-e", "(name = a or name = b) and ext = rs and depth < 2"]);
+        assert_eq!(config.expression, inline.expression);
+
+        // the lints see every expression grouped on its own too
+        let config = config_from(&["-e", "name = a", "-e", "name != a or depth > 1"]);
+        assert!(config.lints.is_empty(), "{:?}", config.lints);
+        let ungrouped = config_from(&["-e", "name = a and name != a or depth > 1"]);
+        assert!(matches!(ungrouped.lints[..], [QueryLint::AlwaysFalse(_)]));
+
+        let args = ["fgr", "-e", "colour = red", "-e", "name = a", "-e", "size > 10Qb"];
+        let args = Args::try_parse_from(args.iter().chain(&["--fail-fast", "false"]));
+        match Config::from_args(args.unwrap()).unwrap_err() {
+            GenericError::MultipleErrors(errors) => assert_eq!(errors.len(), 2),
+            error => panic!("{error:?}"),
+        }
+
+        let error = Args::try_parse_from(["fgr"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+    }
+
+    #[test]
+    fn test_exclude_empty() {
+        let config = config_from(&["-e", "name=*", "--exclude-empty"]);
+        let file = |size| {
+            DirEntryMock::default()
+                .set_file("sample".into())
+                .set_entry_type(EntryType::File)
+                .set_size(size)
+        };
+
+        assert!(!config.root.evaluate(&file(0)).unwrap());
+        assert!(config.root.evaluate(&file(1)).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let entry = DirEntryMock::default()
+            .set_file(dir.path().to_path_buf())
+            .set_entry_type(EntryType::Dir);
+        assert!(config.root.evaluate(&entry).unwrap());
+
+        let config = config_from(&["-e", "name=*"]);
+        assert!(config.root.evaluate(&file(0)).unwrap());
+    }
+
+    #[test]
+    fn test_render_parsed_expression() {
+        let config = config_from(&["-e", "not (name=a or size > 1B) and depth < 2"]);
+        let dot = config.expression.render();
+
+        assert_eq!(
+            config.expression.to_string(),
+            "(¬(name = 'a' ∨ size > 1B) ∧ depth < 2)"
+        );
+        for label in ["AND", "OR", "!", "name = 'a'", "size > 1B", "depth < 2"] {
+            assert!(dot.contains(&format!("label=\"{label}\"")), "{label}: {dot}");
+        }
+        for edge in ["0 -> 1", "1 -> 2", "2 -> 3", "2 -> 4", "0 -> 5"] {
+            assert!(dot.contains(edge), "{edge}: {dot}");
+        }
+    }
+
+    #[test]
+    fn test_strict_query() {
+        for query in ["size > 100", "contains = *a*", "name = a and name != a"] {
+            let config = config_from(&["-e", query]);
+            assert_eq!(config.lints.len(), 1, "{query}");
+
+            let args =
+                Args::try_parse_from(["fgr", "-e", query, "--strict-query"]).unwrap();
+            let error = Config::from_args(args).unwrap_err();
+            assert!(matches!(error, GenericError::StrictQueryError(_)), "{error:?}");
+        }
+
+        let config = config_from(&["-e", "size > 1Kb", "--strict-query"]);
+        assert!(config.lints.is_empty());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let config = config_from(&["-e", "name=*", "--timeout", "30s"]);
+        assert_eq!(config.timeout, Some(Duration::from_secs(30)));
+
+        let config = config_from(&["-e", "name=*", "--timeout", "2 min"]);
+        assert_eq!(config.timeout, Some(Duration::from_secs(120)));
+
+        let config = config_from(&["-e", "name=*"]);
+        assert_eq!(config.timeout, None);
+
+        for timeout in ["soon", "99999999999999w"] {
+            let args =
+                Args::try_parse_from(["fgr", "-e", "name=*", "--timeout", timeout]);
+            assert!(matches!(
+                Config::from_args(args.unwrap()),
+                Err(GenericError::InvalidDuration(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        let config = config_from(&["-e", "name=*", "-t", "0"]);
+        assert_eq!(config.threads, num_cpus::get());
+
+        let config = config_from(&["-e", "name=*", "--threads", "3"]);
+        assert_eq!(config.threads, 3);
+
+        let args = Args::try_parse_from(["fgr", "-e", "name=*", "-t", "100000"]).unwrap();
+        let error = Config::from_args(args).unwrap_err();
+        assert!(
+            matches!(error, GenericError::InvalidThreadCount(100000, _)),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let query = "colour = red and size > 10Qb";
+
+        let args = Args::try_parse_from(["fgr", "-e", query]).unwrap();
+        let error = Config::from_args(args).unwrap_err();
+        assert!(!matches!(error, GenericError::MultipleErrors(_)), "{error:?}");
+
+        let args =
+            Args::try_parse_from(["fgr", "-e", query, "--fail-fast", "false"]).unwrap();
+        match Config::from_args(args).unwrap_err() {
+            GenericError::MultipleErrors(errors) => assert_eq!(errors.len(), 2),
+            error => panic!("{error:?}"),
+        }
+    }
+
+    #[test]
+    fn test_type_by_extension() {
+        let config = config_from(&["-e", "type = vid", "--type-by-extension"]);
+        let entry = DirEntryMock::default()
+            .set_file("/definitely/missing/foo.mp4".into())
+            .set_entry_type(EntryType::File);
+
+        assert!(config.root.evaluate(&entry).unwrap());
+
+        let config = config_from(&["-e", "type = vid"]);
+        assert!(config.root.evaluate(&entry).is_err());
+    }
+
+    #[test]
+    fn test_ignore_ext_case() {
+        let entry = DirEntryMock::default().set_file("photo.JPG".into());
+
+        let config = config_from(&["-e", "ext = jpg"]);
+        assert!(!config.root.evaluate(&entry).unwrap());
+
+        let config = config_from(&["-e", "ext = jpg", "--ignore-ext-case"]);
+        assert!(config.root.evaluate(&entry).unwrap());
+
+        let config = config_from(&["-e", "ext = r'^jp.$'", "--ignore-ext-case"]);
+        assert!(config.root.evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_prune_expr_filter_options() {
+        let entry = DirEntryMock::default().set_file("photo.JPG".into());
+
+        let config = config_from(&["-e", "name = *", "--prune-expr", "ext = jpg"]);
+        assert!(!config.prune.unwrap().evaluate(&entry).unwrap());
+
+        let config = config_from(&[
+            "-e",
+            "name = *",
+            "--prune-expr",
+            "ext = jpg",
+            "--ignore-ext-case",
+        ]);
+        assert!(config.prune.unwrap().evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_contains_max_bytes() {
+        let config =
+            config_from(&["-e", "contains = needle", "--contains-max-bytes", "64"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Contains { max_bytes: Some(64), .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let timeout = Some(Duration::from_secs(5));
+        let config = config_from(&["-e", "contains = needle", "--read-timeout", "5s"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Contains { read_timeout, .. }) if *read_timeout == timeout
+        ));
+
+        let config = config_from(&["-e", "text", "--read-timeout", "5s"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Binary { read_timeout, .. }) if *read_timeout == timeout
+        ));
+
+        let config = config_from(&["-e", "type = text"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Type { read_timeout: None, .. })
+        ));
+    }
+
+    #[test]
+    fn test_io_retries() {
+        let config = config_from(&["-e", "contains = needle", "--io-retries", "3"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Contains { io_retries: 3, .. })
+        ));
+
+        let config = config_from(&["-e", "type = text", "--io-retries", "3"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Type { io_retries: 3, .. })
+        ));
+
+        let config = config_from(&["-e", "contains = needle"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Contains { io_retries: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_contains_encoding() {
+        let config =
+            config_from(&["-e", "contains = *a*", "--contains-encoding", "utf16le"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Contains {
+                encoding: ContentEncoding::Utf16le,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_contains_skip() {
+        let skip_of = |args: &[&str]| match config_from(args).root {
+            ExpressionNode::Leaf(Filter::Contains { skip, .. }) => skip,
+            root => panic!("{root}"),
+        };
+
+        assert_eq!(skip_of(&["-e", "contains = *a*"]), ContainsSkip::default());
+
+        let skip = skip_of(&["-e", "contains = *a*", "--contains-skip", "/sys/**"]);
+        assert!(skip.is_match(Path::new("/sys/kernel/notes")));
+        assert!(!skip.is_match(Path::new("/proc/kcore")));
+
+        assert!(skip_of(&["-e", "contains = *a*", "--contains-skip", ""]).is_empty());
+    }
+
+    #[test]
+    fn test_glob_literal_separator() {
+        let matches = |args: &[&str], path: &str| {
+            let entry = DirEntryMock::default()
+                .set_file(path.into())
+                .set_entry_type(EntryType::File);
+            config_from(args).root.evaluate(&entry).unwrap()
+        };
+
+        let query = ["-e", "path = 'src/*.rs'"];
+        assert!(matches(&query, "src/a.rs"));
+        assert!(!matches(&query, "src/b/c.rs"));
+        assert!(matches(&["-e", "path = 'src/**/*.rs'"], "src/b/c.rs"));
+        assert!(matches(&["-e", "path ~ SRC/B"], "src/b/c.rs"));
+
+        let crossing = ["-e", "path = 'src/*.rs'", "--glob-literal-separator", "false"];
+        assert!(matches(&crossing, "src/a.rs"));
+        assert!(matches(&crossing, "src/b/c.rs"));
+
+        // names have no separator to stop at
+        assert!(matches(&["-e", "name = '*.rs'"], "src/b/c.rs"));
+    }
+
+    #[test]
+    fn test_path_below_start_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/b")).unwrap();
+        File::create(dir.path().join("src/a.rs")).unwrap();
+        File::create(dir.path().join("src/b/c.rs")).unwrap();
+
+        let matched = |query: &str| {
+            let config = config_from(&[dir.path().to_str().unwrap(), "-e", query]);
+            let mut matched = config
+                .walk_builder()
+                .build()
+                .map(|entry| entry.unwrap())
+                .filter(|entry| {
+                    config.root.evaluate(&WalkedEntry::new(entry, true)).unwrap()
+                })
+                .map(|entry| entry.path().strip_prefix(dir.path()).unwrap().to_path_buf())
+                .collect::<Vec<_>>();
+            matched.sort();
+            matched
+        };
+
+        assert_eq!(matched("path = 'src/*.rs'"), [PathBuf::from("src/a.rs")]);
+        assert_eq!(
+            matched("path = 'src/**/*.rs' and path != 'src/*.rs'"),
+            [PathBuf::from("src/b/c.rs")]
+        );
+        assert_eq!(matched("path = r'^src/b$'"), [PathBuf::from("src/b")]);
+    }
+
+    #[test]
+    fn test_regex_full_match() {
+        let entry = DirEntryMock::default().set_file("foobar".into());
+
+        let config = config_from(&["-e", "name = r'foo'"]);
+        assert!(config.root.evaluate(&entry).unwrap());
+
+        let config = config_from(&["-e", "name = r'foo'", "--regex-full-match"]);
+        assert!(!config.root.evaluate(&entry).unwrap());
+
+        let config = config_from(&["-e", "name = ri'FOO.*'", "--regex-full-match"]);
+        assert!(config.root.evaluate(&entry).unwrap());
+
+        let config = config_from(&["-e", "contains = r'foo'", "--regex-full-match"]);
+        assert!(matches!(
+            &config.root,
+            ExpressionNode::Leaf(Filter::Contains { value, .. }) if value.to_string() == "foo"
+        ));
     }
 }
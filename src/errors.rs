@@ -3,7 +3,8 @@ use std::path::PathBuf;
 use nom::error::ErrorKind;
 // use splr::SolverError;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, thiserror::Error, strum_macros::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
 pub enum GenericError {
     #[error("Unknown unit specifier: {0}")]
     UnknownSpecifierError(String),
@@ -28,11 +29,73 @@ pub enum GenericError {
 
     #[error("Not a file: {0}")]
     NotAFile(PathBuf),
+
+    #[error("Query rejected in strict mode: {0}")]
+    StrictQueryError(String),
+
+    #[error("Cannot read reference file {0}: {1}")]
+    ReferenceFileError(PathBuf, std::io::Error),
+
+    #[error("Cannot resolve start directory {0}: {1}")]
+    StartDirError(PathBuf, std::io::Error),
+
+    #[error("Start directory not found: {0}")]
+    StartDirNotFound(PathBuf),
+
+    #[error("Start directory is not a directory: {0}")]
+    StartDirNotADirectory(PathBuf),
+
+    #[error("Invalid FGR_DEFAULT_ARGS: {0}")]
+    InvalidDefaultArgs(String),
+
+    #[error("Conflicting flags: {0}")]
+    ConflictingFlags(String),
+
+    #[error("Invalid thread count {0}, expected at most {1}")]
+    InvalidThreadCount(usize, usize),
+
+    #[error("Unknown --printf directive: {0}")]
+    UnknownPrintfDirective(String),
+
+    #[error("Invalid size: {0}")]
+    InvalidSize(String),
+
+    #[error("Invalid duration: {0}")]
+    InvalidDuration(String),
+
+    #[error("Invalid file type {0}, expected one of f, d, l, s, p, b, c")]
+    InvalidFileType(String),
+
+    #[error("Invalid glob {0}: {1}")]
+    InvalidGlob(String, globset::Error),
+
+    #[error("Invalid regex {0}: {1}")]
+    InvalidRegex(String, regex::Error),
+
+    #[error("Unknown user: {0}")]
+    UnknownUser(String),
+
+    #[error("Unknown group: {0}")]
+    UnknownGroup(String),
+
+    #[error("Unmatched parenthesis: {0}")]
+    UnmatchedParen(char),
+
+    #[error("At offset {0}: {1}")]
+    ErrorAt(usize, Box<GenericError>),
+
+    #[error("{}", .0.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("\n"))]
+    MultipleErrors(Vec<GenericError>),
     // #[error("Solver error: {0}, statement: {1}")]
     // CustomSolverError(SolverError, String)
 }
 
 impl GenericError {
+    /// The variant name in snake case, e.g. `io_error`, stable for `--errors json`
+    pub fn kind(&self) -> &'static str {
+        self.into()
+    }
+
     pub fn is_fatal(&self) -> bool {
         match self {
             GenericError::UnknownSpecifierError(_) => true,
@@ -43,20 +106,93 @@ impl GenericError {
             GenericError::IoError(_) => false,
             GenericError::IgnoreError(_) => false,
             GenericError::NotAFile(_) => false,
+            GenericError::StrictQueryError(_) => true,
+            GenericError::ReferenceFileError(_, _) => true,
+            GenericError::StartDirError(_, _) => true,
+            GenericError::StartDirNotFound(_) => true,
+            GenericError::StartDirNotADirectory(_) => true,
+            GenericError::InvalidDefaultArgs(_) => true,
+            GenericError::ConflictingFlags(_) => true,
+            GenericError::InvalidThreadCount(_, _) => true,
+            GenericError::UnknownPrintfDirective(_) => true,
+            GenericError::InvalidSize(_) => true,
+            GenericError::InvalidDuration(_) => true,
+            GenericError::InvalidFileType(_) => true,
+            GenericError::InvalidGlob(_, _) => true,
+            GenericError::InvalidRegex(_, _) => true,
+            GenericError::UnknownUser(_) => true,
+            GenericError::UnknownGroup(_) => true,
+            GenericError::UnmatchedParen(_) => true,
+            GenericError::ErrorAt(_, error) => error.is_fatal(),
+            GenericError::MultipleErrors(_) => true,
             // GenericError::CustomSolverError(_, _) => true
         }
     }
 }
 
-impl From<GenericError> for nom::Err<nom::error::Error<&str>> {
-    fn from(_: GenericError) -> Self {
-        let error = nom::error::Error::new("Generic Error", ErrorKind::Alt);
-        nom::Err::Error(error)
+/// Parser error that can carry a [`GenericError`] explaining why a query was rejected
+#[derive(Debug)]
+pub struct ParseError<'a> {
+    pub input: &'a str,
+    pub kind: ErrorKind,
+    pub cause: Option<GenericError>,
+}
+
+pub type ParseResult<'a, O> = nom::IResult<&'a str, O, ParseError<'a>>;
+
+impl<'a> ParseError<'a> {
+    pub fn new(input: &'a str, kind: ErrorKind) -> Self {
+        Self { input, kind, cause: None }
+    }
+
+    /// Builds an unrecoverable error, so alternatives are not tried and `cause` is
+    /// reported to the user as is
+    pub fn fatal(input: &'a str, cause: GenericError) -> nom::Err<Self> {
+        nom::Err::Failure(Self { input, kind: ErrorKind::Fail, cause: Some(cause) })
+    }
+}
+
+impl PartialEq for ParseError<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.input == other.input && self.kind == other.kind
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for ParseError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        Self::new(input, kind)
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a, E> nom::error::FromExternalError<&'a str, E> for ParseError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _: E) -> Self {
+        Self::new(input, kind)
     }
 }
 
-impl From<nom::Err<nom::error::Error<&str>>> for GenericError {
-    fn from(err: nom::Err<nom::error::Error<&str>>) -> Self {
-        GenericError::NomError(err.to_string())
+impl From<GenericError> for nom::Err<ParseError<'_>> {
+    fn from(error: GenericError) -> Self {
+        ParseError::fatal("", error)
+    }
+}
+
+impl From<nom::Err<ParseError<'_>>> for GenericError {
+    fn from(err: nom::Err<ParseError<'_>>) -> Self {
+        match err {
+            nom::Err::Error(error) | nom::Err::Failure(error) => match error.cause {
+                Some(cause) => cause,
+                None => GenericError::NomError(format!(
+                    "{:?} at {:?}",
+                    error.kind, error.input
+                )),
+            },
+            nom::Err::Incomplete(needed) => {
+                GenericError::NomError(format!("Incomplete input: {needed:?}"))
+            }
+        }
     }
 }
@@ -0,0 +1,130 @@
+use std::time::SystemTime;
+
+use crate::errors::GenericError;
+use crate::evaluate::shared_cache::SharedCache;
+use crate::walk::traits::{DirEntryWrapperExt, FileIdentity};
+
+/// Memoizes what a filter reading file contents found, keyed by the file
+/// `(dev, inode)`, so hard links to the same file are read once. A result is used
+/// only while the mtime and the size of the file stay the same. Clones share it: the
+/// result is cached before the comparison is applied, so a negated clone reads the
+/// same value.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ContentCache {
+    results: SharedCache<(u64, u64), CachedResult>,
+}
+
+#[derive(Clone, Copy)]
+struct CachedResult {
+    mtime: SystemTime,
+    size: usize,
+    result: Option<bool>,
+}
+
+impl ContentCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { results: SharedCache::new(capacity) }
+    }
+
+    pub fn get_or_evaluate<E: DirEntryWrapperExt>(
+        &self,
+        entry: &E,
+        evaluate: impl FnOnce() -> Result<Option<bool>, GenericError>,
+    ) -> Result<Option<bool>, GenericError> {
+        let Ok(FileIdentity { dev, inode, mtime, size }) = entry.get_identity() else {
+            return evaluate();
+        };
+
+        let key = (dev, inode);
+        if let Some(cached) = self.results.get(&key) {
+            if cached.mtime == mtime && cached.size == size {
+                return Ok(cached.result);
+            }
+        }
+
+        let result = evaluate()?;
+        self.results.insert(key, CachedResult { mtime, size, result });
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use ignore::WalkBuilder;
+
+    use crate::evaluate::content_cache::ContentCache;
+    use crate::test_utils::DirEntryMock;
+    use crate::walk::traits::DirEntryWrapperExt;
+    use crate::walk::WalkedEntry;
+
+    #[test]
+    fn test_capacity() {
+        let cache = ContentCache::new(2);
+        let entry = |inode| {
+            DirEntryMock::default()
+                .set_dev(1)
+                .set_inode(inode)
+                .set_mtime(SystemTime::UNIX_EPOCH)
+                .set_size(1)
+        };
+
+        for inode in 0..3 {
+            let result = cache.get_or_evaluate(&entry(inode), || Ok(Some(true)));
+            assert_eq!(result.unwrap(), Some(true));
+        }
+        assert_eq!(cache.results.len(), 1);
+
+        let result = cache.get_or_evaluate(&entry(2), || unreachable!());
+        assert_eq!(result.unwrap(), Some(true));
+
+        // no identity, no caching
+        let result = cache.get_or_evaluate(&DirEntryMock::default(), || Ok(None));
+        assert_eq!(result.unwrap(), None);
+        assert_eq!(cache.results.len(), 1);
+    }
+
+    #[test]
+    fn test_changed_file() {
+        let cache = ContentCache::default();
+        let entry = DirEntryMock::default()
+            .set_dev(1)
+            .set_inode(1)
+            .set_mtime(SystemTime::UNIX_EPOCH)
+            .set_size(1);
+        assert_eq!(cache.get_or_evaluate(&entry, || Ok(Some(true))).unwrap(), Some(true));
+
+        let entry = entry.set_size(2);
+        let result = cache.get_or_evaluate(&entry, || Ok(Some(false)));
+        assert_eq!(result.unwrap(), Some(false));
+
+        let entry = entry.set_mtime(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        let result = cache.get_or_evaluate(&entry, || Ok(None));
+        assert_eq!(result.unwrap(), None);
+        assert_eq!(cache.get_or_evaluate(&entry, || unreachable!()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_walked_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, "one").unwrap();
+        let dir_entry = WalkBuilder::new(&path).build().next().unwrap().unwrap();
+        let entry = WalkedEntry::new(&dir_entry, true);
+
+        let identity = entry.get_identity().unwrap();
+        assert_eq!(identity.inode, entry.get_inode().unwrap());
+        assert_eq!(identity.mtime, entry.get_mtime().unwrap());
+        assert_eq!(identity.size, 3);
+
+        let cache = ContentCache::default();
+        assert_eq!(cache.get_or_evaluate(&entry, || Ok(Some(true))).unwrap(), Some(true));
+        assert_eq!(cache.get_or_evaluate(&entry, || unreachable!()).unwrap(), Some(true));
+
+        std::fs::write(&path, "three").unwrap();
+        let result = cache.get_or_evaluate(&entry, || Ok(Some(false)));
+        assert_eq!(result.unwrap(), Some(false));
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::Read;
+
+use clap::ValueEnum;
+use encoding_rs::{UTF_16BE, UTF_16LE};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+
+/// The encoding `contains` decodes a file from before matching it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ContentEncoding {
+    /// Read the bytes as they are, lines that are not valid UTF-8 never match
+    #[default]
+    Utf8,
+    Utf16le,
+    Utf16be,
+    /// UTF-16 if the file starts with a UTF-16 byte order mark, UTF-8 otherwise
+    Auto,
+}
+
+/// Wraps `reader` so it yields UTF-8, dropping the byte order mark of `encoding`.
+/// UTF-8 is passed through untouched, and so is a file without a UTF-16 byte order
+/// mark with `Auto`.
+pub fn decoding_reader<'a>(
+    reader: impl Read + 'a,
+    encoding: ContentEncoding,
+) -> Box<dyn Read + 'a> {
+    let encoding = match encoding {
+        ContentEncoding::Utf8 => return Box::new(reader),
+        ContentEncoding::Utf16le => Some(UTF_16LE),
+        ContentEncoding::Utf16be => Some(UTF_16BE),
+        ContentEncoding::Auto => None,
+    };
+
+    Box::new(DecodeReaderBytesBuilder::new().encoding(encoding).build(reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::evaluate::decode::{decoding_reader, ContentEncoding};
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// Returns at most 3 bytes per read
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    fn decode(bytes: &[u8], encoding: ContentEncoding) -> String {
+        let mut decoded = String::new();
+        decoding_reader(bytes, encoding).read_to_string(&mut decoded).unwrap();
+        decoded
+    }
+
+    #[test]
+    fn test_decoding_reader() {
+        let text = "héllo, 🦀\nworld";
+        let with_bom = [&[0xFF, 0xFE][..], &utf16le(text)].concat();
+
+        assert_eq!(decode(&with_bom, ContentEncoding::Auto), text);
+        assert_eq!(decode(&with_bom, ContentEncoding::Utf16le), text);
+        assert_eq!(decode(&utf16le(text), ContentEncoding::Utf16le), text);
+
+        let big_endian =
+            text.encode_utf16().flat_map(u16::to_be_bytes).collect::<Vec<_>>();
+        assert_eq!(
+            decode(&[&[0xFE, 0xFF][..], &big_endian].concat(), ContentEncoding::Auto),
+            text
+        );
+        assert_eq!(decode(&big_endian, ContentEncoding::Utf16be), text);
+
+        // without a UTF-16 byte order mark, auto falls back to UTF-8
+        assert_eq!(decode(text.as_bytes(), ContentEncoding::Auto), text);
+        assert_eq!(decode(b"\xEF\xBB\xBFbom", ContentEncoding::Auto), "bom");
+        assert_eq!(decode(b"ab", ContentEncoding::Auto), "ab");
+        assert_eq!(decode(b"", ContentEncoding::Auto), "");
+    }
+
+    #[test]
+    fn test_utf16_split_reads() {
+        let text = "🦀".repeat(10_000);
+        // an odd chunk size splits units and surrogate pairs between reads
+        let bytes = [utf16le(&text), vec![0x41]].concat();
+        let mut reader = decoding_reader(Trickle(&bytes), ContentEncoding::Utf16le);
+
+        let mut decoded = String::new();
+        reader.read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, text + "\u{FFFD}");
+
+        let lone_surrogate = [0x00, 0xD8, 0x41, 0x00];
+        assert_eq!(decode(&lone_surrogate, ContentEncoding::Utf16le), "\u{FFFD}A");
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs::ReadDir;
+use std::path::{Path, PathBuf};
+
+use crate::errors::GenericError;
+use crate::evaluate::shared_cache::SharedCache;
+
+/// The recursive sizes of the directories a `dirsize` filter has summed
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct DirSizes {
+    sizes: SharedCache<PathBuf, usize>,
+}
+
+impl DirSizes {
+    pub fn new(capacity: usize) -> Self {
+        Self { sizes: SharedCache::new(capacity) }
+    }
+
+    /// Sums the sizes of all files under `path` without following symlinks. Sizes of
+    /// nested directories are remembered, so the walker visiting them later does not
+    /// read them again. Entries under `path` that cannot be read are left out, only
+    /// `path` itself has to be readable.
+    pub fn get(&self, path: &Path) -> Result<usize, GenericError> {
+        if let Some(size) = self.cached(path) {
+            return Ok(size);
+        }
+
+        // the directories being read, deepest last, with the size summed so far
+        let mut stack: Vec<(PathBuf, ReadDir, usize)> =
+            vec![(path.to_path_buf(), std::fs::read_dir(path)?, 0)];
+
+        loop {
+            let (_, children, size) = stack.last_mut().expect("the stack holds path");
+            match children.next() {
+                Some(Ok(child)) => match child.file_type() {
+                    Ok(file_type) if file_type.is_dir() => {
+                        let child = child.path();
+                        if let Some(child_size) = self.cached(&child) {
+                            *size += child_size;
+                        } else if let Ok(grandchildren) = std::fs::read_dir(&child) {
+                            stack.push((child, grandchildren, 0));
+                        }
+                    }
+                    Ok(file_type) if file_type.is_file() => {
+                        *size += child.metadata().map_or(0, |metadata| metadata.len())
+                            as usize;
+                    }
+                    _ => {}
+                },
+                Some(Err(_)) => {}
+                None => {
+                    let (dir, _, size) = stack.pop().expect("the stack holds path");
+                    self.sizes.insert(dir, size);
+                    match stack.last_mut() {
+                        Some((_, _, parent_size)) => *parent_size += size,
+                        None => return Ok(size),
+                    }
+                }
+            }
+        }
+    }
+
+    fn cached(&self, path: &Path) -> Option<usize> {
+        self.sizes.get(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluate::dir_sizes::DirSizes;
+
+    #[test]
+    fn test_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b/c");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join("a/one"), [0; 10]).unwrap();
+        std::fs::write(nested.join("two"), [0; 20]).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("a"), nested.join("loop")).unwrap();
+
+        let sizes = DirSizes::new(2);
+        assert_eq!(sizes.get(dir.path()).unwrap(), 30);
+        // c and b filled the cache, so it started over with a
+        assert_eq!(sizes.cached(&nested), None);
+        assert_eq!(sizes.cached(&dir.path().join("a")), Some(30));
+        assert_eq!(sizes.cached(dir.path()), Some(30));
+
+        std::fs::write(dir.path().join("a/three"), [0; 30]).unwrap();
+        assert_eq!(sizes.get(dir.path()).unwrap(), 30);
+        assert_eq!(DirSizes::default().get(dir.path()).unwrap(), 60);
+        assert_eq!(DirSizes::default().get(&nested).unwrap(), 20);
+
+        assert!(DirSizes::default().get(&dir.path().join("missing")).is_err());
+    }
+}
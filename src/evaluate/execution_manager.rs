@@ -28,25 +28,25 @@ impl Default for FilterVar {
 
 impl PartialOrd<Self> for FilterVar {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FilterVar {
+    fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
             (
                 Self::Var { id: l_id, weight: l_weight },
                 Self::Var { id: r_id, weight: r_weight },
-            ) => (l_weight, l_id).partial_cmp(&(r_weight, r_id)),
+            ) => (l_weight, l_id).cmp(&(r_weight, r_id)),
 
-            (Self::Var { .. }, Self::Aux(_)) => Some(Ordering::Less),
-            (Self::Aux(_), Self::Var { .. }) => Some(Ordering::Greater),
-            (Self::Aux(l_id), Self::Aux(r_id)) => l_id.partial_cmp(r_id),
+            (Self::Var { .. }, Self::Aux(_)) => Ordering::Less,
+            (Self::Aux(_), Self::Var { .. }) => Ordering::Greater,
+            (Self::Aux(l_id), Self::Aux(r_id)) => l_id.cmp(r_id),
         }
     }
 }
 
-impl Ord for FilterVar {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
-
 impl FilterVar {
     fn new_var(id: usize, weight: usize) -> Self {
         Self::Var { id, weight }
@@ -109,6 +109,7 @@ impl ExecutionManager {
     }
 }
 
+#[allow(dead_code)]
 trait ComputationWeight {
     fn compute_weight(&self) -> usize;
 }
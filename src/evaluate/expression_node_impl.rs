@@ -1,21 +1,168 @@
+use std::fmt::Write;
+
 use nnf::parse_tree::ExpressionNode;
 
 use crate::errors::GenericError;
-use crate::evaluate::traits::Evaluate;
+use crate::evaluate::traits::{Evaluate, RequiresFileExt};
 use crate::parse::filter::Filter;
 use crate::walk::traits::DirEntryWrapperExt;
 
+/// Operands are evaluated left to right and short-circuit. The parsed tree is sorted
+/// with `sort_by_key(Filter::weight)` beforehand, so a cheap `name` decides an `and`
+/// or an `or` before an expensive `contains` on the other side is read.
 impl<E: DirEntryWrapperExt> Evaluate<E> for ExpressionNode<Filter> {
     fn evaluate(&self, entry: &E) -> Result<bool, GenericError> {
+        evaluate_leaves(self, &mut |filter| filter.evaluate(entry))
+    }
+}
+
+/// Evaluates `node` the same way, with `evaluate_leaf` deciding each filter it
+/// reaches
+pub fn evaluate_leaves(
+    node: &ExpressionNode<Filter>,
+    evaluate_leaf: &mut impl FnMut(&Filter) -> Result<bool, GenericError>,
+) -> Result<bool, GenericError> {
+    match node {
+        ExpressionNode::Leaf(filter) => evaluate_leaf(filter),
+        ExpressionNode::And(left, right) => Ok(evaluate_leaves(left, evaluate_leaf)?
+            && evaluate_leaves(right, evaluate_leaf)?),
+        ExpressionNode::Or(left, right) => Ok(evaluate_leaves(left, evaluate_leaf)?
+            || evaluate_leaves(right, evaluate_leaf)?),
+        ExpressionNode::Not(exp) => Ok(!evaluate_leaves(exp, evaluate_leaf)?),
+    }
+}
+
+/// Describes how `root` is evaluated for `--explain`: every leaf filter with its
+/// weight as `f<id>`, in the order they are reached, and the expression over them
+pub fn explain(root: &ExpressionNode<Filter>) -> String {
+    let mut filters = vec![];
+    let expression = render_leaves(root, &mut filters);
+
+    let mut output = String::from("Filters, in evaluation order:\n");
+    for (id, filter) in filters.iter().enumerate() {
+        let var = format!("f{id}");
+        let _ = writeln!(output, "    {var:<4} weight {:<4} {filter}", filter.weight());
+    }
+    let _ = writeln!(output, "Evaluated as:\n    {expression}");
+    output
+}
+
+/// Renders `node` with its leaves replaced by `f<id>`, numbered left to right
+fn render_leaves<'a>(
+    node: &'a ExpressionNode<Filter>,
+    filters: &mut Vec<&'a Filter>,
+) -> String {
+    match node {
+        ExpressionNode::Leaf(filter) => {
+            filters.push(filter);
+            format!("f{}", filters.len() - 1)
+        }
+        ExpressionNode::And(left, right) => {
+            let left = render_operand(node, left, filters);
+            format!("{left} and {}", render_operand(node, right, filters))
+        }
+        ExpressionNode::Or(left, right) => {
+            let left = render_operand(node, left, filters);
+            format!("{left} or {}", render_operand(node, right, filters))
+        }
+        ExpressionNode::Not(operand) => match operand.as_ref() {
+            ExpressionNode::Leaf(_) => format!("not {}", render_leaves(operand, filters)),
+            _ => format!("not ({})", render_leaves(operand, filters)),
+        },
+    }
+}
+
+/// Renders an operand of `parent`, in parentheses if it is a different operator
+fn render_operand<'a>(
+    parent: &ExpressionNode<Filter>,
+    operand: &'a ExpressionNode<Filter>,
+    filters: &mut Vec<&'a Filter>,
+) -> String {
+    let rendered = render_leaves(operand, filters);
+    match (parent, operand) {
+        (_, ExpressionNode::Leaf(_) | ExpressionNode::Not(_))
+        | (ExpressionNode::And(..), ExpressionNode::And(..))
+        | (ExpressionNode::Or(..), ExpressionNode::Or(..)) => rendered,
+        _ => format!("({rendered})"),
+    }
+}
+
+impl RequiresFileExt for ExpressionNode<Filter> {
+    fn requires_file(&self) -> bool {
         match self {
-            ExpressionNode::Leaf(filter) => filter.evaluate(entry),
+            ExpressionNode::Leaf(filter) => filter.requires_file(),
             ExpressionNode::And(left, right) => {
-                Ok(left.evaluate(entry)? && right.evaluate(entry)?)
+                left.requires_file() || right.requires_file()
             }
             ExpressionNode::Or(left, right) => {
-                Ok(left.evaluate(entry)? || right.evaluate(entry)?)
+                left.requires_file() && right.requires_file()
             }
-            ExpressionNode::Not(exp) => Ok(!exp.evaluate(entry)?),
+            // a negated file-only filter matches everything else
+            ExpressionNode::Not(_) => false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nnf::parse_tree::ExpressionNode;
+
+    use crate::evaluate::expression_node_impl::explain;
+    use crate::evaluate::traits::{Evaluate, RequiresFileExt};
+    use crate::parse::parse_root;
+    use crate::test_utils::DirEntryMock;
+
+    #[test]
+    fn test_requires_file() {
+        let requires_file = |query| parse_root(query).unwrap().requires_file();
+
+        assert!(requires_file("size > 1"));
+        assert!(requires_file("name = *.rs and contains = *fn*"));
+        assert!(requires_file("type = text or size > 1Mb"));
+        assert!(!requires_file("type = text or name = *.rs"));
+        assert!(!requires_file("not contains = *fn*"));
+        assert!(!requires_file("depth > 1"));
+    }
+
+    #[test]
+    fn test_cheap_operand_first() {
+        let evaluate = |query| {
+            let mut root = parse_root(query).unwrap().to_nnf();
+            root.sort_by_key(|filter| filter.weight());
+
+            // the mock has no entry type, so evaluating `contains` or `type` panics
+            let entry = DirEntryMock::default().set_file("sample.rs".into());
+            root.evaluate(&entry).unwrap()
+        };
+
+        assert!(!evaluate("contains = *fn* and name = *.py"));
+        assert!(evaluate("contains = *fn* or name = *.rs"));
+        assert!(!evaluate("(contains = *fn* or type = text) and ext = py"));
+        assert!(evaluate("not (type = text and contains = *fn*) or stem = sample"));
+
+        let weight = |query| match parse_root(query).unwrap() {
+            ExpressionNode::Leaf(filter) => filter.weight(),
+            _ => unreachable!(),
+        };
+        assert!(weight("binary") < weight("contains = *fn*"));
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut root = parse_root("not (type = vid and (size > 1Mb or name = *.mkv))")
+            .unwrap()
+            .to_nnf();
+        root.sort_by_key(|filter| filter.weight());
+        let explanation = explain(&root);
+
+        let lines = explanation.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Filters, in evaluation order:");
+        assert_eq!(lines[1], "    f0   weight 1    name != '*.mkv'");
+        assert_eq!(lines[2], "    f1   weight 4    size <= 1000000B");
+        assert_eq!(lines[3], "    f2   weight 16   type != Video");
+        assert_eq!(lines[4..], ["Evaluated as:", "    (f0 and f1) or f2"]);
+
+        let root = parse_root("not (name = a or name = b) and not name = c").unwrap();
+        assert!(explain(&root).ends_with("    not (f0 or f1) and not f2\n"));
+    }
+}
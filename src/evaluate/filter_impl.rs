@@ -1,116 +1,240 @@
+use std::collections::BTreeMap;
+use std::ffi::CString;
 use std::fs::OpenOptions;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::prelude::PermissionsExt;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
 
-use globset::GlobMatcher;
 use lazy_static::lazy_static;
+use memchr::memmem;
+use nnf::parse_tree::ExpressionNode;
 use timeout_readwrite::TimeoutReader;
 
 use crate::errors::GenericError;
+use crate::evaluate::decode::{decoding_reader, ContentEncoding};
+use crate::evaluate::literal_finder::Needle;
 use crate::evaluate::traits::DurationOffsetExt;
 use crate::evaluate::NOW;
+use crate::parse::access::Access;
 use crate::parse::comparison::Comparison;
+use crate::parse::contains_skip::ContainsSkip;
 use crate::parse::file_type::FileType;
 use crate::parse::filter::Filter;
+use crate::parse::permission_mode::PermissionMode;
+use crate::parse::time_attribute::TimeAttribute;
+use crate::parse::time_point::TimePoint;
 use crate::walk::entry_type::EntryType;
 use crate::walk::traits::DirEntryWrapperExt;
 use crate::Evaluate;
 
+/// The lines `contains` filters matched, by their 1-based numbers
+pub type MatchLines = BTreeMap<usize, String>;
+
+/// The suid, sgid, sticky and rwx bits of a mode, without the file type
+const PERMISSION_BITS: u32 = 0o7777;
+
+/// How long a single read of `type`, `contains`, `binary`, `text` and of archives may
+/// block unless `--read-timeout` says otherwise
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long to wait before the first retry of a transient read error
+const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// The longest wait between two retries, however many there are
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How many bytes `contains` reads at once when it looks for a literal
+const LITERAL_CHUNK_SIZE: usize = 64 * 1024;
+
 lazy_static! {
-    static ref PAGEMAP_FILTER: GlobMatcher =
-        globset::Glob::new("/proc/**/pagemap").unwrap().compile_matcher();
+    static ref IDENTITY: Identity = Identity::current();
+}
+
+/// The effective user and the groups of the process, captured once
+struct Identity {
+    uid: u32,
+    gids: Vec<u32>,
+}
+
+impl Identity {
+    fn current() -> Self {
+        let mut gids = uzers::group_access_list()
+            .map(|groups| groups.iter().map(|group| group.gid()).collect())
+            .unwrap_or_else(|_| vec![]);
+        gids.push(uzers::get_effective_gid());
+
+        Self { uid: uzers::get_effective_uid(), gids }
+    }
 }
 
 impl<E: DirEntryWrapperExt> Evaluate<E> for Filter {
     fn evaluate(&self, entry: &E) -> Result<bool, GenericError> {
         match self {
-            Self::Size { value, comparison } => {
-                if entry.get_entry_type() != EntryType::File {
+            Self::Size { value, comparison, .. } => {
+                // symlinks are only reported for dangling links or with lstat
+                // metadata, where the size is the length of the target path
+                if !matches!(entry.get_entry_type(), EntryType::File | EntryType::Symlink)
+                {
                     return Err(GenericError::NotAFile(entry.get_path().to_path_buf()));
                 }
-                Ok(comparison.evaluate(entry.get_size(), *value))
+                Ok(comparison.evaluate(entry.get_size()?, *value))
+            }
+            Self::DirSize { value, comparison, sizes } => {
+                if entry.get_entry_type() != EntryType::Dir {
+                    return Ok(false);
+                }
+                Ok(comparison.evaluate(sizes.get(entry.get_disk_path()?)?, *value))
             }
             Self::Depth { value, comparison } => {
                 Ok(comparison.evaluate(entry.get_depth(), *value))
             }
-            Self::Type { value, comparison } => {
+            Self::Type { value, comparison, read_timeout, io_retries, cache } => {
                 if entry.get_entry_type() != EntryType::File {
                     return Ok(false);
                 }
 
-                let file = OpenOptions::new().read(true).open(entry.get_path())?;
-                let reader = TimeoutReader::new(file, std::time::Duration::from_secs(1));
-                let mut reader = BufReader::new(reader);
-
-                let mut buf = vec![0; entry.get_size().min(8192)];
-                reader.read_exact(&mut buf)?;
-
-                let file_type: FileType = if let Some(file_type) = infer::get(&buf) {
-                    file_type.matcher_type()
-                } else {
+                let is_type = cache.get_or_evaluate(entry, || {
+                    let read_timeout = read_timeout_or_default(*read_timeout);
+                    with_retries(*io_retries, || is_type(value, read_timeout, entry))
+                })?;
+                // a file of an unknown type matches neither `=` nor `!=`
+                Ok(is_type.is_some_and(|is_type| comparison.evaluate(is_type, true)))
+            }
+            Self::TypeByExtension { value, comparison } => {
+                if entry.get_entry_type() != EntryType::File {
                     return Ok(false);
                 }
-                .into();
 
-                let mut result = &file_type == value;
-                if comparison != &Comparison::Eq {
-                    result = !result;
-                }
+                let file_type = entry
+                    .get_path()
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(|extension| {
+                        FileType::from_extension(&extension.to_lowercase())
+                    });
 
-                Ok(result)
+                match file_type {
+                    Some(file_type) => Ok(comparison.evaluate(&file_type == value, true)),
+                    None => Ok(false),
+                }
             }
             Self::AccessTime { value, comparison } => {
                 let file_atime = entry.get_atime()?;
-                let user_time = value.add_to(*NOW);
+                let user_time = resolve_time(value);
 
                 Ok(comparison.evaluate(file_atime, user_time))
             }
             Self::ModificationTime { value, comparison } => {
                 let file_mtime = entry.get_mtime()?;
-                let user_time = value.add_to(*NOW);
+                let user_time = resolve_time(value);
 
                 Ok(comparison.evaluate(file_mtime, user_time))
             }
+            Self::CompareAttrs { left, right, comparison } => {
+                let left = get_time(entry, *left)?;
+                let right = get_time(entry, *right)?;
+
+                Ok(comparison.evaluate(left, right))
+            }
             Self::Name { value, comparison } => {
                 let is_match = value.is_match(entry.get_name().to_string_lossy());
 
                 Ok(comparison.evaluate(is_match, true))
             }
+            Self::Path { value, comparison } => {
+                let is_match = value.is_match(relative_path(entry).to_string_lossy());
+
+                Ok(comparison.evaluate(is_match, true))
+            }
             Self::Extension { value, comparison } => {
                 if let Some(extension) = entry.get_path().extension() {
-                    Ok(comparison.evaluate(value.is_match(extension.to_string_lossy()), true))
+                    Ok(comparison
+                        .evaluate(value.is_match(extension.to_string_lossy()), true))
                 } else {
                     Ok(comparison.evaluate(false, true))
                 }
             }
-            Self::Contains { value, comparison } => {
+            Self::Stem { value, comparison } => {
+                if let Some(stem) = entry.get_path().file_stem() {
+                    Ok(comparison.evaluate(value.is_match(stem.to_string_lossy()), true))
+                } else {
+                    Ok(comparison.evaluate(false, true))
+                }
+            }
+            Self::Parent { value, comparison } => {
+                // `/` and a bare relative name have no parent directory name
+                let parent = entry.get_path().parent().and_then(Path::file_name);
+                if let Some(parent) = parent {
+                    Ok(comparison
+                        .evaluate(value.is_match(parent.to_string_lossy()), true))
+                } else {
+                    Ok(false)
+                }
+            }
+            Self::Kind { value, comparison } => {
+                Ok(comparison.evaluate(value.matches(&entry.get_entry_type()), true))
+            }
+            Self::Contains {
+                value,
+                comparison,
+                max_bytes,
+                read_timeout,
+                io_retries,
+                encoding,
+                skip,
+                cache,
+                finder,
+            } => {
                 if entry.get_entry_type() != EntryType::File {
                     return Ok(false);
                 }
 
-                let path = entry.get_path();
-
-                // skip pagemap because OOM Killer will NOT end our misery
-                if PAGEMAP_FILTER.is_match(path) {
+                let found = cache.get_or_evaluate(entry, || {
+                    let read_timeout = read_timeout_or_default(*read_timeout);
+                    with_retries(*io_retries, || {
+                        find_contents(
+                            finder.needle(value),
+                            *max_bytes,
+                            read_timeout,
+                            *encoding,
+                            skip,
+                            entry,
+                            None,
+                        )
+                    })
+                })?;
+                // a skipped file matches neither `=` nor `!=`
+                Ok(found.is_some_and(|found| comparison.evaluate(found, true)))
+            }
+            Self::Access { value, comparison } => {
+                Ok(comparison
+                    .evaluate(is_accessible(entry.get_disk_path()?, *value)?, true))
+            }
+            Self::Device { value, comparison } => {
+                Ok(comparison.evaluate(entry.get_dev()?, *value))
+            }
+            Self::SymlinkTarget { value, comparison } => {
+                // symlinks to existing entries report the type of their target, so
+                // the link itself is checked instead of the entry type
+                let Some(target) = entry.get_symlink_target()? else {
                     return Ok(false);
-                }
+                };
 
-                let file = OpenOptions::new().read(true).open(path)?;
-                let reader = TimeoutReader::new(file, std::time::Duration::from_secs(1));
-                let reader = BufReader::new(reader);
-
-                for line in reader.lines() {
-                    match line {
-                        Ok(line) if value.is_match(&line) => {
-                            return Ok(comparison.evaluate(true, true));
-                        }
-                        Err(err) => {
-                            return Err(err.into());
-                        }
-                        _ => continue,
-                    }
-                }
-                Ok(comparison.evaluate(false, true))
+                Ok(comparison.evaluate(value.is_match(target.to_string_lossy()), true))
+            }
+            Self::Xattr { name, value, comparison } => {
+                let is_match = match (entry.get_xattr(name)?, value) {
+                    (None, _) => false,
+                    (Some(_), None) => true,
+                    (Some(actual), Some(expected)) => actual == expected.as_bytes(),
+                };
+
+                Ok(comparison.evaluate(is_match, true))
+            }
+            Self::Inode { value, comparison } => {
+                Ok(comparison.evaluate(entry.get_inode()?, *value))
             }
             Self::User { value, comparison } => {
                 Ok(comparison.evaluate(entry.get_user_id()?, *value))
@@ -118,13 +242,67 @@ impl<E: DirEntryWrapperExt> Evaluate<E> for Filter {
             Self::Group { value, comparison } => {
                 Ok(comparison.evaluate(entry.get_group_id()?, *value))
             }
-            Self::Permissions { value, comparison } => {
-                let msb = 32 - value.mode().leading_zeros();
-                let mask = (1 << msb) - 1;
+            Self::Permissions { value, mode, comparison } => {
+                let file_bits = entry.get_permissions()?.mode() & PERMISSION_BITS;
+                let bits = value.mode() & PERMISSION_BITS;
 
-                let file_permissions = entry.get_permissions()?;
-                Ok(comparison
-                    .evaluate(file_permissions.mode() & mask, value.mode() & mask))
+                Ok(match mode {
+                    PermissionMode::Exact => comparison.evaluate(file_bits, bits),
+                    PermissionMode::AllSet => {
+                        comparison.evaluate(file_bits & bits == bits, true)
+                    }
+                    // like find, no requested bits match any mode
+                    PermissionMode::AnySet => {
+                        comparison.evaluate(bits == 0 || file_bits & bits != 0, true)
+                    }
+                })
+            }
+            Self::NewerThan { mtime, comparison, .. } => {
+                Ok(comparison.evaluate(entry.get_mtime()?, *mtime))
+            }
+            Self::Empty { dirs, comparison } => {
+                let is_empty = match entry.get_entry_type() {
+                    EntryType::File => entry.get_size()? == 0,
+                    EntryType::Dir if *dirs => {
+                        std::fs::read_dir(entry.get_disk_path()?)?.next().is_none()
+                    }
+                    _ => false,
+                };
+
+                Ok(comparison.evaluate(is_empty, true))
+            }
+            Self::NameInSet { names, comparison, .. } => {
+                Ok(comparison.evaluate(names.contains(entry.get_name()), true))
+            }
+            Self::ChildCount { value, comparison } => {
+                if entry.get_entry_type() != EntryType::Dir {
+                    return Ok(false);
+                }
+
+                // one entry past `value` decides any comparison, huge directories
+                // are not read to the end
+                let count = std::fs::read_dir(entry.get_disk_path()?)?
+                    .take(value.saturating_add(1))
+                    .count();
+                Ok(comparison.evaluate(count, *value))
+            }
+            Self::Binary { text, comparison, read_timeout } => {
+                if entry.get_entry_type() != EntryType::File {
+                    return Ok(false);
+                }
+
+                let read_timeout = read_timeout_or_default(*read_timeout);
+                let is_binary = is_binary(entry.get_disk_path()?, read_timeout)?;
+                Ok(comparison.evaluate(is_binary != *text, true))
+            }
+            Self::Mine { group, comparison } => {
+                let is_mine = if *group {
+                    IDENTITY.gids.contains(&entry.get_group_id()?)
+                } else {
+                    entry.get_user_id()? == IDENTITY.uid
+                };
+
+                Ok(comparison.evaluate(is_mine, true))
             }
 
             #[cfg(test)]
@@ -133,9 +311,339 @@ impl<E: DirEntryWrapperExt> Evaluate<E> for Filter {
     }
 }
 
+/// The path of `entry` below the start directory it was walked from, which is the
+/// last `depth` components of its path. A start directory, or an entry that is
+/// not deep enough for that, is matched by its whole path.
+fn relative_path<E: DirEntryWrapperExt>(entry: &E) -> &Path {
+    let (path, depth) = (entry.get_path(), entry.get_depth());
+    let count = path.components().count();
+    if depth == 0 || depth > count {
+        return path;
+    }
+
+    let mut components = path.components();
+    for _ in depth..count {
+        components.next();
+    }
+    components.as_path()
+}
+
+fn get_time<E: DirEntryWrapperExt>(
+    entry: &E,
+    attribute: TimeAttribute,
+) -> Result<SystemTime, GenericError> {
+    match attribute {
+        TimeAttribute::Access => entry.get_atime(),
+        TimeAttribute::Modification => entry.get_mtime(),
+        TimeAttribute::Birth => entry.get_btime(),
+    }
+}
+
+/// Checks the access with the effective user and group ids of the process, so the
+/// ownership, ACLs and read-only mounts are taken into account
+fn is_accessible(path: &Path, access: Access) -> Result<bool, GenericError> {
+    let mode = match access {
+        Access::Readable => libc::R_OK,
+        Access::Writable => libc::W_OK,
+        Access::Executable => libc::X_OK,
+    };
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| GenericError::IoError(err.into()))?;
+
+    // SAFETY: the path is a valid NUL-terminated string that outlives the call
+    let result =
+        unsafe { libc::faccessat(libc::AT_FDCWD, path.as_ptr(), mode, libc::AT_EACCESS) };
+    if result == 0 {
+        return Ok(true);
+    }
+
+    let error = std::io::Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::EACCES | libc::EROFS | libc::ETXTBSY) => Ok(false),
+        _ => Err(error.into()),
+    }
+}
+
+fn read_timeout_or_default(read_timeout: Option<Duration>) -> Duration {
+    read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT)
+}
+
+/// Runs `read` again up to `retries` times while it fails with a transient error,
+/// waiting twice as long before each retry, up to [`MAX_RETRY_BACKOFF`]. Any other
+/// result is returned at once.
+fn with_retries<T>(
+    retries: u32,
+    mut read: impl FnMut() -> Result<T, GenericError>,
+) -> Result<T, GenericError> {
+    let mut backoff = RETRY_BACKOFF;
+    for _ in 0..retries {
+        match read() {
+            Err(GenericError::IoError(error)) if is_transient(&error) => {
+                std::thread::sleep(backoff);
+                backoff = next_backoff(backoff);
+            }
+            result => return result,
+        }
+    }
+
+    read()
+}
+
+fn next_backoff(backoff: Duration) -> Duration {
+    backoff.saturating_mul(2).min(MAX_RETRY_BACKOFF)
+}
+
+fn is_transient(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+    )
+}
+
+fn resolve_time(value: &TimePoint) -> SystemTime {
+    match value {
+        TimePoint::Relative(duration) => duration.add_to(*NOW),
+        TimePoint::Absolute(time) => *time,
+    }
+}
+
+/// Whether the type guessed from the first bytes of the file is `value`, `None` if
+/// the type is unknown
+fn is_type<E: DirEntryWrapperExt>(
+    value: &FileType,
+    read_timeout: Duration,
+    entry: &E,
+) -> Result<Option<bool>, GenericError> {
+    let file = OpenOptions::new().read(true).open(entry.get_disk_path()?)?;
+    let reader = TimeoutReader::new(file, read_timeout);
+    let mut reader = BufReader::new(reader);
+
+    let mut buf = vec![0; entry.get_size()?.min(8192)];
+    reader.read_exact(&mut buf)?;
+
+    let Some(file_type) = infer::get(&buf) else {
+        return Ok(None);
+    };
+    let file_type: FileType = file_type.matcher_type().into();
+
+    Ok(Some(&file_type == value))
+}
+
+/// Looks for a NUL byte in the first 8 KiB of the file, like grep does to tell
+/// binary files from text ones
+fn is_binary(path: &Path, read_timeout: Duration) -> Result<bool, GenericError> {
+    let file = OpenOptions::new().read(true).open(path)?;
+    let reader = TimeoutReader::new(file, read_timeout);
+
+    let mut buf = Vec::with_capacity(8192);
+    reader.take(8192).read_to_end(&mut buf)?;
+
+    Ok(buf.contains(&0))
+}
+
+/// Whether the file contains `value`, `None` if it is skipped and never read. With
+/// `lines`, the whole file is read to collect every matching line, unless it turns
+/// out to be binary.
+fn find_contents<E: DirEntryWrapperExt>(
+    needle: Needle,
+    max_bytes: Option<u64>,
+    read_timeout: Duration,
+    encoding: ContentEncoding,
+    skip: &ContainsSkip,
+    entry: &E,
+    lines: Option<&mut MatchLines>,
+) -> Result<Option<bool>, GenericError> {
+    let path = entry.get_disk_path()?;
+
+    // skip pagemap and the like, because OOM Killer will NOT end our misery
+    if skip.is_match(path) {
+        return Ok(None);
+    }
+
+    let reader = open_contents(path, max_bytes, read_timeout, encoding)?;
+
+    if let Some(lines) = lines {
+        return Ok(Some(collect_lines(reader, needle, lines)?));
+    }
+
+    if let Needle::Literal(finder) = needle {
+        return Ok(Some(find_literal(reader, finder, LITERAL_CHUNK_SIZE)?));
+    }
+
+    let mut found = false;
+    for_each_line(reader, |_, line| {
+        found = needle.is_match(line);
+        !found
+    })?;
+    Ok(Some(found))
+}
+
+/// Calls `visit` with the number and the bytes of every line of `reader`, without
+/// the line ending, until it returns `false`. Lines need not be UTF-8, so binary
+/// files are read too.
+fn for_each_line(
+    reader: impl Read,
+    mut visit: impl FnMut(usize, &[u8]) -> bool,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut buf = vec![];
+
+    for number in 1.. {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+
+        let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if !visit(number, line) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Opens the file `contains` reads, decoded from `encoding`
+fn open_contents(
+    path: &Path,
+    max_bytes: Option<u64>,
+    read_timeout: Duration,
+    encoding: ContentEncoding,
+) -> Result<Box<dyn Read>, GenericError> {
+    let file = OpenOptions::new().read(true).open(path)?;
+    let reader = TimeoutReader::new(file, read_timeout);
+    let reader = reader.take(max_bytes.unwrap_or(u64::MAX));
+    Ok(decoding_reader(reader, encoding))
+}
+
+/// Reads every line of `reader`, collecting the ones matching `value`. Lines of a
+/// file with a NUL byte are binary, so none of them are collected.
+fn collect_lines(
+    reader: impl Read,
+    needle: Needle,
+    lines: &mut MatchLines,
+) -> std::io::Result<bool> {
+    let (mut found, mut binary) = (false, false);
+    lines.clear();
+
+    for_each_line(reader, |number, line| {
+        binary |= line.contains(&0);
+        if needle.is_match(line) {
+            found = true;
+            lines.insert(number, String::from_utf8_lossy(line).into_owned());
+        }
+        true
+    })?;
+
+    if binary {
+        lines.clear();
+    }
+    Ok(found)
+}
+
+impl Filter {
+    /// Evaluates the filter like `evaluate`, also returning the lines a
+    /// `contains = ...` matched, or `None` if it does not match. The lines are read
+    /// without the cache, since it keeps whether the file matched, not where.
+    pub fn match_lines<E: DirEntryWrapperExt>(
+        &self,
+        entry: &E,
+    ) -> Result<Option<MatchLines>, GenericError> {
+        let Self::Contains {
+            value,
+            comparison: Comparison::Eq,
+            max_bytes,
+            read_timeout,
+            io_retries,
+            encoding,
+            skip,
+            finder,
+            ..
+        } = self
+        else {
+            return Ok(self.evaluate(entry)?.then(MatchLines::new));
+        };
+        if entry.get_entry_type() != EntryType::File {
+            return Ok(None);
+        }
+
+        let mut lines = MatchLines::new();
+        let read_timeout = read_timeout_or_default(*read_timeout);
+        let found = with_retries(*io_retries, || {
+            let lines = Some(&mut lines);
+            let needle = finder.needle(value);
+            find_contents(needle, *max_bytes, read_timeout, *encoding, skip, entry, lines)
+        })?;
+        Ok((found == Some(true)).then_some(lines))
+    }
+}
+
+/// Evaluates `root` like `evaluate` does, with `match_lines` deciding each filter it
+/// reaches. Returns the lines found in the branches that made it match, or `None`
+/// if it does not. Lines under `not` only tell why the negated part did not match,
+/// so they are left out.
+pub fn evaluate_match_lines(
+    root: &ExpressionNode<Filter>,
+    match_lines: &mut impl FnMut(&Filter) -> Result<Option<MatchLines>, GenericError>,
+) -> Result<Option<MatchLines>, GenericError> {
+    match root {
+        ExpressionNode::Leaf(filter) => match_lines(filter),
+        ExpressionNode::And(left, right) => {
+            let Some(mut lines) = evaluate_match_lines(left, match_lines)? else {
+                return Ok(None);
+            };
+            let Some(right) = evaluate_match_lines(right, match_lines)? else {
+                return Ok(None);
+            };
+            lines.extend(right);
+            Ok(Some(lines))
+        }
+        ExpressionNode::Or(left, right) => match evaluate_match_lines(left, match_lines)?
+        {
+            Some(lines) => Ok(Some(lines)),
+            None => evaluate_match_lines(right, match_lines),
+        },
+        ExpressionNode::Not(exp) => match evaluate_match_lines(exp, match_lines)? {
+            Some(_) => Ok(None),
+            None => Ok(Some(MatchLines::new())),
+        },
+    }
+}
+
+/// Searches the raw bytes chunk by chunk, keeping the last `needle.len() - 1` bytes
+/// of a chunk so a needle split between two reads is still found. Unlike the line
+/// by line search, it works for binary files and lines that are not valid UTF-8.
+fn find_literal(
+    mut reader: impl Read,
+    finder: &memmem::Finder,
+    chunk_size: usize,
+) -> std::io::Result<bool> {
+    let needle = finder.needle();
+    let mut buffer = vec![0; chunk_size + needle.len()];
+    let mut carried = 0;
+
+    loop {
+        let read = match reader.read(&mut buffer[carried..]) {
+            Ok(0) => return Ok(false),
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        let filled = carried + read;
+        if finder.find(&buffer[..filled]).is_some() {
+            return Ok(true);
+        }
+
+        carried = filled.min(needle.len().saturating_sub(1));
+        buffer.copy_within(filled - carried..filled, 0);
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::io::Write;
+    use std::fs::Permissions;
+    use std::io::{Read, Write};
     use std::ops::Add;
     #[cfg(target_os = "linux")]
     use std::os::linux::fs::MetadataExt;
@@ -143,15 +651,39 @@ mod tests {
     #[cfg(target_os = "macos")]
     use std::os::macos::fs::MetadataExt;
 
+    use std::os::unix::fs::PermissionsExt;
     use std::path::PathBuf;
 
     use chrono::Duration;
+    use memchr::memmem::Finder;
+    use strum::IntoEnumIterator;
 
+    use crate::errors::GenericError;
+    use crate::evaluate::content_cache::ContentCache;
+    use crate::evaluate::decode::ContentEncoding;
+    use crate::evaluate::dir_sizes::DirSizes;
+    use crate::evaluate::filter_impl::{
+        evaluate_match_lines, find_literal, next_backoff, with_retries, MatchLines,
+        DEFAULT_READ_TIMEOUT, MAX_RETRY_BACKOFF, RETRY_BACKOFF,
+    };
+    use crate::evaluate::NOW;
+    use crate::parse::access::Access;
+    use crate::parse::attribute_token::AttributeToken;
     use crate::parse::comparison::Comparison;
+    use crate::parse::contains_skip::ContainsSkip;
+    use crate::parse::entry_kind::EntryKind;
     use crate::parse::file_type::FileType;
     use crate::parse::filter::Filter;
-    use crate::test_utils::DirEntryMock;
+    use crate::parse::match_pattern::MatchPattern;
+    use crate::parse::parse_root;
+    use crate::parse::permission_mode::PermissionMode;
+    use crate::parse::time_attribute::TimeAttribute;
+    use crate::parse::time_point::TimePoint;
+    use crate::parse::traits::GenericParser;
+    use crate::test_utils::{contains_filter, DirEntryMock};
     use crate::walk::entry_type::EntryType;
+    use crate::walk::traits::DirEntryWrapperExt;
+    use crate::walk::WalkedEntry;
     use crate::Evaluate;
 
     #[test]
@@ -173,7 +705,8 @@ mod tests {
 
     #[test]
     fn test_size() {
-        let filter = Filter::Size { value: 100, comparison: Comparison::Lte };
+        let filter =
+            Filter::Size { value: 100, comparison: Comparison::Lte, unitless: false };
         let mut entry =
             DirEntryMock::default().set_size(110).set_entry_type(EntryType::File);
 
@@ -191,6 +724,49 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_size_metadata_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vanished.txt");
+        std::fs::write(&path, "x".repeat(100)).unwrap();
+        let entry = ignore::WalkBuilder::new(&path).build().next().unwrap().unwrap();
+        let entry = WalkedEntry::new(&entry, true);
+
+        // stat fails from now on, like it does in a directory that cannot be searched
+        std::fs::remove_file(&path).unwrap();
+        assert!(entry.get_size().is_err());
+        for query in [" = 0", " < 1Kb", " != 100"] {
+            let (_, filter) = AttributeToken::Size.parse(query).unwrap();
+            assert!(filter.evaluate(&entry).is_err(), "size{query}");
+        }
+    }
+
+    #[test]
+    fn test_size_without_following_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("target"), "x".repeat(100)).unwrap();
+        std::os::unix::fs::symlink("target", dir.path().join("link")).unwrap();
+
+        let link = ignore::WalkBuilder::new(dir.path())
+            .build()
+            .map(|entry| entry.unwrap())
+            .find(|entry| entry.file_name() == "link")
+            .unwrap();
+        let filter =
+            Filter::Size { value: 6, comparison: Comparison::Eq, unitless: false };
+
+        let followed = WalkedEntry::new(&link, true);
+        assert_eq!(followed.get_entry_type(), EntryType::File);
+        assert_eq!(followed.get_size().unwrap(), 100);
+        assert!(!filter.evaluate(&followed).unwrap());
+
+        // the link itself holds the 6 bytes of "target"
+        let link = WalkedEntry::new(&link, false);
+        assert_eq!(link.get_entry_type(), EntryType::Symlink);
+        assert_eq!(link.get_size().unwrap(), 6);
+        assert!(filter.evaluate(&link).unwrap());
+    }
+
     #[test]
     fn test_depth() {
         let filter = Filter::Depth { value: 100, comparison: Comparison::Neq };
@@ -204,9 +780,40 @@ mod tests {
         assert!(!filter.evaluate(&entry).unwrap());
     }
 
+    #[test]
+    fn test_depth_of_walked_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("child/grandchild")).unwrap();
+        std::fs::write(dir.path().join("child/grandchild/file"), "").unwrap();
+
+        let depth_of = |depth: usize| {
+            let filter = Filter::Depth { value: depth, comparison: Comparison::Eq };
+            let mut matched = ignore::WalkBuilder::new(dir.path())
+                .build()
+                .map(|entry| entry.unwrap())
+                .filter(|entry| filter.evaluate(&WalkedEntry::new(entry, true)).unwrap())
+                .map(|entry| entry.path().strip_prefix(dir.path()).unwrap().to_path_buf())
+                .collect::<Vec<_>>();
+            matched.sort();
+            matched
+        };
+
+        assert_eq!(depth_of(0), vec![PathBuf::new()]);
+        assert_eq!(depth_of(1), vec![PathBuf::from("child")]);
+        assert_eq!(depth_of(2), vec![PathBuf::from("child/grandchild")]);
+        assert_eq!(depth_of(3), vec![PathBuf::from("child/grandchild/file")]);
+        assert!(depth_of(4).is_empty());
+    }
+
     #[test]
     fn test_type() {
-        let filter = Filter::Type { value: FileType::Text, comparison: Comparison::Eq };
+        let filter = Filter::Type {
+            value: FileType::Text,
+            comparison: Comparison::Eq,
+            read_timeout: None,
+            io_retries: 0,
+            cache: ContentCache::default(),
+        };
         let mut entry = DirEntryMock::default()
             .set_file("sample".into())
             .set_entry_type(EntryType::File);
@@ -234,9 +841,12 @@ mod tests {
             .set_mtime(file_atime);
 
         let filters = [
-            Filter::AccessTime { value: Duration::zero(), comparison: Comparison::Lte },
+            Filter::AccessTime {
+                value: Duration::zero().into(),
+                comparison: Comparison::Lte,
+            },
             Filter::ModificationTime {
-                value: Duration::zero(),
+                value: Duration::zero().into(),
                 comparison: Comparison::Lte,
             },
         ];
@@ -258,6 +868,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_future_time() {
+        let future = NOW.add(std::time::Duration::from_secs(3600));
+        let entry = DirEntryMock::default().set_mtime(future).set_atime(future);
+
+        let matches = |value: Duration, comparison: Comparison| {
+            let atime = Filter::AccessTime {
+                value: value.into(),
+                comparison: comparison.clone(),
+            };
+            let mtime = Filter::ModificationTime { value: value.into(), comparison };
+            let result = mtime.evaluate(&entry).unwrap();
+            assert_eq!(result, atime.evaluate(&entry).unwrap());
+            result
+        };
+
+        // a future time is later than any point up to now, nothing is clamped
+        for value in [-Duration::days(1), Duration::zero()] {
+            assert!(matches(value, Comparison::Gt));
+            assert!(matches(value, Comparison::Gte));
+            assert!(matches(value, Comparison::Neq));
+            assert!(!matches(value, Comparison::Lt));
+            assert!(!matches(value, Comparison::Lte));
+            assert!(!matches(value, Comparison::Eq));
+        }
+    }
+
+    #[test]
+    fn test_compare_attrs() {
+        let day = std::time::Duration::from_secs(86400);
+        let mtime = *NOW - day;
+        let never_read = DirEntryMock::default().set_atime(mtime - day).set_mtime(mtime);
+        let read = DirEntryMock::default().set_atime(mtime + day).set_mtime(mtime);
+
+        let filter = Filter::CompareAttrs {
+            left: TimeAttribute::Access,
+            right: TimeAttribute::Modification,
+            comparison: Comparison::Lt,
+        };
+        assert!(filter.evaluate(&never_read).unwrap());
+        assert!(!filter.evaluate(&read).unwrap());
+        assert!((!filter.clone()).evaluate(&read).unwrap());
+
+        let same = Filter::CompareAttrs {
+            left: TimeAttribute::Modification,
+            right: TimeAttribute::Modification,
+            comparison: Comparison::Eq,
+        };
+        assert!(same.evaluate(&read).unwrap());
+
+        // a missing timestamp is an error, not a mismatch
+        let birth = Filter::CompareAttrs {
+            left: TimeAttribute::Birth,
+            right: TimeAttribute::Modification,
+            comparison: Comparison::Lt,
+        };
+        assert!(birth.evaluate(&read).is_err());
+        assert!(birth.evaluate(&read.set_btime(mtime - day)).unwrap());
+    }
+
+    #[test]
+    fn test_path() {
+        let (_, filter) = AttributeToken::Path.parse(" = 'src/*.rs'").unwrap();
+        let entry = |path: &str, depth| {
+            DirEntryMock::default().set_file(path.into()).set_depth(depth)
+        };
+
+        assert!(filter.evaluate(&entry("/home/user/project/src/main.rs", 2)).unwrap());
+        assert!(filter.evaluate(&entry("./src/main.rs", 2)).unwrap());
+        assert!(!filter.evaluate(&entry("./src/main.rs", 3)).unwrap());
+        assert!(!filter.evaluate(&entry("/project/src/main.rs", 1)).unwrap());
+        // start directories and entries of a shallower tree are matched whole
+        assert!(filter.evaluate(&entry("src/main.rs", 0)).unwrap());
+        assert!(filter.evaluate(&entry("src/main.rs", 5)).unwrap());
+    }
+
     #[test]
     fn test_extension() {
         let filter = Filter::Extension {
@@ -277,11 +963,61 @@ mod tests {
     }
 
     #[test]
-    fn test_contains() {
-        let filter = Filter::Contains {
-            value: globset::Glob::new("*amp*").unwrap().into(),
-            comparison: Comparison::Eq,
+    fn test_stem() {
+        let (_, filter) = AttributeToken::Stem.parse(" = test").unwrap();
+        let matches = |file: &str| {
+            let entry = DirEntryMock::default().set_file(file.into());
+            filter.evaluate(&entry).unwrap()
+        };
+
+        assert!(matches("test.rs"));
+        assert!(matches("test.py"));
+        assert!(matches("test"));
+        assert!(!matches("testing.rs"));
+
+        let (_, filter) = AttributeToken::Stem.parse(" = archive.tar").unwrap();
+        let entry = DirEntryMock::default().set_file("archive.tar.gz".into());
+        assert!(filter.evaluate(&entry).unwrap());
+
+        let (_, filter) = AttributeToken::Stem.parse(" != archive").unwrap();
+        assert!(filter.evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_parent() {
+        let matches = |query: &str, file: &str| {
+            let (_, filter) = AttributeToken::Parent.parse(query).unwrap();
+            let entry = DirEntryMock::default().set_file(file.into());
+            filter.evaluate(&entry).unwrap()
         };
+
+        assert!(matches(" = migrations", "/srv/app/migrations/0001_init.sql"));
+        assert!(matches(" = migrations", "app/migrations/nested"));
+        assert!(!matches(" = migrations", "/srv/app/migrations"));
+        assert!(!matches(" = migrations", "/srv/migrations/v1/0001_init.sql"));
+        assert!(matches(" = r'^mig'", "migrations/0001_init.sql"));
+        assert!(matches(" != migrations", "/srv/app/src/main.rs"));
+
+        // no parent directory name: neither equal nor different
+        for file in ["/", "main.rs"] {
+            assert!(!matches(" = *", file));
+            assert!(!matches(" != migrations", file));
+        }
+    }
+
+    #[test]
+    fn test_extension_ignore_case() {
+        let (_, filter) = AttributeToken::Extension.parse(" = i'jpg'").unwrap();
+        let entry = DirEntryMock::default().set_file("photo.JPG".into());
+
+        let result = filter.evaluate(&entry);
+        assert!(result.is_ok());
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    fn test_contains() {
+        let filter = contains_filter(globset::Glob::new("*amp*").unwrap());
         let mut file = tempfile::NamedTempFile::new().unwrap();
 
         let entry = DirEntryMock::default()
@@ -299,6 +1035,433 @@ mod tests {
         assert!(result.unwrap());
     }
 
+    #[test]
+    fn test_contains_max_bytes() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let line = "x".repeat(1023);
+        for _ in 0..1024 {
+            writeln!(file, "{line}").unwrap();
+        }
+        writeln!(file, "needle").unwrap();
+        file.flush().unwrap();
+
+        let entry = DirEntryMock::default()
+            .set_file(file.path().to_path_buf())
+            .set_entry_type(EntryType::File);
+        let filter = |limit| {
+            let mut filter = contains_filter(globset::Glob::new("needle").unwrap());
+            if let Filter::Contains { max_bytes, .. } = &mut filter {
+                *max_bytes = limit;
+            }
+            filter
+        };
+
+        assert!(filter(None).evaluate(&entry).unwrap());
+        assert!(!filter(Some(1024 * 1024)).evaluate(&entry).unwrap());
+        assert!(filter(Some(1024 * 1024 + 6)).evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_contains_literal() {
+        // the needle straddles every chunk boundary of a 4 byte chunk at some offset
+        for offset in 0..8 {
+            let mut haystack = vec![b'x'; offset];
+            haystack.extend_from_slice(b"needle");
+            haystack.extend_from_slice(b"xx");
+            let (needle, needles) = (Finder::new(b"needle"), Finder::new(b"needles"));
+            assert!(find_literal(&haystack[..], &needle, 4).unwrap(), "{offset}");
+            assert!(!find_literal(&haystack[..], &needles, 4).unwrap(), "{offset}");
+        }
+
+        // binary content and a line break inside the needle
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"\xff\xfe\x00first\nsecond\x00").unwrap();
+        file.flush().unwrap();
+        let entry = DirEntryMock::default()
+            .set_file(file.path().to_path_buf())
+            .set_entry_type(EntryType::File);
+        let filter =
+            |pattern: &str| contains_filter(regex::Regex::new(pattern).unwrap());
+
+        assert!(filter("first\nsecond").evaluate(&entry).unwrap());
+        assert!(!filter("third").evaluate(&entry).unwrap());
+
+        // patterns search the lines of binary files too, converted lossily
+        assert!(filter("^sec.nd").evaluate(&entry).unwrap());
+        assert!(filter("^\u{fffd}\u{fffd}\x00fir").evaluate(&entry).unwrap());
+        assert!(!filter("^first$").evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_contains_skip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("skipped-by-test.txt");
+        std::fs::write(&path, "needle").unwrap();
+
+        let entry =
+            DirEntryMock::default().set_file(path).set_entry_type(EntryType::File);
+        let mut filter = contains_filter(globset::Glob::new("*needle*").unwrap());
+        assert!(filter.evaluate(&entry).unwrap());
+
+        if let Filter::Contains { skip, .. } = &mut filter {
+            *skip = ContainsSkip::new(&["**/skipped-by-*.txt"]).unwrap();
+        }
+        assert!(!filter.evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_contains_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("windows.log");
+        let text = "first line\r\nprice: 5 €uro\r\n";
+        let utf16 = text.encode_utf16().flat_map(u16::to_le_bytes);
+        std::fs::write(&path, [0xFF, 0xFE].into_iter().chain(utf16).collect::<Vec<_>>())
+            .unwrap();
+
+        let entry =
+            DirEntryMock::default().set_file(path).set_entry_type(EntryType::File);
+        let contains = |value: MatchPattern, decoding| {
+            let mut filter = contains_filter(value);
+            if let Filter::Contains { encoding, .. } = &mut filter {
+                *encoding = decoding;
+            }
+            filter
+        };
+        let literal =
+            |encoding| contains(globset::Glob::new("*€uro*").unwrap().into(), encoding);
+        let regex = |encoding| {
+            contains(regex::Regex::new(r"price: \d €").unwrap().into(), encoding)
+        };
+        // read as UTF-8, the lines are not valid and never match
+        assert!(!literal(ContentEncoding::Utf8).evaluate(&entry).unwrap());
+        assert!(!regex(ContentEncoding::Utf8).evaluate(&entry).unwrap());
+
+        assert!(literal(ContentEncoding::Auto).evaluate(&entry).unwrap());
+        assert!(regex(ContentEncoding::Auto).evaluate(&entry).unwrap());
+        assert!(regex(ContentEncoding::Utf16le).evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_match_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.rs");
+        std::fs::write(&path, "use std::io;\r\n\nfn main() {\n    todo!()\n}\n").unwrap();
+        let entry =
+            DirEntryMock::default().set_file(path).set_entry_type(EntryType::File);
+
+        let lines = |query| {
+            let root = parse_root(query).unwrap();
+            let lines =
+                evaluate_match_lines(&root, &mut |filter| filter.match_lines(&entry));
+            lines.unwrap().map(|lines| lines.into_iter().collect::<Vec<_>>())
+        };
+        let (todo, usage) =
+            ((4, "    todo!()".to_string()), (1, "use std::io;".to_string()));
+        assert_eq!(
+            lines("contains = *todo* and contains = r'^use'"),
+            Some(vec![usage, todo.clone()])
+        );
+        // the right side is never read, it did not make the match
+        assert_eq!(lines("contains = *todo* or contains = r'^use'"), Some(vec![todo]));
+        assert_eq!(lines("(contains = *todo* and ext = py) or ext = rs"), Some(vec![]));
+        // the lines of a negated filter do not explain the match
+        assert_eq!(lines("ext = rs and not contains = *unsafe*"), Some(vec![]));
+        assert_eq!(lines("not (contains = *todo* and ext = py)"), Some(vec![]));
+        assert_eq!(lines("contains = *unsafe* or ext = py"), None);
+
+        let path = dir.path().join("main.o");
+        std::fs::write(&path, "todo\n\0\ntodo\n").unwrap();
+        let entry =
+            DirEntryMock::default().set_file(path).set_entry_type(EntryType::File);
+        let filter = parse_root("contains = *todo*").unwrap();
+        let lines =
+            evaluate_match_lines(&filter, &mut |filter| filter.match_lines(&entry));
+        // matched, but the lines of a binary file are not printed
+        assert_eq!(lines.unwrap(), Some(MatchLines::new()));
+    }
+
+    #[test]
+    fn test_io_retries() {
+        /// Fails the first `failures` reads with a timeout, then reads `data`
+        struct FlakyReader {
+            failures: usize,
+            data: &'static [u8],
+        }
+
+        impl Read for FlakyReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.failures > 0 {
+                    self.failures -= 1;
+                    return Err(std::io::ErrorKind::TimedOut.into());
+                }
+                self.data.read(buf)
+            }
+        }
+
+        let mut reader = FlakyReader { failures: 1, data: b"needle" };
+        let mut attempts = 0;
+        let mut read = || {
+            attempts += 1;
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            Ok(text)
+        };
+        assert_eq!(with_retries(1, &mut read).unwrap(), "needle");
+        assert_eq!(attempts, 2);
+
+        // without retries, the first failure is final
+        let mut reader = FlakyReader { failures: 1, data: b"needle" };
+        let result = with_retries(0, || Ok(reader.read(&mut [0; 8])?));
+        assert!(matches!(result, Err(GenericError::IoError(_))));
+
+        // other errors are not retried
+        let mut attempts = 0;
+        let result: Result<(), _> = with_retries(3, || {
+            attempts += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::NotFound).into())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        // many retries neither overflow the backoff nor wait for ages
+        assert_eq!(next_backoff(RETRY_BACKOFF), RETRY_BACKOFF * 2);
+        assert_eq!(next_backoff(std::time::Duration::MAX), MAX_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("fifo");
+        let path = std::ffi::CString::new(fifo.as_os_str().as_encoded_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o644) }, 0);
+
+        // keep a writer open that never writes, so reads block instead of hitting EOF
+        let _writer =
+            std::fs::OpenOptions::new().read(true).write(true).open(&fifo).unwrap();
+
+        let entry =
+            DirEntryMock::default().set_file(fifo).set_entry_type(EntryType::File);
+        let mut filter = contains_filter(globset::Glob::new("*needle*").unwrap());
+        if let Filter::Contains { read_timeout, .. } = &mut filter {
+            *read_timeout = Some(std::time::Duration::from_millis(50));
+        }
+
+        let started = std::time::Instant::now();
+        let result = filter.evaluate(&entry);
+        assert!(matches!(
+            result,
+            Err(GenericError::IoError(error)) if error.kind() == std::io::ErrorKind::TimedOut
+        ));
+        assert!(started.elapsed() < DEFAULT_READ_TIMEOUT);
+    }
+
+    #[test]
+    fn test_contains_hard_links_share_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("original");
+        let link = dir.path().join("link");
+        std::fs::write(&original, "needle").unwrap();
+        std::fs::hard_link(&original, &link).unwrap();
+
+        let metadata = original.metadata().unwrap();
+        let filter = contains_filter(globset::Glob::new("*needle*").unwrap());
+        let entry = |path: &PathBuf, size| {
+            DirEntryMock::default()
+                .set_file(path.clone())
+                .set_entry_type(EntryType::File)
+                .set_dev(metadata.st_dev())
+                .set_inode(metadata.st_ino())
+                .set_mtime(metadata.modified().unwrap())
+                .set_size(size)
+        };
+
+        assert!(filter.evaluate(&entry(&original, 6)).unwrap());
+
+        // the second link must not be read again, so the new content is not seen,
+        // not even by the negated clone
+        std::fs::write(&original, "hay").unwrap();
+        assert!(filter.evaluate(&entry(&link, 6)).unwrap());
+        assert!(!(!filter.clone()).evaluate(&entry(&link, 6)).unwrap());
+
+        // a changed size or mtime means the file changed and is read again
+        assert!(!filter.evaluate(&entry(&link, 3)).unwrap());
+        std::fs::write(&original, "needle").unwrap();
+        let touched = entry(&link, 3).set_mtime(std::time::SystemTime::now());
+        assert!(filter.evaluate(&touched).unwrap());
+
+        // another search has a cache of its own
+        let (_, other) = AttributeToken::Contains.parse(" = *needle*").unwrap();
+        std::fs::write(&original, "hay").unwrap();
+        assert!(!other.evaluate(&entry(&link, 6)).unwrap());
+    }
+
+    #[test]
+    fn test_type_by_extension() {
+        let filter = Filter::TypeByExtension {
+            value: FileType::Video,
+            comparison: Comparison::Eq,
+        };
+        // the file does not exist, so it can only be classified by its name
+        let entry = |name: &str| {
+            DirEntryMock::default()
+                .set_file(PathBuf::from("/definitely/missing").join(name))
+                .set_entry_type(EntryType::File)
+        };
+
+        assert!(filter.evaluate(&entry("foo.mp4")).unwrap());
+        assert!(filter.evaluate(&entry("FOO.MKV")).unwrap());
+        assert!(!filter.evaluate(&entry("foo.mp3")).unwrap());
+        assert!(!filter.evaluate(&entry("foo")).unwrap());
+        assert!(!(!filter).evaluate(&entry("foo.unknown")).unwrap());
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("a"), [0; 100]).unwrap();
+        std::fs::write(dir.path().join("nested").join("b"), [0; 28]).unwrap();
+
+        let entry = |path: PathBuf, entry_type| {
+            DirEntryMock::default().set_file(path).set_entry_type(entry_type)
+        };
+        let filter = |value| Filter::DirSize {
+            value,
+            comparison: Comparison::Eq,
+            sizes: DirSizes::default(),
+        };
+
+        let root = entry(dir.path().to_path_buf(), EntryType::Dir);
+        assert!(filter(128).evaluate(&root).unwrap());
+        assert!(!filter(100).evaluate(&root).unwrap());
+
+        let nested = entry(dir.path().join("nested"), EntryType::Dir);
+        assert!(filter(28).evaluate(&nested).unwrap());
+
+        let file = entry(dir.path().join("a"), EntryType::File);
+        assert!(!filter(100).evaluate(&file).unwrap());
+    }
+
+    #[test]
+    fn test_absolute_time() {
+        let mtime = std::time::UNIX_EPOCH.add(std::time::Duration::from_secs(1_000_000));
+        let entry = DirEntryMock::default().set_mtime(mtime).set_atime(mtime);
+
+        let before = |time| Filter::ModificationTime {
+            value: TimePoint::Absolute(time),
+            comparison: Comparison::Lt,
+        };
+        assert!(before(mtime.add(std::time::Duration::from_secs(1)))
+            .evaluate(&entry)
+            .unwrap());
+        assert!(!before(mtime).evaluate(&entry).unwrap());
+
+        let at = Filter::AccessTime { value: mtime.into(), comparison: Comparison::Eq };
+        assert!(at.evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_access() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let entry = DirEntryMock::default().set_file(file.path().to_path_buf());
+        let filter = |value| Filter::Access { value, comparison: Comparison::Eq };
+
+        std::fs::set_permissions(file.path(), Permissions::from_mode(0o644)).unwrap();
+        assert!(filter(Access::Readable).evaluate(&entry).unwrap());
+        assert!(!filter(Access::Executable).evaluate(&entry).unwrap());
+        assert!((!filter(Access::Executable)).evaluate(&entry).unwrap());
+
+        std::fs::set_permissions(file.path(), Permissions::from_mode(0o755)).unwrap();
+        assert!(filter(Access::Executable).evaluate(&entry).unwrap());
+
+        let missing = DirEntryMock::default().set_file("/definitely/missing".into());
+        assert!(filter(Access::Readable).evaluate(&missing).is_err());
+    }
+
+    #[test]
+    fn test_device() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let dev = file.as_file().metadata().unwrap().st_dev();
+
+        let filter = Filter::Device { value: dev, comparison: Comparison::Eq };
+        assert!(filter.evaluate(&DirEntryMock::default().set_dev(dev)).unwrap());
+        assert!(!filter.evaluate(&DirEntryMock::default().set_dev(dev + 1)).unwrap());
+        assert!((!filter).evaluate(&DirEntryMock::default().set_dev(dev + 1)).unwrap());
+    }
+
+    #[test]
+    fn test_symlink_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink("/etc/hostname", &link).unwrap();
+        let entry = ignore::WalkBuilder::new(&link).build().next().unwrap().unwrap();
+        let entry = WalkedEntry::new(&entry, true);
+
+        let (_, etc) = AttributeToken::SymlinkTarget.parse(" = '/etc/*'").unwrap();
+        let (_, usr) = AttributeToken::SymlinkTarget.parse(" = '/usr/*'").unwrap();
+        let (_, not_usr) = AttributeToken::SymlinkTarget.parse(" != '/usr/*'").unwrap();
+        assert!(etc.evaluate(&entry).unwrap());
+        assert!(!usr.evaluate(&entry).unwrap());
+        assert!(not_usr.evaluate(&entry).unwrap());
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        let entry = ignore::WalkBuilder::new(&file).build().next().unwrap().unwrap();
+        let entry = WalkedEntry::new(&entry, true);
+        assert!(!etc.evaluate(&entry).unwrap());
+        assert!(!not_usr.evaluate(&entry).unwrap());
+
+        let (_, relative) = AttributeToken::SymlinkTarget.parse(r" = r'^\.\./'").unwrap();
+        let entry = DirEntryMock::default().set_symlink_target("../shared/lib".into());
+        assert!(relative.evaluate(&entry).unwrap());
+        assert!(!relative.evaluate(&DirEntryMock::default()).unwrap());
+    }
+
+    #[test]
+    fn test_xattr() {
+        let (_, present) = AttributeToken::Xattr.parse(" = 'user.comment'").unwrap();
+        let (_, tagged) = AttributeToken::Xattr.parse(" = user.tag:archive").unwrap();
+        let (_, untagged) = AttributeToken::Xattr.parse(" != user.tag:archive").unwrap();
+
+        let entry = DirEntryMock::default().set_xattr("user.tag", b"archive");
+        assert!(!present.evaluate(&entry).unwrap());
+        assert!(tagged.evaluate(&entry).unwrap());
+        assert!(!untagged.evaluate(&entry).unwrap());
+
+        let entry = entry.set_xattr("user.comment", b"").set_xattr("user.tag", b"other");
+        assert!(present.evaluate(&entry).unwrap());
+        assert!(!tagged.evaluate(&entry).unwrap());
+        assert!(untagged.evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_xattr_on_disk() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        if xattr::set(file.path(), "user.tag", b"archive").is_err() {
+            // the file system does not support user attributes
+            return;
+        }
+
+        let entry =
+            ignore::WalkBuilder::new(file.path()).build().next().unwrap().unwrap();
+        let entry = WalkedEntry::new(&entry, true);
+        let (_, tagged) = AttributeToken::Xattr.parse(" = user.tag:archive").unwrap();
+        let (_, missing) = AttributeToken::Xattr.parse(" = user.comment").unwrap();
+        assert!(tagged.evaluate(&entry).unwrap());
+        assert!(!missing.evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_inode() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let inode = file.as_file().metadata().unwrap().st_ino();
+
+        let filter = Filter::Inode { value: inode, comparison: Comparison::Eq };
+        assert!(filter.evaluate(&DirEntryMock::default().set_inode(inode)).unwrap());
+        assert!(!filter.evaluate(&DirEntryMock::default().set_inode(inode + 1)).unwrap());
+        assert!(filter.evaluate(&DirEntryMock::default()).is_err());
+    }
+
     #[test]
     fn test_user() {
         let file = tempfile::NamedTempFile::new().unwrap();
@@ -332,6 +1495,7 @@ mod tests {
 
         let filter = Filter::Permissions {
             value: permissions.clone(),
+            mode: PermissionMode::Exact,
             comparison: Comparison::Lte,
         };
         let entry = DirEntryMock::default().set_permissions(permissions);
@@ -341,6 +1505,225 @@ mod tests {
         assert!(result.unwrap());
     }
 
+    #[test]
+    fn test_permission_modes() {
+        use PermissionMode::{AllSet, AnySet, Exact};
+
+        // (file mode, requested bits, exact, all set, any set)
+        let table = [
+            (0o100644, 0o644, true, true, true),
+            (0o100644, 0o600, false, true, true),
+            (0o100600, 0o644, false, false, true),
+            (0o100755, 0o111, false, true, true),
+            (0o100744, 0o111, false, false, true),
+            (0o100644, 0o111, false, false, false),
+            (0o104755, 0o4000, false, true, true),
+            (0o100755, 0o4000, false, false, false),
+            (0o100644, 0o0, false, true, true),
+        ];
+
+        for (file_mode, bits, exact, all_set, any_set) in table {
+            let entry = DirEntryMock::default()
+                .set_permissions(Permissions::from_mode(file_mode));
+
+            for (mode, expected) in [(Exact, exact), (AllSet, all_set), (AnySet, any_set)]
+            {
+                let filter = Filter::Permissions {
+                    value: Permissions::from_mode(bits),
+                    mode,
+                    comparison: Comparison::Eq,
+                };
+                let case = format!("{file_mode:o} {mode:?} {bits:o}");
+                assert_eq!(filter.evaluate(&entry).unwrap(), expected, "{case}");
+                assert_eq!((!filter).evaluate(&entry).unwrap(), !expected, "{case}");
+            }
+        }
+
+        let filter = |comparison| Filter::Permissions {
+            value: Permissions::from_mode(0o4000),
+            mode: Exact,
+            comparison,
+        };
+        let suid =
+            DirEntryMock::default().set_permissions(Permissions::from_mode(0o104755));
+        assert!(filter(Comparison::Gt).evaluate(&suid).unwrap());
+        assert!(!filter(Comparison::Lt).evaluate(&suid).unwrap());
+    }
+
+    #[test]
+    fn test_newer_than() {
+        let reference = std::time::SystemTime::now();
+        let filter = Filter::NewerThan {
+            value: "reference".into(),
+            mtime: reference,
+            comparison: Comparison::Gt,
+        };
+
+        let entry = DirEntryMock::default()
+            .set_mtime(reference.add(std::time::Duration::from_secs(1)));
+        assert!(filter.evaluate(&entry).unwrap());
+
+        let entry = DirEntryMock::default().set_mtime(reference);
+        assert!(!filter.evaluate(&entry).unwrap());
+        assert!((!filter).evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_empty() {
+        let filter = Filter::Empty { dirs: true, comparison: Comparison::Eq };
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut entry = DirEntryMock::default()
+            .set_file(dir.path().to_path_buf())
+            .set_entry_type(EntryType::Dir);
+        assert!(filter.evaluate(&entry).unwrap());
+
+        std::fs::File::create(dir.path().join("child")).unwrap();
+        assert!(!filter.evaluate(&entry).unwrap());
+
+        entry = entry.set_entry_type(EntryType::File).set_size(0);
+        assert!(filter.evaluate(&entry).unwrap());
+
+        entry = entry.set_size(1);
+        assert!(!filter.evaluate(&entry).unwrap());
+
+        let files_only = Filter::Empty { dirs: false, comparison: Comparison::Eq };
+        let empty_dir = tempfile::tempdir().unwrap();
+        entry =
+            entry.set_file(empty_dir.path().to_path_buf()).set_entry_type(EntryType::Dir);
+        assert!(!files_only.evaluate(&entry).unwrap());
+    }
+
+    #[test]
+    fn test_kind() {
+        let kinds = [
+            (EntryType::Dir, EntryKind::Dir),
+            (EntryType::File, EntryKind::File),
+            (EntryType::Symlink, EntryKind::Symlink),
+            (EntryType::Socket, EntryKind::Socket),
+            (EntryType::FIFO, EntryKind::Fifo),
+            (EntryType::BlockDevice, EntryKind::Block),
+            (EntryType::CharDevice, EntryKind::Char),
+        ];
+
+        for (entry_type, expected) in kinds {
+            let entry = DirEntryMock::default().set_entry_type(entry_type.clone());
+            for kind in EntryKind::iter() {
+                let filter =
+                    Filter::Kind { value: kind.clone(), comparison: Comparison::Eq };
+                assert_eq!(filter.evaluate(&entry).unwrap(), kind == expected, "{kind}");
+                assert_eq!(
+                    (!filter).evaluate(&entry).unwrap(),
+                    kind != expected,
+                    "{kind}"
+                );
+            }
+        }
+
+        for entry_type in [EntryType::StdIn, EntryType::Unknown] {
+            let entry = DirEntryMock::default().set_entry_type(entry_type);
+            assert!(EntryKind::iter().all(|kind| {
+                !Filter::Kind { value: kind, comparison: Comparison::Eq }
+                    .evaluate(&entry)
+                    .unwrap()
+            }));
+        }
+    }
+
+    #[test]
+    fn test_child_count() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..5 {
+            std::fs::File::create(dir.path().join(format!("{index}.txt"))).unwrap();
+        }
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+
+        let entry = DirEntryMock::default()
+            .set_file(dir.path().to_path_buf())
+            .set_entry_type(EntryType::Dir);
+        let matches = |value, comparison| {
+            Filter::ChildCount { value, comparison }.evaluate(&entry).unwrap()
+        };
+
+        assert!(matches(6, Comparison::Eq));
+        assert!(matches(5, Comparison::Gt));
+        assert!(!matches(6, Comparison::Gt));
+        assert!(matches(6, Comparison::Gte));
+        assert!(matches(7, Comparison::Lt));
+        assert!(!matches(5, Comparison::Lte));
+        assert!(matches(1, Comparison::Neq));
+        assert!(!(!Filter::ChildCount { value: 6, comparison: Comparison::Eq })
+            .evaluate(&entry)
+            .unwrap());
+
+        // files have no children, whatever the comparison is
+        let file = DirEntryMock::default()
+            .set_file(dir.path().join("0.txt"))
+            .set_entry_type(EntryType::File);
+        let filter = Filter::ChildCount { value: 1, comparison: Comparison::Lt };
+        assert!(!filter.evaluate(&file).unwrap());
+
+        assert_eq!(
+            AttributeToken::Children.parse("> 1000"),
+            Ok(("", Filter::ChildCount { value: 1000, comparison: Comparison::Gt }))
+        );
+    }
+
+    #[test]
+    fn test_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("binary.dat");
+        let text = dir.path().join("text.txt");
+        std::fs::write(&binary, b"ELF\x00\x01\x02").unwrap();
+        std::fs::write(&text, "plain UTF-8 text, no signature: ż\n").unwrap();
+
+        let file = |path: &PathBuf| {
+            DirEntryMock::default().set_file(path.clone()).set_entry_type(EntryType::File)
+        };
+        let filter = |text| Filter::Binary {
+            text,
+            comparison: Comparison::Eq,
+            read_timeout: None,
+        };
+        let is_binary = filter(false);
+        let is_text = filter(true);
+
+        assert!(is_binary.evaluate(&file(&binary)).unwrap());
+        assert!(!is_text.evaluate(&file(&binary)).unwrap());
+        assert!(is_text.evaluate(&file(&text)).unwrap());
+        assert!(!is_binary.evaluate(&file(&text)).unwrap());
+        assert!((!is_binary.clone()).evaluate(&file(&text)).unwrap());
+
+        // directories are neither
+        let dir = DirEntryMock::default()
+            .set_file(dir.path().to_path_buf())
+            .set_entry_type(EntryType::Dir);
+        assert!(!is_binary.evaluate(&dir).unwrap());
+        assert!(!is_text.evaluate(&dir).unwrap());
+    }
+
+    #[test]
+    fn test_mine() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let metadata = file.as_file().metadata().unwrap();
+
+        let owned = DirEntryMock::default()
+            .set_user_id(metadata.st_uid())
+            .set_group_id(metadata.st_gid());
+        let foreign = DirEntryMock::default()
+            .set_user_id(metadata.st_uid() + 1)
+            .set_group_id(u32::MAX - 1);
+
+        let mine = Filter::Mine { group: false, comparison: Comparison::Eq };
+        let my_group = Filter::Mine { group: true, comparison: Comparison::Eq };
+
+        assert!(mine.evaluate(&owned).unwrap());
+        assert!(my_group.evaluate(&owned).unwrap());
+        assert!(!mine.evaluate(&foreign).unwrap());
+        assert!(!my_group.evaluate(&foreign).unwrap());
+        assert!((!mine).evaluate(&foreign).unwrap());
+    }
+
     #[test]
     fn test_bool() {
         let filter = Filter::Bool { value: true, comparison: Comparison::Eq };
@@ -0,0 +1,74 @@
+use std::sync::OnceLock;
+
+use memchr::memmem::Finder;
+
+use crate::parse::match_pattern::MatchPattern;
+
+/// The searcher for the literal text of a `contains` pattern, built the first time
+/// the filter reads a file and kept for all the others. Clones made after that share
+/// it. Finders never make filters unequal.
+#[derive(Clone, Default)]
+pub struct LiteralFinder(OnceLock<Option<Box<Finder<'static>>>>);
+
+impl LiteralFinder {
+    /// How to look for `value`: with the finder if it is a plain substring search,
+    /// line by line otherwise
+    pub fn needle<'a>(&'a self, value: &'a MatchPattern) -> Needle<'a> {
+        let finder = self.0.get_or_init(|| {
+            value
+                .as_literal()
+                .map(|literal| Box::new(Finder::new(literal.as_bytes()).into_owned()))
+        });
+
+        match finder.as_deref() {
+            Some(finder) => Needle::Literal(finder),
+            None => Needle::Pattern(value),
+        }
+    }
+}
+
+impl PartialEq for LiteralFinder {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for LiteralFinder {}
+
+/// What `contains` looks for in a file
+#[derive(Clone, Copy)]
+pub enum Needle<'a> {
+    /// Raw bytes, found even across lines
+    Literal(&'a Finder<'static>),
+    /// A pattern matched against every line, converted to UTF-8 lossily
+    Pattern(&'a MatchPattern),
+}
+
+impl Needle<'_> {
+    pub fn is_match(&self, line: &[u8]) -> bool {
+        match self {
+            Needle::Literal(finder) => finder.find(line).is_some(),
+            Needle::Pattern(pattern) => pattern.is_match(String::from_utf8_lossy(line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluate::literal_finder::{LiteralFinder, Needle};
+    use crate::parse::primitives::compile_glob;
+
+    #[test]
+    fn test_needle() {
+        let finder = LiteralFinder::default();
+        let literal = compile_glob("*needle*", false).unwrap();
+        assert!(matches!(finder.needle(&literal), Needle::Literal(_)));
+        assert!(finder.needle(&literal).is_match(b"\xff needle \x00"));
+        assert!(!finder.needle(&literal).is_match(b"needl"));
+
+        let pattern = compile_glob("*needle*", true).unwrap();
+        let needle = LiteralFinder::default();
+        assert!(matches!(needle.needle(&pattern), Needle::Pattern(_)));
+        assert!(needle.needle(&pattern).is_match(b"\xff NEEDLE \x00"));
+    }
+}
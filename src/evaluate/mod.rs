@@ -1,14 +1,42 @@
 use std::time::SystemTime;
 
+#[cfg(not(test))]
 use lazy_static::lazy_static;
 
 pub mod comparison_impl;
+pub mod content_cache;
+pub mod decode;
+pub mod dir_sizes;
 pub mod execution_manager;
 pub mod expression_node_impl;
 pub mod filter_impl;
+pub mod literal_finder;
+pub mod profile;
+pub mod shared_cache;
 pub mod solve;
 pub mod traits;
 
+#[cfg(not(test))]
 lazy_static! {
     pub static ref NOW: SystemTime = SystemTime::now();
 }
+
+/// Every test runs on its own thread and gets its own `NOW`, taken on first use, so
+/// it does not depend on which test happened to read it first
+#[cfg(test)]
+pub static NOW: ThreadNow = ThreadNow;
+
+#[cfg(test)]
+pub struct ThreadNow;
+
+#[cfg(test)]
+impl std::ops::Deref for ThreadNow {
+    type Target = SystemTime;
+
+    fn deref(&self) -> &SystemTime {
+        thread_local! {
+            static NOW: &'static SystemTime = Box::leak(Box::new(SystemTime::now()));
+        }
+        NOW.with(|now| *now)
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use nnf::parse_tree::ExpressionNode;
+
+use crate::errors::GenericError;
+use crate::evaluate::expression_node_impl::evaluate_leaves;
+use crate::evaluate::filter_impl::{evaluate_match_lines, MatchLines};
+use crate::parse::filter::Filter;
+use crate::walk::traits::DirEntryWrapperExt;
+use crate::Evaluate;
+
+/// How many times a filter variant was evaluated and how long it took altogether
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FilterTiming {
+    pub evaluations: u64,
+    pub total: Duration,
+}
+
+impl FilterTiming {
+    fn add(&mut self, other: FilterTiming) {
+        self.evaluations += other.evaluations;
+        self.total += other.total;
+    }
+}
+
+type Timings = HashMap<&'static str, FilterTiming>;
+
+/// The timings of the filter evaluations of one run, for `--profile`. Clones share
+/// them.
+#[derive(Debug, Default, Clone)]
+pub struct Profile {
+    /// The timings of the recorders that are dropped
+    merged: Arc<Mutex<Timings>>,
+}
+
+impl Profile {
+    /// Returns a recorder for a single thread, so evaluations never wait for each
+    /// other. Its timings are merged into the profile when it is dropped.
+    pub fn recorder(&self) -> Recorder {
+        Recorder { profile: Some(self.clone()), timings: Timings::new() }
+    }
+
+    /// Returns the timings of the dropped recorders, the slowest filter first
+    pub fn timings(&self) -> Vec<(&'static str, FilterTiming)> {
+        let merged = self.merged.lock().unwrap();
+        let mut timings =
+            merged.iter().map(|(name, timing)| (*name, *timing)).collect::<Vec<_>>();
+        timings.sort_by(|(left_name, left), (right_name, right)| {
+            right.total.cmp(&left.total).then(left_name.cmp(right_name))
+        });
+        timings
+    }
+
+    /// Formats the timings as a table, one filter variant per line
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<20} {:>12} {:>12} {:>12}\n",
+            "Filter", "Evaluations", "Total", "Average"
+        );
+        for (name, timing) in self.timings() {
+            let average = timing.total.div_f64(timing.evaluations.max(1) as f64);
+            let _ = writeln!(
+                report,
+                "{name:<20} {:>12} {:>12} {:>12}",
+                timing.evaluations,
+                format!("{:.3?}", timing.total),
+                format!("{:.3?}", average)
+            );
+        }
+        report
+    }
+}
+
+/// Evaluates expressions on one thread, timing every filter if it records for a
+/// profile
+#[derive(Debug, Default)]
+pub struct Recorder {
+    profile: Option<Profile>,
+    timings: Timings,
+}
+
+impl Recorder {
+    /// A recorder for `profile`, or one that only evaluates without it
+    pub fn new(profile: Option<&Profile>) -> Self {
+        profile.map(Profile::recorder).unwrap_or_default()
+    }
+
+    pub fn evaluate<E: DirEntryWrapperExt>(
+        &mut self,
+        root: &ExpressionNode<Filter>,
+        entry: &E,
+    ) -> Result<bool, GenericError> {
+        if self.profile.is_none() {
+            return root.evaluate(entry);
+        }
+
+        evaluate_leaves(root, &mut |filter| {
+            self.measure(filter, || filter.evaluate(entry))
+        })
+    }
+
+    /// Evaluates `root` like `evaluate`, also returning the lines its `contains`
+    /// filters matched, see [`evaluate_match_lines`]
+    pub fn evaluate_match_lines<E: DirEntryWrapperExt>(
+        &mut self,
+        root: &ExpressionNode<Filter>,
+        entry: &E,
+    ) -> Result<Option<MatchLines>, GenericError> {
+        evaluate_match_lines(root, &mut |filter| {
+            self.measure(filter, || filter.match_lines(entry))
+        })
+    }
+
+    fn measure<T>(&mut self, filter: &Filter, evaluate: impl FnOnce() -> T) -> T {
+        if self.profile.is_none() {
+            return evaluate();
+        }
+
+        let started = Instant::now();
+        let result = evaluate();
+        let timing = FilterTiming { evaluations: 1, total: started.elapsed() };
+        self.timings.entry(filter.into()).or_default().add(timing);
+        result
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(profile) = &self.profile {
+            let mut merged = profile.merged.lock().unwrap();
+            for (name, timing) in self.timings.drain() {
+                merged.entry(name).or_default().add(timing);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluate::profile::{Profile, Recorder};
+    use crate::parse::parse_root;
+    use crate::test_utils::DirEntryMock;
+    use crate::walk::entry_type::EntryType;
+
+    #[test]
+    fn test_report() {
+        let profile = Profile::default();
+        let root = parse_root("name = *.rs and (size > 10 or depth < 3)").unwrap();
+        let entry = DirEntryMock::default()
+            .set_file("/tmp/main.rs".into())
+            .set_entry_type(EntryType::File)
+            .set_size(5);
+
+        // evaluated in another thread, so its timings are merged when it exits
+        let mut recorder = profile.recorder();
+        std::thread::spawn(move || assert!(recorder.evaluate(&root, &entry).unwrap()))
+            .join()
+            .unwrap();
+
+        let report = profile.report();
+        for name in ["Name", "Size", "Depth"] {
+            let line = report.lines().find(|line| line.starts_with(&format!("{name} ")));
+            assert!(line.is_some(), "{report}");
+        }
+        assert!(report.starts_with("Filter "));
+        assert_eq!(profile.timings()[0].1.evaluations, 1);
+
+        // every run starts from nothing
+        assert!(Profile::default().timings().is_empty());
+
+        let root = parse_root("name = *.rs").unwrap();
+        let entry = DirEntryMock::default().set_file("/tmp/main.rs".into());
+        assert!(Recorder::new(None).evaluate(&root, &entry).unwrap());
+        assert_eq!(profile.timings().len(), 3);
+    }
+}
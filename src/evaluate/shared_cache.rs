@@ -0,0 +1,86 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// How many entries a cache remembers before it starts over
+const DEFAULT_CAPACITY: usize = 64 * 1024;
+
+/// A map shared by the clones of the filter holding it. Every parsed filter gets a
+/// cache of its own, so it lives as long as the search. When `capacity` is reached
+/// the cache starts over. Caches never make filters unequal.
+pub struct SharedCache<K, V> {
+    capacity: usize,
+    entries: Arc<Mutex<HashMap<K, V>>>,
+}
+
+impl<K: Hash + Eq, V: Clone> SharedCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity {
+            entries.clear();
+        }
+        entries.insert(key, value);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+impl<K, V> Clone for SharedCache<K, V> {
+    fn clone(&self) -> Self {
+        Self { capacity: self.capacity, entries: Arc::clone(&self.entries) }
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Default for SharedCache<K, V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl<K, V> PartialEq for SharedCache<K, V> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<K, V> Eq for SharedCache<K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluate::shared_cache::SharedCache;
+
+    #[test]
+    fn test_capacity() {
+        let cache = SharedCache::new(2);
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        assert_eq!(cache.get(&1), Some("one"));
+
+        // a full cache starts over
+        cache.insert(3, "three");
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&1), None);
+
+        // clones share the entries
+        let clone = cache.clone();
+        clone.insert(4, "four");
+        assert_eq!(cache.get(&4), Some("four"));
+        assert!(cache == SharedCache::default());
+    }
+}
@@ -10,6 +10,12 @@ pub trait Evaluate<E: DirEntryWrapperExt> {
     fn evaluate(&self, entry: &E) -> Result<bool, GenericError>;
 }
 
+pub trait RequiresFileExt {
+    /// Whether only regular files can satisfy the expression, so other entries can
+    /// be skipped without evaluating it
+    fn requires_file(&self) -> bool;
+}
+
 pub trait DurationOffsetExt<T> {
     fn add_to(&self, absolute_time: T) -> T;
 }
@@ -0,0 +1,36 @@
+//! Find & Grep with an SQL-like query language.
+//!
+//! ```
+//! use fgr_rs::{search, SearchOptions};
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! std::fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+//! std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+//!
+//! let found = search(
+//!     &[dir.path().to_path_buf()],
+//!     "ext = txt and contains = *hello*",
+//!     SearchOptions::default(),
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(found, [dir.path().join("notes.txt")]);
+//! ```
+
+pub use crate::errors::GenericError;
+pub use crate::evaluate::traits::Evaluate;
+pub use crate::parse::parse_root;
+pub use crate::search::{search, search_each, SearchOptions};
+
+pub mod config;
+pub mod errors;
+pub mod evaluate;
+pub mod parse;
+pub mod printf;
+pub mod run;
+pub mod search;
+pub mod walk;
+
+pub mod r#macro;
+#[cfg(test)]
+pub mod test_utils;
@@ -1,25 +1,18 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use ignore::WalkBuilder;
 use nnf::traits::Render;
 
-use crate::config::Config;
-use crate::errors::GenericError;
-use crate::evaluate::traits::Evaluate;
-use crate::parse::parse_root;
-use crate::run::{set_int_handler, spawn_senders, EntryReceiver, ProcessStatus};
-
-pub mod config;
-pub mod errors;
-pub mod evaluate;
-pub mod parse;
-pub mod run;
-pub mod walk;
-
-pub mod r#macro;
-#[cfg(test)]
-pub mod test_utils;
+use fgr_rs::config::Config;
+use fgr_rs::errors::GenericError;
+use fgr_rs::evaluate::expression_node_impl::explain;
+use fgr_rs::run::{
+    read_paths, set_int_handler, spawn_path_senders, spawn_senders, spawn_watchdog,
+    EntryReceiver, ProcessStatus, SenderOptions, WalkStats,
+};
 
 fn main() {
     let config = match Config::build() {
@@ -30,38 +23,58 @@ fn main() {
         }
     };
 
+    for lint in config.lints.iter().filter(|lint| lint.warns()) {
+        eprintln!("Warning: {lint}");
+    }
+
     if config.print_expression_tree {
-        println!("{}", config.root);
-        println!("{}", config.root.render());
+        println!("{}", config.expression);
+        println!("{}", config.expression.render());
         std::process::exit(0);
     }
 
-    let mut dir_iter = config.start_dirs.iter();
-    let first_path = dir_iter.next().unwrap();
+    if config.explain {
+        print!("{}", explain(&config.root));
+        std::process::exit(0);
+    }
 
     let root_node = Arc::new(config.root.clone());
 
-    let mut builder = WalkBuilder::new(first_path);
-    builder.standard_filters(config.standard_filters);
-    config.hidden.map(|yes| builder.hidden(yes));
-    config.parents.map(|yes| builder.parents(yes));
-    config.ignore.map(|yes| builder.ignore(yes));
-    config.git_ignore.map(|yes| builder.git_ignore(yes));
-    config.git_global.map(|yes| builder.git_global(yes));
-    config.git_exclude.map(|yes| builder.git_exclude(yes));
-    config.same_filesystem.map(|yes| builder.same_file_system(yes));
-
-    builder.threads(config.threads);
-
-    let walk = builder.build_parallel();
-
     let (sender, receiver) = kanal::unbounded();
     let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+    let stats = Arc::new(WalkStats::default());
 
     set_int_handler(&status);
+    if let Some(timeout) = config.timeout {
+        spawn_watchdog(&status, timeout);
+    }
 
-    spawn_senders(&status, &root_node, sender, walk);
-
+    // the paths are read while they are evaluated
+    let files_from = config.files_from.clone().map(|files_from| {
+        let reader: Result<Box<dyn BufRead>, GenericError> =
+            if files_from.as_os_str() == "-" {
+                Ok(Box::new(std::io::stdin().lock()))
+            } else {
+                File::open(&files_from)
+                    .map(|file| Box::new(BufReader::new(file)) as Box<dyn BufRead>)
+                    .map_err(GenericError::from)
+            };
+        match reader {
+            Ok(reader) => (files_from, read_paths(reader, config.input_separator)),
+            Err(error) => {
+                eprintln!("Failed to read {}: {:?}", files_from.display(), error);
+                std::process::exit(1);
+            }
+        }
+    });
+    let prune_node = config.prune.clone().map(Arc::new);
+    let walks = files_from.is_none().then(|| {
+        config.walk_builders().iter().map(WalkBuilder::build_parallel).collect::<Vec<_>>()
+    });
+    let threads = config.threads;
+    let options = SenderOptions::from(&config);
+
+    // receive while walking, so the walk can stop as soon as the receiver is done
     let entry_receiver = EntryReceiver::new(
         config,
         1024 * 10,
@@ -69,10 +82,43 @@ fn main() {
         receiver,
         Duration::from_millis(100),
         &status,
+        &stats,
     );
-
     let handle = entry_receiver.receive_all();
 
+    let mut read_failed = false;
+    if let Some((files_from, paths)) = files_from {
+        if let Err(error) = spawn_path_senders(
+            &status,
+            &root_node,
+            &stats,
+            sender,
+            paths,
+            threads,
+            options.clone(),
+        ) {
+            eprintln!("Failed to read {}: {:?}", files_from.display(), error);
+            read_failed = true;
+        }
+    } else if let Some(walks) = walks {
+        // every walk is over before the next one starts, so their matches never mix
+        for walk in walks {
+            spawn_senders(
+                &status,
+                &root_node,
+                prune_node.as_ref(),
+                &stats,
+                sender.clone(),
+                walk,
+                options.clone(),
+            );
+        }
+        drop(sender);
+    }
+
     let status = handle.join().unwrap();
-    std::process::exit(status);
+    if let Some(profile) = &options.profile {
+        eprint!("{}", profile.report());
+    }
+    std::process::exit(if read_failed { 1 } else { status });
 }
@@ -0,0 +1,19 @@
+use std::fmt::{Display, Formatter};
+
+/// An access check for the current process, as opposed to raw permission bits
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Access {
+    Readable,
+    Writable,
+    Executable,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Readable => write!(f, "readable"),
+            Access::Writable => write!(f, "writable"),
+            Access::Executable => write!(f, "executable"),
+        }
+    }
+}
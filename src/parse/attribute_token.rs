@@ -1,25 +1,37 @@
 use std::collections::BTreeMap;
 use std::fs::Permissions;
-use std::ops::Deref;
 use std::os::unix::prelude::PermissionsExt;
+use std::sync::Mutex;
 
 use chrono::Duration;
 use lazy_static::lazy_static;
-use nom::branch::alt;
-use nom::character::complete::{alphanumeric1, digit1, multispace0};
-use nom::combinator::{map, map_res, opt};
+use nnf::parse_tree::ExpressionNode;
+use nnf::{e_and, e_leaf, e_not};
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{digit1, one_of};
+use nom::combinator::{map_res, opt};
 use nom::error::ErrorKind;
-use nom::sequence::terminated;
-use nom::IResult;
 use uzers::{Groups, Users, UsersCache};
 
+use crate::errors::{ParseError, ParseResult};
+use crate::evaluate::content_cache::ContentCache;
+use crate::evaluate::decode::ContentEncoding;
+use crate::evaluate::dir_sizes::DirSizes;
+use crate::evaluate::literal_finder::LiteralFinder;
+use crate::parse::access::Access;
 use crate::parse::comparison::Comparison;
+use crate::parse::contains_skip::ContainsSkip;
 use crate::parse::filter::Filter;
 use crate::parse::match_pattern::MatchPattern;
+use crate::parse::permission_mode::PermissionMode;
 use crate::parse::primitives::{
-    parse_comparison, parse_duration, parse_file_type, parse_pattern,
-    parse_positive_number, parse_size_unit,
+    parse_attribute_name, parse_comparison, parse_entry_kind, parse_file_type,
+    parse_flag, parse_negatable_pattern, parse_path_argument, parse_positive_number,
+    parse_size_or_bytes, parse_size_unit, parse_string_argument, parse_substring_pattern,
+    parse_symbolic_mode, parse_time_attribute, parse_time_point, parse_time_span,
 };
+use crate::parse::size_unit::SizeUnit;
+use crate::parse::time_point::TimePoint;
 use crate::parse::traits::GenericParser;
 use crate::parse::util::{prepare_enum_map, ws};
 use crate::{mk_filter_enum, GenericError};
@@ -28,47 +40,43 @@ lazy_static! {
     static ref SORTED_IDENTIFIERS: BTreeMap<&'static str, &'static str> =
         prepare_enum_map::<AttributeToken>();
 
-    // SAFETY: We will not share the UserCache instance and use it once while parsing a query
-    // in the main thread
-    static ref USERS: UnsafeWrapper<UsersCache> = unsafe {
-        UnsafeWrapper::new(UsersCache::new())
-    };
-}
-
-struct UnsafeWrapper<T> {
-    inner: T,
-}
-
-impl<T> Deref for UnsafeWrapper<T> {
-    type Target = T;
-
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
-}
-
-impl<T> UnsafeWrapper<T> {
-    unsafe fn new(inner: T) -> Self {
-        Self { inner }
-    }
+    // queries can be parsed on any thread, and the cache is not Sync on its own
+    static ref USERS: Mutex<UsersCache> = Mutex::new(UsersCache::new());
 }
 
-unsafe impl<T> Send for UnsafeWrapper<T> {}
-unsafe impl<T> Sync for UnsafeWrapper<T> {}
-
 #[cfg(not(test))]
 mk_filter_enum!(AttributeToken, ATTRIBUTE_TOKEN_ALIASES, [
     Name: "name",
     ModificationTime: "mtime",
     AccessTime: "atime",
     Size: "size",
+    DirSize: "dirsize",
     Extension: "ext", "extension",
+    Stem: "stem",
+    Parent: "parent",
+    Path: "path",
     Contains: "contains",
     Depth: "depth",
     Permissions: "permissions", "perms", "perm",
     Group: "group",
-    User: "user",
-    Type: "type"
+    User: "user", "owner",
+    Type: "type",
+    Kind: "kind",
+    Empty: "empty",
+    Children: "children", "entries",
+    Binary: "binary",
+    Text: "text",
+    Newer: "newer",
+    Older: "older",
+    Inode: "inode", "ino",
+    Device: "dev", "device",
+    Xattr: "xattr",
+    SymlinkTarget: "target", "linkto",
+    Readable: "readable",
+    Writable: "writable",
+    Executable: "executable",
+    Mine: "mine",
+    MyGroup: "my-group", "mygroup"
 ]);
 
 #[cfg(test)]
@@ -77,67 +85,221 @@ mk_filter_enum!(AttributeToken, ATTRIBUTE_TOKEN_ALIASES, [
     ModificationTime: "mtime",
     AccessTime: "atime",
     Size: "size",
+    DirSize: "dirsize",
     Extension: "ext", "extension",
+    Stem: "stem",
+    Parent: "parent",
+    Path: "path",
     Contains: "contains",
     Depth: "depth",
     Permissions: "permissions", "perms", "perm",
     Group: "group",
-    User: "user",
+    User: "user", "owner",
     Type: "type",
+    Kind: "kind",
+    Empty: "empty",
+    Children: "children", "entries",
+    Binary: "binary",
+    Text: "text",
+    Newer: "newer",
+    Older: "older",
+    Inode: "inode", "ino",
+    Device: "dev", "device",
+    Xattr: "xattr",
+    SymlinkTarget: "target", "linkto",
+    Readable: "readable",
+    Writable: "writable",
+    Executable: "executable",
+    Mine: "mine",
+    MyGroup: "my-group", "mygroup",
     Bool: "bool"
 ]);
 
 fn parse_comparison_and_pattern(
     input: &str,
-) -> IResult<&str, (Comparison, MatchPattern)> {
-    let (input, comparison) = parse_comparison(input)?;
-    let (input, pattern) = parse_pattern(input)?;
+) -> ParseResult<'_, (Comparison, MatchPattern)> {
+    if let Ok(parsed) = parse_substring_pattern(input) {
+        return Ok(parsed);
+    }
+
+    let (input, mut comparison) = parse_comparison(input)?;
+    let (input, (negated, pattern)) = parse_negatable_pattern(input)?;
+    if negated {
+        comparison.negate();
+    }
 
     Ok((input, (comparison, pattern)))
 }
 
-fn parse_comparison_and_duration(input: &str) -> IResult<&str, (Comparison, Duration)> {
+fn parse_comparison_and_time(input: &str) -> ParseResult<'_, (Comparison, TimePoint)> {
     let (input, comparison) = parse_comparison(input)?;
-    let (input, duration) = parse_duration(input)?;
+    let (input, time) = parse_time_point(input)?;
 
-    Ok((input, (comparison, duration)))
+    Ok((input, (comparison, time)))
 }
 
-fn filter_eq_neq(input: &str, comparison: Comparison) -> IResult<&str, Comparison> {
+fn filter_eq_neq(input: &str, comparison: Comparison) -> ParseResult<'_, Comparison> {
     if comparison != Comparison::Eq && comparison != Comparison::Neq {
-        return Err(nom::Err::Failure(nom::error::Error::new(input, ErrorKind::Fail)));
+        return Err(nom::Err::Failure(ParseError::new(input, ErrorKind::Fail)));
     }
     Ok((input, comparison))
 }
 
 fn get_user(name: &str) -> Result<u32, GenericError> {
-    if let Some(value) = USERS.get_user_by_name(name).map(|user| user.uid() as u32) {
+    if let Some(value) = USERS.lock().unwrap().get_user_by_name(name).map(|user| user.uid()) {
         return Ok(value);
     }
 
-    Err(GenericError::WrongTokenType(name.to_string()))
+    Err(GenericError::UnknownUser(name.to_string()))
 }
 
 fn get_group(name: &str) -> Result<u32, GenericError> {
-    if let Some(value) = USERS.get_group_by_name(name).map(|user| user.gid() as u32) {
+    if let Some(value) = USERS.lock().unwrap().get_group_by_name(name).map(|user| user.gid()) {
         return Ok(value);
     }
 
-    Err(GenericError::WrongTokenType(name.to_string()))
+    Err(GenericError::UnknownGroup(name.to_string()))
 }
 
+/// Parses a numeric id or a name made of the characters allowed in POSIX user and
+/// group names; a name that does not resolve is a fatal error
 fn parse_user_or_group(
     f: fn(&str) -> Result<u32, GenericError>,
-) -> impl FnMut(&str) -> IResult<&str, u32> {
+) -> impl FnMut(&str) -> ParseResult<'_, u32> {
     move |input: &str| {
-        alt((map(parse_positive_number, |num| num as u32), map_res(alphanumeric1, f)))(
-            input,
-        )
+        if let Ok((rest, number)) = ws(parse_positive_number)(input) {
+            return Ok((rest, number as u32));
+        }
+
+        let (rest, name) = ws(take_while1(|ch: char| {
+            ch.is_ascii_alphanumeric() || "_-.$".contains(ch)
+        }))(input)?;
+
+        match f(name) {
+            Ok(id) => Ok((rest, id)),
+            Err(cause) => Err(ParseError::fatal(input, cause)),
+        }
+    }
+}
+
+fn reject_reversed(input: &str) -> nom::Err<ParseError<'_>> {
+    nom::Err::Error(ParseError::new(input, ErrorKind::Verify))
+}
+
+/// Parses the reversed `value op attribute` form (`1M < size`, `now - 1d > mtime`)
+/// into the same filter as `attribute op' value`, where `op'` is the flipped `op`.
+/// Only size, depth and time attributes can be written this way.
+pub fn parse_reversed_filter(input: &str) -> ParseResult<'_, Filter> {
+    if let Ok((rest, value)) = parse_time_point(input) {
+        let (rest, mut comparison) = parse_comparison(rest)?;
+        let (rest, attribute) = parse_attribute_name(rest)?;
+        comparison.flip();
+
+        return match attribute {
+            AttributeToken::AccessTime => {
+                Ok((rest, Filter::AccessTime { value, comparison }))
+            }
+            AttributeToken::ModificationTime => {
+                Ok((rest, Filter::ModificationTime { value, comparison }))
+            }
+            _ => Err(reject_reversed(input)),
+        };
+    }
+
+    let (rest, number) = ws(parse_positive_number)(input)?;
+    let (rest, unit) = opt(parse_size_unit)(rest)?;
+    let (rest, mut comparison) = parse_comparison(rest)?;
+    let (rest, attribute) = parse_attribute_name(rest)?;
+    comparison.flip();
+
+    match (attribute, unit) {
+        (AttributeToken::Size, unit) => {
+            let unitless = unit.is_none();
+            let value = unit.unwrap_or(SizeUnit::Byte).to_bytes(number);
+            Ok((rest, Filter::Size { value, comparison, unitless }))
+        }
+        (AttributeToken::DirSize, unit) => {
+            let value = unit.unwrap_or(SizeUnit::Byte).to_bytes(number);
+            Ok((rest, Filter::DirSize { value, comparison, sizes: DirSizes::default() }))
+        }
+        (AttributeToken::Depth, None) => {
+            Ok((rest, Filter::Depth { value: number, comparison }))
+        }
+        _ => Err(reject_reversed(input)),
+    }
+}
+
+/// Parses `mtime within 1h` (or `atime`) into `mtime >= now - 1h and mtime <= now`.
+/// Both bounds are separate leaves, so negating the window still works with to_nnf.
+pub fn parse_within_filter(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
+    let (rest, attribute) = parse_attribute_name(input)?;
+    let filter: fn(TimePoint, Comparison) -> Filter = match attribute {
+        AttributeToken::AccessTime => {
+            |value, comparison| Filter::AccessTime { value, comparison }
+        }
+        AttributeToken::ModificationTime => {
+            |value, comparison| Filter::ModificationTime { value, comparison }
+        }
+        _ => return Err(nom::Err::Error(ParseError::new(input, ErrorKind::Tag))),
+    };
+
+    let (rest, _) = ws(tag("within"))(rest)?;
+    let (rest, span) = parse_time_span(rest)?;
+
+    let since = filter((-span).into(), Comparison::Gte);
+    let until = filter(Duration::zero().into(), Comparison::Lte);
+
+    Ok((rest, e_and!(e_leaf!(since), e_leaf!(until))))
+}
+
+/// Parses a comparison of two timestamps of the same entry, e.g. `atime < mtime`
+/// for files not read since they were last modified
+pub fn parse_attribute_comparison_filter(
+    input: &str,
+) -> ParseResult<'_, ExpressionNode<Filter>> {
+    let (rest, left) = parse_time_attribute(input)?;
+    let (rest, comparison) = parse_comparison(rest)?;
+    let (rest, right) = parse_time_attribute(rest)?;
+
+    Ok((rest, e_leaf!(Filter::CompareAttrs { left, right, comparison })))
+}
+
+/// Parses an inclusive size range, `size = 1Mb..10Mb`, into the bounds it has:
+/// `1Mb..` and `..10Mb` are open-ended. `!=` matches the sizes outside the range.
+pub fn parse_size_range_filter(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
+    let (rest, attribute) = parse_attribute_name(input)?;
+    if attribute != AttributeToken::Size {
+        return Err(nom::Err::Error(ParseError::new(input, ErrorKind::Tag)));
+    }
+
+    let (rest, comparison) = parse_comparison(rest)?;
+    let (rest, lower) = opt(parse_size_or_bytes)(rest)?;
+    let (rest, _) = ws(tag(".."))(rest)?;
+    let (rest, upper) = opt(parse_size_or_bytes)(rest)?;
+    let (rest, comparison) = filter_eq_neq(rest, comparison)?;
+
+    let bound = |(value, unitless), comparison| {
+        e_leaf!(Filter::Size { value, comparison, unitless })
+    };
+    let range = match (lower, upper) {
+        (Some(lower), Some(upper)) => {
+            e_and!(bound(lower, Comparison::Gte), bound(upper, Comparison::Lte))
+        }
+        (Some(lower), None) => bound(lower, Comparison::Gte),
+        (None, Some(upper)) => bound(upper, Comparison::Lte),
+        (None, None) => {
+            return Err(nom::Err::Failure(ParseError::new(rest, ErrorKind::Fail)))
+        }
+    };
+
+    match comparison {
+        Comparison::Neq => Ok((rest, e_not!(range))),
+        _ => Ok((rest, range)),
     }
 }
 
 impl GenericParser for AttributeToken {
-    fn parse(self, input: &str) -> IResult<&str, Filter> {
+    fn parse(self, input: &str) -> ParseResult<'_, Filter> {
         Ok(match self {
             Self::Name => {
                 let (input, (comparison, pattern)) = parse_comparison_and_pattern(input)?;
@@ -151,17 +313,65 @@ impl GenericParser for AttributeToken {
 
                 (input, Filter::Extension { value: pattern, comparison })
             }
+            Self::Stem => {
+                let (input, (comparison, pattern)) = parse_comparison_and_pattern(input)?;
+                let (input, comparison) = filter_eq_neq(input, comparison)?;
+
+                (input, Filter::Stem { value: pattern, comparison })
+            }
+            Self::Parent => {
+                let (input, (comparison, pattern)) = parse_comparison_and_pattern(input)?;
+                let (input, comparison) = filter_eq_neq(input, comparison)?;
+
+                (input, Filter::Parent { value: pattern, comparison })
+            }
+            Self::Path => {
+                // a `~` substring crosses directories whatever the separator mode
+                let (input, (comparison, pattern)) = match parse_substring_pattern(input)
+                {
+                    Ok(parsed) => parsed,
+                    Err(_) => {
+                        let (input, (comparison, pattern)) =
+                            parse_comparison_and_pattern(input)?;
+                        let pattern = pattern
+                            .with_literal_separator(true)
+                            .map_err(|err| ParseError::fatal(input, err))?;
+                        (input, (comparison, pattern))
+                    }
+                };
+                let (input, comparison) = filter_eq_neq(input, comparison)?;
+
+                (input, Filter::Path { value: pattern, comparison })
+            }
             Self::Contains => {
                 let (input, (comparison, pattern)) = parse_comparison_and_pattern(input)?;
                 let (input, comparison) = filter_eq_neq(input, comparison)?;
 
-                (input, Filter::Contains { value: pattern, comparison })
+                (
+                    input,
+                    Filter::Contains {
+                        value: pattern,
+                        comparison,
+                        max_bytes: None,
+                        read_timeout: None,
+                        io_retries: 0,
+                        encoding: ContentEncoding::Utf8,
+                        skip: ContainsSkip::default(),
+                        cache: ContentCache::default(),
+                        finder: LiteralFinder::default(),
+                    },
+                )
+            }
+            Self::Mine | Self::MyGroup => {
+                let (input, comparison) = parse_flag(input)?;
+
+                (input, Filter::Mine { group: self == Self::MyGroup, comparison })
             }
             Self::Group => {
                 let (input, comparison) = parse_comparison(input)?;
                 let (input, value) = parse_user_or_group(get_group)(input)?;
 
-                (input, Filter::User { comparison, value })
+                (input, Filter::Group { comparison, value })
             }
             Self::User => {
                 let (input, comparison) = parse_comparison(input)?;
@@ -171,23 +381,24 @@ impl GenericParser for AttributeToken {
             }
 
             Self::AccessTime => {
-                let (input, (comparison, duration)) =
-                    parse_comparison_and_duration(input)?;
-                (input, Filter::AccessTime { value: duration, comparison })
+                let (input, (comparison, value)) = parse_comparison_and_time(input)?;
+                (input, Filter::AccessTime { value, comparison })
             }
             Self::ModificationTime => {
-                let (input, (comparison, duration)) =
-                    parse_comparison_and_duration(input)?;
-                (input, Filter::ModificationTime { value: duration, comparison })
+                let (input, (comparison, value)) = parse_comparison_and_time(input)?;
+                (input, Filter::ModificationTime { value, comparison })
             }
             Self::Size => {
                 let (input, comparison) = parse_comparison(input)?;
-                let (input, number) =
-                    terminated(parse_positive_number, opt(multispace0))(input)?;
-                let (input, unit) = parse_size_unit(input)?;
-                let num_bytes = unit.to_bytes(number);
+                let (input, (num_bytes, unitless)) = parse_size_or_bytes(input)?;
+
+                (input, Filter::Size { value: num_bytes, comparison, unitless })
+            }
+            Self::DirSize => {
+                let (input, comparison) = parse_comparison(input)?;
+                let (input, (value, _)) = parse_size_or_bytes(input)?;
 
-                (input, Filter::Size { value: num_bytes, comparison })
+                (input, Filter::DirSize { value, comparison, sizes: DirSizes::default() })
             }
             Self::Depth => {
                 let (input, comparison) = parse_comparison(input)?;
@@ -195,25 +406,156 @@ impl GenericParser for AttributeToken {
 
                 (input, Filter::Depth { value, comparison })
             }
-            Self::Permissions => {
+            Self::Children => {
                 let (input, comparison) = parse_comparison(input)?;
+                let (input, value) = ws(parse_positive_number)(input)?;
 
-                let (input, mode) =
-                    map_res(ws(digit1), |value| u32::from_str_radix(value, 8))(input)?;
+                (input, Filter::ChildCount { value, comparison })
+            }
+            Self::Inode => {
+                let (input, comparison) = parse_comparison(input)?;
+                let (input, value) = ws(parse_positive_number)(input)?;
 
-                let value = Permissions::from_mode(mode);
+                (input, Filter::Inode { value: value as u64, comparison })
+            }
+            Self::Device => {
+                let (input, comparison) = parse_comparison(input)?;
+                let (input, value) = ws(parse_positive_number)(input)?;
+
+                (input, Filter::Device { value: value as u64, comparison })
+            }
+            Self::SymlinkTarget => {
+                let (input, (comparison, pattern)) = parse_comparison_and_pattern(input)?;
+                let (input, comparison) = filter_eq_neq(input, comparison)?;
 
-                (input, Filter::Permissions { value, comparison })
+                (input, Filter::SymlinkTarget { value: pattern, comparison })
+            }
+            Self::Xattr => {
+                let (input, comparison) = parse_comparison(input)?;
+                let (input, comparison) = filter_eq_neq(input, comparison)?;
+                let (input, argument) = parse_string_argument(input)?;
+
+                let (name, value) = match argument.split_once(':') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (argument, None),
+                };
+                (input, Filter::Xattr { name: name.to_string(), value, comparison })
+            }
+            Self::Permissions => {
+                let (input, comparison) = parse_comparison(input)?;
+                if let Ok((input, (bits, added))) = parse_symbolic_mode(input) {
+                    let (input, mut comparison) = filter_eq_neq(input, comparison)?;
+                    // u+x requires all of the bits to be set, go-w none of them
+                    let mode = if added {
+                        PermissionMode::AllSet
+                    } else {
+                        comparison.negate();
+                        PermissionMode::AnySet
+                    };
+                    let value = Permissions::from_mode(bits);
+
+                    return Ok((input, Filter::Permissions { value, mode, comparison }));
+                }
+
+                let (input, prefix) = opt(ws(one_of("-/")))(input)?;
+
+                let mode = match prefix {
+                    None => PermissionMode::Exact,
+                    Some('-') => PermissionMode::AllSet,
+                    Some(_) => PermissionMode::AnySet,
+                };
+                let (input, comparison) = if mode == PermissionMode::Exact {
+                    (input, comparison)
+                } else {
+                    filter_eq_neq(input, comparison)?
+                };
+
+                let (input, bits) =
+                    map_res(digit1, |value| u32::from_str_radix(value, 8))(input)?;
+                let value = Permissions::from_mode(bits);
+
+                (input, Filter::Permissions { value, mode, comparison })
             }
             Self::Type => {
                 let (input, comparison) = parse_comparison(input)?;
                 let (input, value) = ws(parse_file_type)(input)?;
 
-                (input, Filter::Type { value, comparison })
+                (
+                    input,
+                    Filter::Type {
+                        value,
+                        comparison,
+                        read_timeout: None,
+                        io_retries: 0,
+                        cache: ContentCache::default(),
+                    },
+                )
+            }
+            Self::Kind => {
+                let (input, comparison) = parse_comparison(input)?;
+                let (input, comparison) = filter_eq_neq(input, comparison)?;
+                let (input, value) = ws(parse_entry_kind)(input)?;
+
+                (input, Filter::Kind { value, comparison })
+            }
+            Self::Newer | Self::Older => {
+                let (input, comparison) = parse_comparison(input)?;
+                let (input, mut comparison) = filter_eq_neq(input, comparison)?;
+                let (input, value) = parse_path_argument(input)?;
+
+                let mtime =
+                    match value.metadata().and_then(|metadata| metadata.modified()) {
+                        Ok(mtime) => mtime,
+                        Err(err) => {
+                            let cause = GenericError::ReferenceFileError(value, err);
+                            return Err(ParseError::fatal(input, cause));
+                        }
+                    };
+
+                if comparison == Comparison::Eq {
+                    comparison =
+                        if self == Self::Newer { Comparison::Gt } else { Comparison::Lt };
+                } else {
+                    comparison = if self == Self::Newer {
+                        Comparison::Lte
+                    } else {
+                        Comparison::Gte
+                    };
+                }
+
+                (input, Filter::NewerThan { value, mtime, comparison })
+            }
+            Self::Readable | Self::Writable | Self::Executable => {
+                let (input, comparison) = parse_flag(input)?;
+                let value = match self {
+                    Self::Readable => Access::Readable,
+                    Self::Writable => Access::Writable,
+                    _ => Access::Executable,
+                };
+
+                (input, Filter::Access { value, comparison })
+            }
+            Self::Empty => {
+                let (input, comparison) = parse_flag(input)?;
+
+                (input, Filter::Empty { dirs: true, comparison })
+            }
+            Self::Binary | Self::Text => {
+                let (input, comparison) = parse_flag(input)?;
+
+                (
+                    input,
+                    Filter::Binary {
+                        text: self == Self::Text,
+                        comparison,
+                        read_timeout: None,
+                    },
+                )
             }
 
             #[cfg(test)]
             Self::Bool => {
+                use nom::branch::alt;
                 use nom::bytes::complete::tag;
 
                 let (input, comparison) = parse_comparison(input)?;
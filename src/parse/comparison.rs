@@ -21,6 +21,18 @@ impl Comparison {
             Self::Neq => Self::Eq,
         }
     }
+
+    /// Mirrors the comparison for swapped operands: `a < b` is the same as `b > a`
+    pub fn flip(&mut self) {
+        *self = match self {
+            Self::Lt => Self::Gt,
+            Self::Gt => Self::Lt,
+            Self::Lte => Self::Gte,
+            Self::Gte => Self::Lte,
+            Self::Eq => Self::Eq,
+            Self::Neq => Self::Neq,
+        }
+    }
 }
 
 impl TryFrom<&str> for Comparison {
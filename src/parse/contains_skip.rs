@@ -0,0 +1,102 @@
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use lazy_static::lazy_static;
+
+use crate::errors::GenericError;
+
+/// Pseudo-files `contains` does not read unless `--contains-skip` replaces them:
+/// reading pagemap gets the process OOM killed, and kcore is as large as the memory
+pub const DEFAULT_CONTAINS_SKIP: [&str; 2] = ["/proc/**/pagemap", "/proc/kcore"];
+
+lazy_static! {
+    static ref DEFAULT: ContainsSkip =
+        ContainsSkip::new(&DEFAULT_CONTAINS_SKIP).expect("the default globs compile");
+}
+
+/// The paths `contains` never reads. Two sets are equal if they were built from the
+/// same globs.
+#[derive(Clone)]
+pub struct ContainsSkip {
+    globs: Vec<String>,
+    set: Arc<GlobSet>,
+}
+
+impl ContainsSkip {
+    /// Compiles `globs`; empty globs are left out, so an empty one alone skips
+    /// nothing
+    pub fn new(globs: &[impl AsRef<str>]) -> Result<Self, GenericError> {
+        let globs = globs
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|glob| !glob.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let mut builder = GlobSetBuilder::new();
+        for glob in &globs {
+            let compiled = Glob::new(glob)
+                .map_err(|err| GenericError::InvalidGlob(glob.clone(), err))?;
+            builder.add(compiled);
+        }
+        let set = builder
+            .build()
+            .map_err(|err| GenericError::InvalidGlob(globs.join(", "), err))?;
+
+        Ok(Self { globs, set: Arc::new(set) })
+    }
+
+    pub fn is_match(&self, path: &Path) -> bool {
+        self.set.is_match(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+    }
+}
+
+/// [`DEFAULT_CONTAINS_SKIP`]
+impl Default for ContainsSkip {
+    fn default() -> Self {
+        DEFAULT.clone()
+    }
+}
+
+impl PartialEq for ContainsSkip {
+    fn eq(&self, other: &Self) -> bool {
+        self.globs == other.globs
+    }
+}
+
+impl Eq for ContainsSkip {}
+
+impl Debug for ContainsSkip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ContainsSkip").field(&self.globs).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::errors::GenericError;
+    use crate::parse::contains_skip::ContainsSkip;
+
+    #[test]
+    fn test_contains_skip() {
+        let skip = ContainsSkip::default();
+        assert!(skip.is_match(Path::new("/proc/1/pagemap")));
+        assert!(!skip.is_match(Path::new("/proc/1/status")));
+
+        let skip = ContainsSkip::new(&["/sys/**", ""]).unwrap();
+        assert!(skip.is_match(Path::new("/sys/kernel/notes")));
+        assert_eq!(skip, ContainsSkip::new(&["/sys/**"]).unwrap());
+        assert_ne!(skip, ContainsSkip::default());
+
+        assert!(ContainsSkip::new(&[""]).unwrap().is_empty());
+        assert!(matches!(ContainsSkip::new(&["["]), Err(GenericError::InvalidGlob(..))));
+    }
+}
@@ -0,0 +1,234 @@
+use nnf::parse_tree::ExpressionNode;
+
+use crate::errors::GenericError;
+use crate::parse::filter::Filter;
+use crate::parse::{parse_attribute, parse_root};
+
+/// Parses `input` like [`parse_root`], but on failure keeps going and reports every
+/// problem it can find: unbalanced parentheses and each filter that fails to parse.
+/// Every error is wrapped in [`GenericError::ErrorAt`] with its byte offset.
+pub fn parse_root_all(input: &str) -> Result<ExpressionNode<Filter>, Vec<GenericError>> {
+    let error = match parse_root(input) {
+        Ok(expression) => return Ok(expression),
+        Err(error) => error,
+    };
+
+    let mut errors = vec![];
+    let mut depth = vec![];
+
+    for token in tokenize(input) {
+        match token {
+            Token::Open(offset) => depth.push(offset),
+            Token::Close(offset) => {
+                if depth.pop().is_none() {
+                    errors.push(error_at(offset, GenericError::UnmatchedParen(')')));
+                }
+            }
+            Token::Clause(offset, clause) => {
+                if let Err(error) = check_clause(input, offset, clause) {
+                    errors.push(error);
+                }
+            }
+        }
+    }
+    for offset in depth {
+        errors.push(error_at(offset, GenericError::UnmatchedParen('(')));
+    }
+
+    // the filters and parentheses are fine, so the operators are misplaced
+    if errors.is_empty() {
+        errors.push(error_at(0, error));
+    }
+    errors.sort_by_key(|error| match error {
+        GenericError::ErrorAt(offset, _) => *offset,
+        _ => 0,
+    });
+
+    Err(errors)
+}
+
+fn error_at(offset: usize, error: GenericError) -> GenericError {
+    GenericError::ErrorAt(offset, Box::new(error))
+}
+
+fn check_clause(input: &str, offset: usize, clause: &str) -> Result<(), GenericError> {
+    // fatal errors built from a `GenericError` do not point into the input
+    let offset_of = |rest: &str| {
+        let position = rest.as_ptr() as usize - input.as_ptr() as usize;
+        if rest.is_empty() || position > input.len() {
+            offset
+        } else {
+            position
+        }
+    };
+
+    match parse_attribute(clause) {
+        Ok((rest, _)) if rest.trim().is_empty() => Ok(()),
+        Ok((rest, _)) => {
+            let rest = rest.trim_start();
+            Err(error_at(
+                offset_of(rest),
+                GenericError::SomeTokensWereNotParsed(rest.to_string()),
+            ))
+        }
+        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+            let position = offset_of(error.input);
+            Err(error_at(position, nom::Err::Failure(error).into()))
+        }
+        Err(error) => Err(error_at(offset, error.into())),
+    }
+}
+
+enum Token<'a> {
+    Open(usize),
+    Close(usize),
+    Clause(usize, &'a str),
+}
+
+/// Splits the query into parentheses and the filters between the `and`, `or` and
+/// `not` keywords, leaving quoted values intact
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut clause_start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+
+    let mut chars = input.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        if let Some(open) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == open {
+                quote = None;
+            }
+            continue;
+        }
+
+        match ch {
+            '"' | '\'' if opens_string(input, index) => quote = Some(ch),
+            '(' | ')' => {
+                push_clause(&mut tokens, input, clause_start, index);
+                tokens.push(if ch == '(' {
+                    Token::Open(index)
+                } else {
+                    Token::Close(index)
+                });
+                clause_start = index + 1;
+            }
+            _ if ch.is_whitespace() || index == 0 => {
+                let word_start = if ch.is_whitespace() { index + 1 } else { index };
+                let Some(keyword) = ["and", "or", "not"]
+                    .into_iter()
+                    .find(|keyword| is_keyword_at(input, word_start, keyword))
+                else {
+                    continue;
+                };
+
+                push_clause(&mut tokens, input, clause_start, word_start);
+                clause_start = word_start + keyword.len();
+                while chars.peek().is_some_and(|(index, _)| *index < clause_start) {
+                    chars.next();
+                }
+            }
+            _ => {}
+        }
+    }
+    push_clause(&mut tokens, input, clause_start, input.len());
+
+    tokens
+}
+
+/// Whether the quote at `index` opens a string. Like the parser, only a value right
+/// after an operator can be quoted, optionally with the `r` and `i` prefixes, so the
+/// apostrophe in an unquoted `name = don't*` is a part of the glob.
+fn opens_string(input: &str, index: usize) -> bool {
+    let before = &input[..index];
+    let before = ["ri", "r", "i"]
+        .into_iter()
+        .find_map(|prefix| before.strip_suffix(prefix))
+        .unwrap_or(before);
+    before.trim_end().ends_with(['=', '<', '>', '~'])
+}
+
+fn push_clause<'a>(
+    tokens: &mut Vec<Token<'a>>,
+    input: &'a str,
+    start: usize,
+    end: usize,
+) {
+    let clause = &input[start..end];
+    let trimmed = clause.trim_start();
+    if !trimmed.trim_end().is_empty() {
+        let offset = start + clause.len() - trimmed.len();
+        tokens.push(Token::Clause(offset, trimmed.trim_end()));
+    }
+}
+
+fn is_keyword_at(input: &str, start: usize, keyword: &str) -> bool {
+    let Some(rest) = input.get(start..) else {
+        return false;
+    };
+    rest.starts_with(keyword)
+        && rest[keyword.len()..]
+            .chars()
+            .next()
+            .is_none_or(|ch| ch.is_whitespace() || ch == '(')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::GenericError;
+    use crate::parse::diagnostics::parse_root_all;
+
+    fn offsets(input: &str) -> Vec<usize> {
+        parse_root_all(input)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| match error {
+                GenericError::ErrorAt(offset, _) => offset,
+                error => panic!("{error:?} has no offset"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_query() {
+        assert!(
+            parse_root_all("name = 'a and b' and (not size > 1 or depth < 2)").is_ok()
+        );
+    }
+
+    #[test]
+    fn test_two_errors() {
+        let query = "colour = red and size > 10Qb";
+        let errors = parse_root_all(query).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert_eq!(offsets(query), [0, 26]);
+        assert!(matches!(
+            &errors[1],
+            GenericError::ErrorAt(_, error)
+                if matches!(**error, GenericError::SomeTokensWereNotParsed(ref rest) if rest == "Qb")
+        ));
+    }
+
+    #[test]
+    fn test_unmatched_parens() {
+        assert_eq!(offsets("(name = a or depth > 1"), [0]);
+        assert_eq!(offsets("name = a) or ext = rs and (mtime"), [8, 26, 27]);
+    }
+
+    #[test]
+    fn test_apostrophe_in_unquoted_glob() {
+        assert!(parse_root_all("name = don't* and ext = rs").is_ok());
+        assert_eq!(offsets("name = don't* and size > 10Qb"), [27]);
+        assert_eq!(offsets("name = \"it's\" and size > 10Qb"), [27]);
+        assert_eq!(offsets("name = ri'a and b' and size > 10Qb"), [32]);
+    }
+
+    #[test]
+    fn test_misplaced_operator() {
+        assert_eq!(offsets("name = a and and ext = rs"), [0]);
+    }
+}
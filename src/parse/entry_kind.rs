@@ -0,0 +1,43 @@
+use crate::mk_filter_enum;
+use crate::walk::entry_type::EntryType;
+
+mk_filter_enum!(EntryKind, ENTRY_KIND_ALIASES, [
+    Dir: "dir", "directory", "d",
+    File: "file", "f",
+    Symlink: "symlink", "link", "l",
+    Socket: "socket", "s",
+    Fifo: "fifo", "pipe", "p",
+    Block: "block", "b",
+    Char: "char", "c"
+]);
+
+impl EntryKind {
+    /// The kind for a letter of find's `-type`: f, d, l, s, p, b or c
+    pub fn from_find_letter(letter: &str) -> Option<Self> {
+        match letter {
+            "f" => Some(Self::File),
+            "d" => Some(Self::Dir),
+            "l" => Some(Self::Symlink),
+            "s" => Some(Self::Socket),
+            "p" => Some(Self::Fifo),
+            "b" => Some(Self::Block),
+            "c" => Some(Self::Char),
+            _ => None,
+        }
+    }
+
+    /// Whether an entry of `entry_type` is of this kind; stdin and entries of an
+    /// unknown type are of no kind
+    pub fn matches(&self, entry_type: &EntryType) -> bool {
+        matches!(
+            (self, entry_type),
+            (Self::Dir, EntryType::Dir)
+                | (Self::File, EntryType::File)
+                | (Self::Symlink, EntryType::Symlink)
+                | (Self::Socket, EntryType::Socket)
+                | (Self::Fifo, EntryType::FIFO)
+                | (Self::Block, EntryType::BlockDevice)
+                | (Self::Char, EntryType::CharDevice)
+        )
+    }
+}
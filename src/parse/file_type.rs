@@ -14,6 +14,31 @@ mk_filter_enum!(FileType, FILE_TYPE_ALIASES, [
     Custom: "custom"
 ]);
 
+impl FileType {
+    /// Guesses the category from a lowercase file extension without reading the file
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Some(match extension {
+            "apk" | "class" | "deb" | "dex" | "dll" | "elf" | "exe" | "msi" | "o"
+            | "rpm" | "so" | "wasm" => Self::App,
+            "7z" | "ar" | "br" | "bz2" | "cab" | "cpio" | "gz" | "lz" | "lz4" | "rar"
+            | "tar" | "tgz" | "xz" | "z" | "zip" | "zst" => Self::Archive,
+            "aac" | "aiff" | "amr" | "ape" | "flac" | "m4a" | "mid" | "midi" | "mp3"
+            | "ogg" | "opus" | "wav" | "wma" => Self::Audio,
+            "epub" | "mobi" => Self::Book,
+            "doc" | "docx" | "odp" | "ods" | "odt" | "pdf" | "ppt" | "pptx" | "rtf"
+            | "xls" | "xlsx" => Self::Doc,
+            "otf" | "ttc" | "ttf" | "woff" | "woff2" => Self::Font,
+            "avif" | "bmp" | "cr2" | "gif" | "heic" | "heif" | "ico" | "jpeg" | "jpg"
+            | "jxl" | "png" | "psd" | "svg" | "tif" | "tiff" | "webp" => Self::Image,
+            "csv" | "htm" | "html" | "json" | "md" | "sh" | "toml" | "txt" | "xml"
+            | "yaml" | "yml" => Self::Text,
+            "3gp" | "avi" | "flv" | "m4v" | "mkv" | "mov" | "mp4" | "mpeg" | "mpg"
+            | "webm" | "wmv" => Self::Video,
+            _ => return None,
+        })
+    }
+}
+
 impl From<MatcherType> for FileType {
     fn from(matcher_type: MatcherType) -> Self {
         match matcher_type {
@@ -1,20 +1,41 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
 use std::fmt::{Debug, Display, Formatter};
 use std::fs::Permissions;
 use std::ops::Not;
 use std::os::unix::prelude::PermissionsExt;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
-use chrono::Duration;
 use strum_macros::IntoStaticStr;
 
+use crate::evaluate::content_cache::ContentCache;
+use crate::evaluate::decode::ContentEncoding;
+use crate::evaluate::dir_sizes::DirSizes;
+use crate::evaluate::literal_finder::LiteralFinder;
+use crate::parse::access::Access;
 use crate::parse::comparison::Comparison;
+use crate::parse::contains_skip::ContainsSkip;
+use crate::parse::entry_kind::EntryKind;
 use crate::parse::file_type::FileType;
 use crate::parse::match_pattern::MatchPattern;
+use crate::parse::permission_mode::PermissionMode;
+use crate::parse::time_attribute::TimeAttribute;
+use crate::parse::time_point::TimePoint;
 
 #[derive(Eq, PartialEq, Clone, IntoStaticStr)]
 pub enum Filter {
     Size {
         value: usize,
         comparison: Comparison,
+        /// Written as a bare number of bytes, without a unit
+        unitless: bool,
+    },
+    /// The recursive size of the files inside a directory
+    DirSize {
+        value: usize,
+        comparison: Comparison,
+        sizes: DirSizes,
     },
     Depth {
         value: usize,
@@ -23,26 +44,84 @@ pub enum Filter {
     Type {
         value: FileType,
         comparison: Comparison,
+        /// How long a single read may block, the default is used if not set
+        read_timeout: Option<Duration>,
+        /// How many times a read failing with a transient error is tried again
+        io_retries: u32,
+        cache: ContentCache,
+    },
+    /// The structural kind of an entry: a directory, regular file, symlink, socket,
+    /// FIFO, block or character device
+    Kind {
+        value: EntryKind,
+        comparison: Comparison,
+    },
+    /// Same as `Type`, but the category is guessed from the extension
+    TypeByExtension {
+        value: FileType,
+        comparison: Comparison,
     },
+    /// Compares the entry atime with `value`. Relative points are resolved against
+    /// `NOW`, captured once when the search starts, and nothing is clamped: a time
+    /// after `NOW` is later than any point in the past, so it matches `>`, `>=` and
+    /// `!=` and never `<`, `<=` or `=` against one, `within` included. With
+    /// `--no-future`, such entries are skipped instead.
     AccessTime {
-        value: Duration,
+        value: TimePoint,
         comparison: Comparison,
     },
+    /// Compares the entry mtime with `value`, see `AccessTime` for future times
     ModificationTime {
-        value: Duration,
+        value: TimePoint,
+        comparison: Comparison,
+    },
+    /// Compares two timestamps of the entry with each other, e.g. `atime < mtime`
+    CompareAttrs {
+        left: TimeAttribute,
+        right: TimeAttribute,
         comparison: Comparison,
     },
     Name {
         value: MatchPattern,
         comparison: Comparison,
     },
+    /// The whole path as it was walked, like `-path` in find. Its globs are compiled
+    /// with a literal separator unless `--glob-literal-separator false` is set, so
+    /// `*` stops at `/` and only `**` crosses it
+    Path {
+        value: MatchPattern,
+        comparison: Comparison,
+    },
     Extension {
         value: MatchPattern,
         comparison: Comparison,
     },
+    /// The file name without its last extension, like `Path::file_stem`
+    Stem {
+        value: MatchPattern,
+        comparison: Comparison,
+    },
+    /// The name of the directory holding the entry
+    Parent {
+        value: MatchPattern,
+        comparison: Comparison,
+    },
     Contains {
         value: MatchPattern,
         comparison: Comparison,
+        /// Stop scanning after this many bytes, the rest of the file never matches
+        max_bytes: Option<u64>,
+        /// How long a single read may block, the default is used if not set
+        read_timeout: Option<Duration>,
+        /// How many times a read failing with a transient error is tried again
+        io_retries: u32,
+        /// What the file is decoded from before it is matched
+        encoding: ContentEncoding,
+        /// Files never read, so they never match
+        skip: ContainsSkip,
+        cache: ContentCache,
+        /// Finds a literal `value` in the bytes of a file
+        finder: LiteralFinder,
     },
     User {
         value: u32,
@@ -54,6 +133,71 @@ pub enum Filter {
     },
     Permissions {
         value: Permissions,
+        mode: PermissionMode,
+        comparison: Comparison,
+    },
+    Inode {
+        value: u64,
+        comparison: Comparison,
+    },
+    /// The id of the device holding the entry. Unlike `--same-filesystem`, which
+    /// stops the walker at mount points, this only filters the visited entries.
+    Device {
+        value: u64,
+        comparison: Comparison,
+    },
+    /// The destination of a symlink, as stored in the link
+    SymlinkTarget {
+        value: MatchPattern,
+        comparison: Comparison,
+    },
+    /// An extended attribute `name` is set, with the exact `value` if given
+    Xattr {
+        name: String,
+        value: Option<String>,
+        comparison: Comparison,
+    },
+    /// Whether the current process can access the entry, checked with access(2)
+    Access {
+        value: Access,
+        comparison: Comparison,
+    },
+    /// Compares the entry mtime against `mtime` of the reference file `value`,
+    /// which is read once while parsing
+    NewerThan {
+        value: PathBuf,
+        mtime: SystemTime,
+        comparison: Comparison,
+    },
+    /// Zero-byte regular files and, if `dirs` is set, directories without entries
+    Empty {
+        dirs: bool,
+        comparison: Comparison,
+    },
+    /// The number of immediate entries of a directory; other entries never match
+    ChildCount {
+        value: usize,
+        comparison: Comparison,
+    },
+    /// Regular files with (or, if `text` is set, without) a NUL byte in the first
+    /// 8 KiB, the same heuristic grep uses
+    Binary {
+        text: bool,
+        comparison: Comparison,
+        /// How long a single read may block, the default is used if not set
+        read_timeout: Option<Duration>,
+    },
+    /// Entries owned by the effective user of the process or, if `group` is set,
+    /// by one of its groups
+    Mine {
+        group: bool,
+        comparison: Comparison,
+    },
+    /// Entries named exactly like one of `names`, read from the `--names-from` file
+    /// at `path`
+    NameInSet {
+        names: HashSet<OsString>,
+        path: PathBuf,
         comparison: Comparison,
     },
     #[cfg(test)]
@@ -72,6 +216,10 @@ impl Not for Filter {
                 comparison.negate();
                 self
             }
+            Self::DirSize { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
             Self::Depth { ref mut comparison, .. } => {
                 comparison.negate();
                 self
@@ -80,6 +228,14 @@ impl Not for Filter {
                 comparison.negate();
                 self
             }
+            Self::TypeByExtension { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Kind { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
             Self::AccessTime { ref mut comparison, .. } => {
                 comparison.negate();
                 self
@@ -96,6 +252,18 @@ impl Not for Filter {
                 comparison.negate();
                 self
             }
+            Self::Stem { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Parent { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Path { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
             Self::Contains { ref mut comparison, .. } => {
                 comparison.negate();
                 self
@@ -112,6 +280,54 @@ impl Not for Filter {
                 comparison.negate();
                 self
             }
+            Self::Inode { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Device { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Xattr { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::SymlinkTarget { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Access { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::NewerThan { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Empty { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Binary { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::ChildCount { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::Mine { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::NameInSet { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
+            Self::CompareAttrs { ref mut comparison, .. } => {
+                comparison.negate();
+                self
+            }
 
             #[cfg(test)]
             Self::Bool { ref mut comparison, .. } => {
@@ -133,57 +349,134 @@ impl Filter {
                 MatchPattern::Regex(_) => 2,
                 MatchPattern::Glob(_) => 1,
             },
+            Filter::Stem { value, .. } => match value {
+                MatchPattern::Regex(_) => 2,
+                MatchPattern::Glob(_) => 1,
+            },
+            Filter::Parent { value, .. } => match value {
+                MatchPattern::Regex(_) => 2,
+                MatchPattern::Glob(_) => 1,
+            },
+            Filter::Path { value, .. } => match value {
+                MatchPattern::Regex(_) => 2,
+                MatchPattern::Glob(_) => 1,
+            },
             Filter::Depth { .. } => 1,
+            Filter::NameInSet { .. } => 1,
+            Filter::TypeByExtension { .. } => 1,
+            Filter::Kind { .. } => 1,
 
             Filter::Size { .. } => 4,
             Filter::AccessTime { .. } => 4,
             Filter::ModificationTime { .. } => 4,
+            Filter::CompareAttrs { .. } => 4,
             Filter::User { .. } => 4,
             Filter::Group { .. } => 4,
             Filter::Permissions { .. } => 4,
+            Filter::Inode { .. } => 4,
+            Filter::Access { .. } => 4,
+            Filter::Device { .. } => 4,
+            Filter::Xattr { .. } => 4,
+            Filter::SymlinkTarget { .. } => 4,
+            Filter::NewerThan { .. } => 4,
+            Filter::Empty { .. } => 4,
+            Filter::Mine { .. } => 4,
+            // only reads the start of a file, unlike contains
+            Filter::Binary { .. } => 6,
 
             Filter::Type { .. } => 16,
+            Filter::DirSize { .. } => 32,
             Filter::Contains { .. } => 8,
+            Filter::ChildCount { .. } => 8,
 
             #[cfg(test)]
             Filter::Bool { .. } => 1,
         }
     }
+
+    /// Whether the filter never matches anything but a regular file or a symlink,
+    /// whatever the comparison is
+    pub fn requires_file(&self) -> bool {
+        matches!(
+            self,
+            Filter::Size { .. }
+                | Filter::Type { .. }
+                | Filter::TypeByExtension { .. }
+                | Filter::Contains { .. }
+                | Filter::Binary { .. }
+        )
+    }
 }
 
+/// Writes the filter in query syntax, so it parses back to an equal filter, see
+/// [`Filter::to_query`]. The filters a query cannot express, like the
+/// `--names-from` set, are described instead.
 impl Display for Filter {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        #[cfg(test)]
-        {
-            if let Self::Bool { .. } = self {
-            } else {
-                let self_repr: &'static str = self.into();
-                write!(f, "{} ", self_repr)?;
-            }
-        }
-
-        #[cfg(not(test))]
-        {
-            let self_repr: &'static str = self.into();
-            write!(f, "{} ", self_repr)?;
+        if let Some(query) = self.to_query() {
+            return f.write_str(&query);
         }
 
+        let self_repr: &'static str = self.into();
+        write!(f, "{self_repr} ")?;
         match self {
-            Self::Size { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Size { comparison, value, .. } => write!(f, "{comparison} {value}"),
+            Self::DirSize { comparison, value, .. } => write!(f, "{comparison} {value}"),
             Self::Depth { comparison, value } => write!(f, "{comparison} {value}"),
-            Self::Type { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::ChildCount { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Type { comparison, value, .. } => write!(f, "{comparison} {value}"),
+            Self::Kind { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::TypeByExtension { comparison, value } => {
+                write!(f, "{comparison} {value}")
+            }
             Self::AccessTime { comparison, value } => write!(f, "{comparison} {value}"),
             Self::ModificationTime { comparison, value } => {
                 write!(f, "{comparison} {value}")
             }
             Self::Name { comparison, value } => write!(f, "{comparison} {value}"),
             Self::Extension { comparison, value } => write!(f, "{comparison} {value}"),
-            Self::Contains { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Stem { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Parent { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Path { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Contains { comparison, value, .. } => write!(f, "{comparison} {value}"),
             Self::User { comparison, value } => write!(f, "{comparison} {value}"),
             Self::Group { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Inode { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Access { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::Device { comparison, value } => write!(f, "{comparison} {value}"),
+            Self::SymlinkTarget { comparison, value } => {
+                write!(f, "{comparison} {value}")
+            }
+            Self::Xattr { comparison, name, value: None } => {
+                write!(f, "{comparison} {name}")
+            }
+            Self::Xattr { comparison, name, value: Some(value) } => {
+                write!(f, "{comparison} {name}:{value}")
+            }
 
-            Self::Permissions { comparison, value } => {
-                write!(f, "{comparison} {}", unix_mode::to_string(value.mode()))
+            Self::Permissions { comparison, value, mode } => {
+                write!(f, "{comparison} {mode}{}", unix_mode::to_string(value.mode()))
+            }
+            Self::NewerThan { comparison, value, .. } => {
+                write!(f, "{comparison} {}", value.display())
+            }
+            Self::Empty { comparison, dirs } => {
+                let scope = if *dirs { "" } else { " (files)" };
+                write!(f, "{comparison} true{scope}")
+            }
+            Self::Binary { comparison, text, .. } => {
+                let kind = if *text { "text" } else { "binary" };
+                write!(f, "{comparison} {kind}")
+            }
+            Self::CompareAttrs { left, right, comparison } => {
+                write!(f, "{left} {comparison} {right}")
+            }
+            Self::NameInSet { comparison, names, path } => {
+                write!(f, "{comparison} {} names from {}", names.len(), path.display())
+            }
+            Self::Mine { comparison, group } => {
+                let owner = if *group { "my group" } else { "me" };
+                write!(f, "{comparison} {owner}")
             }
             #[cfg(test)]
             Self::Bool { comparison: _, value } => {
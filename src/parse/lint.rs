@@ -0,0 +1,190 @@
+use std::fmt::{Display, Formatter};
+
+use itertools::Itertools;
+use nnf::nnf::Nnf;
+use nnf::parse_tree::ExpressionNode;
+use nnf::var;
+
+use crate::parse::filter::Filter;
+
+/// A query construct that is valid but most likely not what the user meant
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum QueryLint {
+    UnitlessSize(String),
+    UnguardedContains,
+    AlwaysTrue(String),
+    AlwaysFalse(String),
+}
+
+impl Display for QueryLint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryLint::UnitlessSize(fragment) => write!(
+                f,
+                "`{fragment}` has no size unit and is compared in bytes; write e.g. `1Kb` or `1B`"
+            ),
+            QueryLint::UnguardedContains => write!(
+                f,
+                "`contains` is not guarded by `type` or `extension` and will scan binary files"
+            ),
+            QueryLint::AlwaysTrue(clause) => {
+                write!(f, "`{clause}` is always true because it contains a filter and its negation")
+            }
+            QueryLint::AlwaysFalse(clause) => {
+                write!(f, "`{clause}` is always false because it contains a filter and its negation")
+            }
+        }
+    }
+}
+
+impl QueryLint {
+    /// Whether the lint is printed as a warning outside `--strict-query`. Searching
+    /// contents without a guard is how `contains` is usually run, so only strict
+    /// mode reports it.
+    pub fn warns(&self) -> bool {
+        !matches!(self, QueryLint::UnguardedContains)
+    }
+}
+
+/// Looks for likely mistakes in the NNF tree of a query
+pub fn lint_query(root: &ExpressionNode<Filter>) -> Vec<QueryLint> {
+    let leafs = root.extract_leafs();
+    let mut lints: Vec<QueryLint> = leafs
+        .iter()
+        .filter_map(|filter| match filter {
+            Filter::Size { value, comparison, unitless: true } => {
+                Some(format!("size {comparison} {value}"))
+            }
+            _ => None,
+        })
+        .unique()
+        .map(QueryLint::UnitlessSize)
+        .collect();
+
+    let has_contains =
+        leafs.iter().any(|filter| matches!(filter, Filter::Contains { .. }));
+    let has_guard = leafs.iter().any(|filter| {
+        matches!(
+            filter,
+            Filter::Type { .. }
+                | Filter::TypeByExtension { .. }
+                | Filter::Extension { .. }
+        )
+    });
+    if has_contains && !has_guard {
+        lints.push(QueryLint::UnguardedContains);
+    }
+
+    let mut filters = vec![];
+    let nnf = to_canonical_nnf(root, &mut filters);
+    collect_inversions(&nnf, &filters, &mut lints);
+
+    lints
+}
+
+/// Maps each filter and its negation onto the same variable, the index of the
+/// filter in `filters`, so `name = a` and `name != a` become `x` and `¬x`
+fn to_canonical_nnf(
+    root: &ExpressionNode<Filter>,
+    filters: &mut Vec<Filter>,
+) -> Nnf<usize> {
+    match root {
+        ExpressionNode::Leaf(filter) => {
+            let negated = !filter.clone();
+            if let Some(index) = filters.iter().position(|known| known == filter) {
+                var!(index, true)
+            } else if let Some(index) = filters.iter().position(|known| *known == negated)
+            {
+                var!(index, false)
+            } else {
+                filters.push(filter.clone());
+                var!(filters.len() - 1, true)
+            }
+        }
+        ExpressionNode::And(left, right) => {
+            to_canonical_nnf(left, filters) & to_canonical_nnf(right, filters)
+        }
+        ExpressionNode::Or(left, right) => {
+            to_canonical_nnf(left, filters) | to_canonical_nnf(right, filters)
+        }
+        ExpressionNode::Not(node) => !to_canonical_nnf(node, filters),
+    }
+}
+
+fn collect_inversions(node: &Nnf<usize>, filters: &[Filter], lints: &mut Vec<QueryLint>) {
+    match node {
+        Nnf::Var(_, _) => {}
+        Nnf::And(children) => {
+            if node.has_inversions() {
+                lints.push(QueryLint::AlwaysFalse(render_clause(node, filters)));
+            }
+            children.iter().for_each(|child| collect_inversions(child, filters, lints));
+        }
+        Nnf::Or(children) => {
+            if node.has_inversions() {
+                lints.push(QueryLint::AlwaysTrue(render_clause(node, filters)));
+            }
+            children.iter().for_each(|child| collect_inversions(child, filters, lints));
+        }
+    }
+}
+
+fn render_clause(node: &Nnf<usize>, filters: &[Filter]) -> String {
+    match node {
+        Nnf::Var(index, true) => filters[*index].to_string(),
+        Nnf::Var(index, false) => format!("not {}", filters[*index]),
+        Nnf::And(children) => {
+            let children = children.iter().map(|child| render_clause(child, filters));
+            format!("({})", children.collect::<Vec<_>>().join(" and "))
+        }
+        Nnf::Or(children) => {
+            let children = children.iter().map(|child| render_clause(child, filters));
+            format!("({})", children.collect::<Vec<_>>().join(" or "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::lint::{lint_query, QueryLint};
+    use crate::parse::parse_root;
+
+    fn lint(query: &str) -> Vec<QueryLint> {
+        lint_query(&parse_root(query).unwrap().to_nnf())
+    }
+
+    #[test]
+    fn test_unitless_size() {
+        let unitless = |fragment: &str| [QueryLint::UnitlessSize(fragment.into())];
+        assert_eq!(lint("size > 100"), unitless("size > 100"));
+        assert_eq!(lint("(100 < size)"), unitless("size > 100"));
+        assert_eq!(lint("not (size > 100 or size > 100)"), unitless("size <= 100"));
+        assert_eq!(lint("size = ..10"), unitless("size <= 10"));
+        assert!(lint("size > 100Kb and size < 2 B and size = 1Kb..2Kb").is_empty());
+        // not a size, but a number of something else
+        assert!(lint("depth > 1 and children > 2").is_empty());
+    }
+
+    #[test]
+    fn test_unguarded_contains() {
+        assert_eq!(lint("contains = *needle*"), [QueryLint::UnguardedContains]);
+        assert!(!QueryLint::UnguardedContains.warns());
+        assert!(lint("type = text and contains = *needle*").is_empty());
+        assert!(lint("ext = rs and contains = *needle*").is_empty());
+    }
+
+    #[test]
+    fn test_inversions() {
+        assert!(matches!(
+            lint("name = a and not name = a")[..],
+            [QueryLint::AlwaysFalse(_)]
+        ));
+        assert!(matches!(lint("depth > 1 or (name = b and depth <= 1)")[..], []));
+        assert!(matches!(
+            lint("name = b and (depth > 1 or depth <= 1)")[..],
+            [QueryLint::AlwaysTrue(ref clause)] if clause.contains("not ")
+        ));
+        // the glob and the regex print the same, but are different filters
+        assert!(lint("name = a and name != r'a'").is_empty());
+    }
+}
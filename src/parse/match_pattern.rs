@@ -1,9 +1,71 @@
 use std::fmt::{Debug, Display, Formatter};
 
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+
+use crate::errors::GenericError;
+
 #[derive(Clone)]
 pub enum MatchPattern {
     Regex(regex::Regex),
-    Glob(globset::GlobMatcher),
+    Glob(GlobPattern),
+}
+
+/// A compiled glob along with the options it was compiled with, so it can be
+/// compiled again with some of them changed
+#[derive(Clone)]
+pub struct GlobPattern {
+    matcher: GlobMatcher,
+    ignore_case: bool,
+    literal_separator: bool,
+}
+
+impl GlobPattern {
+    pub fn new(
+        pattern: &str,
+        ignore_case: bool,
+        literal_separator: bool,
+    ) -> Result<Self, globset::Error> {
+        let matcher = GlobBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .literal_separator(literal_separator)
+            .build()?
+            .compile_matcher();
+
+        Ok(Self { matcher, ignore_case, literal_separator })
+    }
+
+    /// The glob as it was written
+    pub fn glob(&self) -> &str {
+        self.matcher.glob().glob()
+    }
+
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    pub fn literal_separator(&self) -> bool {
+        self.literal_separator
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.matcher.is_match(text)
+    }
+
+    /// The text of a `~ text` substring pattern: a case-insensitive `*text*` whose
+    /// `text` has no metacharacters other than bracketed ones
+    pub fn as_substring(&self) -> Option<String> {
+        if !self.ignore_case {
+            return None;
+        }
+        surrounded_text(self.glob())
+    }
+}
+
+/// The `text` of `*text*`, unless it has unescaped metacharacters
+fn surrounded_text(glob: &str) -> Option<String> {
+    let inner = glob.strip_prefix('*')?.strip_suffix('*')?;
+    unescape_glob(inner)
 }
 
 impl PartialEq<Self> for MatchPattern {
@@ -11,16 +73,23 @@ impl PartialEq<Self> for MatchPattern {
         match (self, other) {
             (Self::Regex(this), Self::Regex(other)) => this.as_str() == other.as_str(),
             (Self::Glob(this), Self::Glob(other)) => {
-                this.glob().to_string() == other.glob().to_string()
+                (this.glob(), this.ignore_case, this.literal_separator)
+                    == (other.glob(), other.ignore_case, other.literal_separator)
             }
-            unexpected => panic!("Unexpected: {unexpected:?}"),
+            _ => false,
         }
     }
 }
 
+/// A glob compiled with the default options: case-sensitive, with `*` crossing `/`
 impl From<globset::Glob> for MatchPattern {
     fn from(g: globset::Glob) -> Self {
-        Self::Glob(g.compile_matcher())
+        let (ignore_case, literal_separator) = (false, false);
+        Self::Glob(GlobPattern {
+            matcher: g.compile_matcher(),
+            ignore_case,
+            literal_separator,
+        })
     }
 }
 
@@ -36,7 +105,7 @@ impl Display for MatchPattern {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             MatchPattern::Regex(rx) => write!(f, "{}", rx.as_str()),
-            MatchPattern::Glob(matcher) => write!(f, "{}", matcher.glob()),
+            MatchPattern::Glob(glob) => write!(f, "{}", glob.glob()),
         }
     }
 }
@@ -58,3 +127,168 @@ impl MatchPattern {
         }
     }
 }
+
+impl MatchPattern {
+    /// Recompiles the pattern so it ignores case. Fails if the case-insensitive
+    /// pattern outgrows the compiled size limit.
+    pub fn to_case_insensitive(&self) -> Result<MatchPattern, GenericError> {
+        match self {
+            MatchPattern::Regex(rx) => recompile(&format!("(?i){}", rx.as_str())),
+            MatchPattern::Glob(glob) => {
+                recompile_glob(glob, true, glob.literal_separator)
+            }
+        }
+    }
+
+    /// Recompiles a glob so `*` and `?` do or do not match `/`, keeping its case
+    /// sensitivity. Regexes are returned as is.
+    pub fn with_literal_separator(
+        &self,
+        literal_separator: bool,
+    ) -> Result<MatchPattern, GenericError> {
+        match self {
+            MatchPattern::Regex(_) => Ok(self.clone()),
+            MatchPattern::Glob(glob) => {
+                recompile_glob(glob, glob.ignore_case, literal_separator)
+            }
+        }
+    }
+
+    /// Anchors a regex so it has to match the whole text, like `find -regex`.
+    /// Globs always match the whole text and are returned as is.
+    pub fn to_full_match(&self) -> Result<MatchPattern, GenericError> {
+        match self {
+            MatchPattern::Regex(rx) => recompile(&format!("^(?:{})$", rx.as_str())),
+            MatchPattern::Glob(_) => Ok(self.clone()),
+        }
+    }
+}
+
+impl MatchPattern {
+    /// Returns the text a pattern looks for when it is a plain substring search: an
+    /// unanchored regex without metacharacters, or a case-sensitive `*text*` glob
+    /// whose `text` has no metacharacters other than bracketed ones like `[*]`.
+    pub fn as_literal(&self) -> Option<String> {
+        let literal = match self {
+            MatchPattern::Regex(rx) => {
+                let pattern = rx.as_str();
+                if pattern.contains(|ch| "\\.+*?()|[]{}^$".contains(ch)) {
+                    return None;
+                }
+                pattern.to_string()
+            }
+            MatchPattern::Glob(glob) => {
+                if glob.ignore_case {
+                    return None;
+                }
+                surrounded_text(glob.glob())?
+            }
+        };
+
+        (!literal.is_empty()).then_some(literal)
+    }
+}
+
+/// Reverses [`escape_glob`](crate::parse::primitives::escape_glob), failing on any
+/// unescaped metacharacter
+fn unescape_glob(text: &str) -> Option<String> {
+    let mut literal = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '[' => {
+                let escaped = chars.next()?;
+                if !"?*[]{}\\".contains(escaped) || chars.next()? != ']' {
+                    return None;
+                }
+                literal.push(escaped);
+            }
+            '?' | '*' | ']' | '{' | '}' | '\\' => return None,
+            _ => literal.push(ch),
+        }
+    }
+    Some(literal)
+}
+
+fn recompile(pattern: &str) -> Result<MatchPattern, GenericError> {
+    Regex::new(pattern)
+        .map(MatchPattern::Regex)
+        .map_err(|err| GenericError::InvalidRegex(pattern.to_string(), err))
+}
+
+fn recompile_glob(
+    glob: &GlobPattern,
+    ignore_case: bool,
+    literal_separator: bool,
+) -> Result<MatchPattern, GenericError> {
+    GlobPattern::new(glob.glob(), ignore_case, literal_separator)
+        .map(MatchPattern::Glob)
+        .map_err(|err| GenericError::InvalidGlob(glob.glob().to_string(), err))
+}
+
+#[cfg(test)]
+mod test_match_pattern {
+    use super::*;
+    use crate::parse::primitives::compile_glob;
+
+    #[test]
+    fn test_as_literal() {
+        let glob = |pattern| compile_glob(pattern, false).unwrap();
+        assert_eq!(glob("*needle*").as_literal().as_deref(), Some("needle"));
+        assert_eq!(glob("*a[*]b[[]*").as_literal().as_deref(), Some("a*b["));
+        assert_eq!(glob("*a?b*").as_literal(), None);
+        assert_eq!(glob("needle").as_literal(), None);
+        assert_eq!(glob("**").as_literal(), None);
+        assert_eq!(compile_glob("*needle*", true).unwrap().as_literal(), None);
+
+        let rx = |pattern| -> MatchPattern { Regex::new(pattern).unwrap().into() };
+        assert_eq!(rx("needle").as_literal().as_deref(), Some("needle"));
+        assert_eq!(rx("a needle").as_literal().as_deref(), Some("a needle"));
+        assert_eq!(rx("need.e").as_literal(), None);
+        assert_eq!(rx("(?i)needle").as_literal(), None);
+    }
+
+    #[test]
+    fn test_recompile_glob() {
+        let glob = compile_glob("SRC/*.rs", true).unwrap();
+        let separated = glob.with_literal_separator(true).unwrap();
+        assert!(separated.is_match("src/main.rs"));
+        assert!(!separated.is_match("src/bin/main.rs"));
+        assert!(separated
+            .with_literal_separator(false)
+            .unwrap()
+            .is_match("SRC/bin/main.rs"));
+
+        let glob = compile_glob("src/*.rs", false)
+            .unwrap()
+            .with_literal_separator(true)
+            .unwrap();
+        assert!(!glob.is_match("SRC/main.rs"));
+        assert!(glob.to_case_insensitive().unwrap().is_match("SRC/main.rs"));
+        assert!(!glob.to_case_insensitive().unwrap().is_match("SRC/bin/main.rs"));
+        assert_ne!(glob, glob.to_case_insensitive().unwrap());
+    }
+
+    #[test]
+    fn test_to_full_match() {
+        let rx: MatchPattern = Regex::new("foo").unwrap().into();
+        assert!(rx.is_match("foobar"));
+
+        let full = rx.to_full_match().unwrap();
+        assert!(!full.is_match("foobar"));
+        assert!(full.is_match("foo"));
+
+        let alternation: MatchPattern = Regex::new("foo|foobar").unwrap().into();
+        assert!(alternation.to_full_match().unwrap().is_match("foobar"));
+
+        let ignore_case: MatchPattern = Regex::new("(?i)foo").unwrap().into();
+        assert!(ignore_case.to_full_match().unwrap().is_match("FOO"));
+        assert!(!ignore_case.to_full_match().unwrap().is_match("FOOBAR"));
+        assert!(rx
+            .to_full_match()
+            .unwrap()
+            .to_case_insensitive()
+            .unwrap()
+            .is_match("FOO"));
+    }
+}
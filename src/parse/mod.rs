@@ -4,57 +4,76 @@ use nom::multi::many0;
 use nom::sequence::tuple;
 use nom::{
     branch::alt, bytes::complete::tag, character::complete::char, combinator::map,
-    sequence::delimited, IResult,
+    sequence::delimited,
 };
 
-use crate::errors::GenericError;
+use crate::errors::{GenericError, ParseResult};
+use crate::parse::attribute_token::{
+    parse_attribute_comparison_filter, parse_reversed_filter, parse_size_range_filter,
+    parse_within_filter,
+};
 use crate::parse::filter::Filter;
 use crate::parse::primitives::parse_attribute_name;
 use crate::parse::traits::GenericParser;
 use crate::parse::util::ws;
 
+pub mod access;
 pub mod attribute_token;
 pub mod comparison;
+pub mod contains_skip;
+pub mod diagnostics;
+pub mod entry_kind;
 pub mod file_type;
 pub mod filter;
+pub mod lint;
 pub mod match_pattern;
+pub mod permission_mode;
 pub mod primitives;
+pub mod query;
 pub mod render;
 pub mod size_unit;
+pub mod time_attribute;
+pub mod time_point;
 pub mod time_unit;
 pub mod traits;
 pub mod util;
 
-fn parse_attribute(input: &str) -> IResult<&str, ExpressionNode<Filter>> {
-    let (input, attribute) = parse_attribute_name(input)?;
-    let (input, filter) = attribute.parse(input)?;
+fn parse_attribute(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
+    alt((
+        parse_within_filter,
+        parse_size_range_filter,
+        parse_attribute_comparison_filter,
+        map(parse_filter, |filter| e_leaf!(filter)),
+    ))(input)
+}
 
-    Ok((input, e_leaf!(filter)))
+/// Only falls back to the reversed `value op attribute` form when the input does not
+/// start with an attribute name, so errors of the direct form are not replaced by it.
+fn parse_filter(input: &str) -> ParseResult<'_, Filter> {
+    match parse_attribute_name(input) {
+        Ok((rest, attribute)) => attribute.parse(rest),
+        Err(_) => parse_reversed_filter(input),
+    }
 }
 
-fn parse_parens(input: &str) -> IResult<&str, ExpressionNode<Filter>> {
+fn parse_parens(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
     let expressions = delimited(ws(char('(')), parse_or, ws(char(')')));
     ws(expressions)(input)
 }
 
-fn parse_parens_or_attribute(input: &str) -> IResult<&str, ExpressionNode<Filter>> {
+fn parse_parens_or_attribute(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
     alt((parse_parens, parse_attribute, parse_not))(input)
 }
 
-#[rustfmt::skip]
-fn parse_not(input: &str) -> IResult<&str, ExpressionNode<Filter>> {
+/// `not` binds tighter than `and`, which binds tighter than `or`, so `not a or b`
+/// is `(not a) or b`. To negate a whole expression, wrap it in parentheses.
+fn parse_not(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
     let (input, _) = ws(tag("not"))(input)?;
-    map(
-        alt((
-            parse_attribute,
-            parse_parens_or_attribute
-        )),
-        |expression| e_not!(expression),
-    )(input)
+    map(parse_parens_or_attribute, |expression| e_not!(expression))(input)
 }
 
 #[rustfmt::skip]
-fn parse_or(input: &str) -> IResult<&str, ExpressionNode<Filter>> {
+fn parse_or(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
     let (input, left) = parse_and(input)?;
     let (input, expressions) = many0(
         tuple((
@@ -67,7 +86,7 @@ fn parse_or(input: &str) -> IResult<&str, ExpressionNode<Filter>> {
 }
 
 #[rustfmt::skip]
-fn parse_and(input: &str) -> IResult<&str, ExpressionNode<Filter>> {
+fn parse_and(input: &str) -> ParseResult<'_, ExpressionNode<Filter>> {
     let (input, left) = parse_parens_or_attribute(input)?;
     let (input, expressions) = many0(
         tuple((
@@ -109,12 +128,28 @@ pub fn parse_root(input: &str) -> Result<ExpressionNode<Filter>, GenericError> {
 
 #[cfg(test)]
 mod test {
-    use chrono::Duration;
+    use std::fs::Permissions;
+    use std::time::SystemTime;
+
+    use chrono::{Duration, Local, TimeZone};
     use regex::Regex;
 
+    use std::os::unix::fs::PermissionsExt;
+
+    use crate::evaluate::content_cache::ContentCache;
+    use crate::evaluate::dir_sizes::DirSizes;
+    use crate::evaluate::NOW;
+    use crate::parse::access::Access;
     use crate::parse::comparison::Comparison;
+    use crate::parse::entry_kind::EntryKind;
     use crate::parse::file_type::FileType;
     use crate::parse::filter::Filter;
+    use crate::parse::permission_mode::PermissionMode;
+    use crate::parse::primitives::compile_glob;
+    use crate::parse::time_attribute::TimeAttribute;
+    use crate::test_utils::{contains_filter, DirEntryMock};
+    use crate::walk::entry_type::EntryType;
+    use crate::Evaluate;
 
     use super::*;
 
@@ -122,13 +157,339 @@ mod test {
     fn test_parse_size() {
         assert_eq!(
             parse_attribute("size <= 1 B"),
-            Ok(("", e_leaf!(Filter::Size { value: 1, comparison: Comparison::Lte })))
+            Ok((
+                "",
+                e_leaf!(Filter::Size {
+                    value: 1,
+                    comparison: Comparison::Lte,
+                    unitless: false
+                })
+            ))
         );
 
         assert_eq!(
             parse_attribute(" size != 10B"),
-            Ok(("", e_leaf!(Filter::Size { value: 10, comparison: Comparison::Neq })))
+            Ok((
+                "",
+                e_leaf!(Filter::Size {
+                    value: 10,
+                    comparison: Comparison::Neq,
+                    unitless: false
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_attribute_comparison() {
+        assert_eq!(
+            parse_attribute("atime < mtime and"),
+            Ok((
+                "and",
+                e_leaf!(Filter::CompareAttrs {
+                    left: TimeAttribute::Access,
+                    right: TimeAttribute::Modification,
+                    comparison: Comparison::Lt,
+                })
+            ))
+        );
+        assert_eq!(
+            parse_attribute("btime!=mtime"),
+            Ok((
+                "",
+                e_leaf!(Filter::CompareAttrs {
+                    left: TimeAttribute::Birth,
+                    right: TimeAttribute::Modification,
+                    comparison: Comparison::Neq,
+                })
+            ))
+        );
+        // literals still work
+        assert!(matches!(
+            parse_attribute("mtime > now - 1d"),
+            Ok(("", ExpressionNode::Leaf(Filter::ModificationTime { .. })))
+        ));
+        assert!(parse_root("size < mtime").is_err());
+    }
+
+    #[test]
+    fn test_parse_size_range() {
+        let node = |query| parse_attribute(query).unwrap().1;
+
+        assert_eq!(
+            parse_attribute("size = 1Mb..10Mb and"),
+            Ok(("and", e_and!(node("size >= 1Mb"), node("size <= 10Mb"))))
+        );
+        assert_eq!(node("size = 1Mb .. 10Mb"), node("size = 1Mb..10Mb"));
+        assert_eq!(node("size = 1Kb.."), node("size >= 1Kb"));
+        assert_eq!(node("size = ..10"), node("size <= 10"));
+        assert_eq!(
+            node("size != 1..2"),
+            e_not!(e_and!(node("size >= 1"), node("size <= 2")))
+        );
+
+        let matches = |query, size| {
+            let root = parse_root(query).unwrap().to_nnf();
+            let entry =
+                DirEntryMock::default().set_size(size).set_entry_type(EntryType::File);
+            root.evaluate(&entry).unwrap()
+        };
+        assert!(matches("size = 10..20", 10));
+        assert!(matches("size = 10..20", 20));
+        assert!(!matches("size = 10..20", 21));
+        assert!(matches("not size = 10..20", 9));
+        assert!(!matches("size = ..20", 21));
+        assert!(matches("size = 10..", 1 << 20));
+
+        assert!(parse_attribute("size = ..").is_err());
+        assert!(parse_attribute("size > 1..2").is_err());
+        assert!(parse_root("depth = 1..2").is_err());
+    }
+
+    #[test]
+    fn test_parse_reversed_operands() {
+        assert_eq!(parse_attribute("1M < size"), parse_attribute("size > 1M"));
+        assert_eq!(parse_attribute("10 B >= size"), parse_attribute("size <= 10 B"));
+        assert_eq!(parse_attribute("2 != depth"), parse_attribute("depth != 2"));
+        assert_eq!(
+            parse_attribute("now - 1d < mtime"),
+            parse_attribute("mtime > now - 1d")
+        );
+        assert_eq!(parse_attribute("now <= atime"), parse_attribute("atime >= now"));
+
+        assert!(parse_attribute("1M < depth").is_err());
+        assert_eq!(parse_attribute("2 < size"), parse_attribute("size > 2"));
+        assert_ne!(parse_attribute("size > 2"), parse_attribute("size > 2B"));
+        assert!(parse_attribute("now < size").is_err());
+
+        let Err(nom::Err::Error(error)) = parse_attribute("size > x") else {
+            panic!("size > x must not parse");
+        };
+        assert_eq!(error.input, "x");
+    }
+
+    #[test]
+    fn test_parse_user_and_group() {
+        let uid = uzers::get_current_uid();
+        let gid = uzers::get_current_gid();
+        let user = uzers::get_current_username().unwrap();
+        let group = uzers::get_current_groupname().unwrap();
+
+        let expected =
+            Ok(("", e_leaf!(Filter::User { value: uid, comparison: Comparison::Eq })));
+        assert_eq!(parse_attribute(&format!("user = {uid}")), expected);
+        assert_eq!(
+            parse_attribute(&format!("owner = {}", user.to_str().unwrap())),
+            expected
+        );
+
+        let expected =
+            Ok(("", e_leaf!(Filter::Group { value: gid, comparison: Comparison::Neq })));
+        assert_eq!(parse_attribute(&format!("group != {gid}")), expected);
+        assert_eq!(
+            parse_attribute(&format!("group != {}", group.to_str().unwrap())),
+            expected
+        );
+
+        let error = parse_root("user = svc.no-such_user$").unwrap_err();
+        assert!(
+            matches!(&error, GenericError::UnknownUser(name) if name == "svc.no-such_user$")
+        );
+        let error = parse_root("group = web-no-such-group").unwrap_err();
+        assert!(
+            matches!(&error, GenericError::UnknownGroup(name) if name == "web-no-such-group")
+        );
+    }
+
+    #[test]
+    fn test_parse_dir_size() {
+        assert_eq!(
+            parse_attribute("dirsize > 2Kb"),
+            Ok((
+                "",
+                e_leaf!(Filter::DirSize {
+                    value: 2000,
+                    comparison: Comparison::Gt,
+                    sizes: DirSizes::default()
+                })
+            ))
+        );
+        assert_eq!(
+            parse_attribute("2Kb > dirsize"),
+            Ok((
+                "",
+                e_leaf!(Filter::DirSize {
+                    value: 2000,
+                    comparison: Comparison::Lt,
+                    sizes: DirSizes::default()
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_permissions() {
+        let filter = |query| match parse_attribute(query) {
+            Ok((
+                "",
+                ExpressionNode::Leaf(Filter::Permissions { value, mode, comparison }),
+            )) => (value.mode(), mode, comparison),
+            result => panic!("{query}: {result:?}"),
+        };
+
+        assert_eq!(filter("perm = 644"), (0o644, PermissionMode::Exact, Comparison::Eq));
+        assert_eq!(
+            filter("perm > 4000"),
+            (0o4000, PermissionMode::Exact, Comparison::Gt)
+        );
+        assert_eq!(
+            filter("perm = -4000"),
+            (0o4000, PermissionMode::AllSet, Comparison::Eq)
+        );
+        assert_eq!(
+            filter("perms != /111"),
+            (0o111, PermissionMode::AnySet, Comparison::Neq)
+        );
+        assert!(parse_attribute("perm > -644").is_err());
+        assert!(parse_attribute("perm = /").is_err());
+    }
+
+    #[test]
+    fn test_parse_symbolic_permissions() {
+        assert_eq!(
+            parse_attribute("perm = u+x"),
+            Ok((
+                "",
+                e_leaf!(Filter::Permissions {
+                    value: Permissions::from_mode(0o100),
+                    mode: PermissionMode::AllSet,
+                    comparison: Comparison::Eq,
+                })
+            ))
+        );
+
+        let matches = |query, mode| {
+            let (_, node) = parse_attribute(query).unwrap();
+            let ExpressionNode::Leaf(filter) = node else {
+                panic!("{query}: {node:?}");
+            };
+            let entry =
+                DirEntryMock::default().set_permissions(Permissions::from_mode(mode));
+            filter.evaluate(&entry).unwrap()
+        };
+
+        assert!(matches("perm = u+x", 0o744));
+        assert!(!matches("perm = u+x", 0o644));
+        assert!(matches("perm != u+x", 0o644));
+
+        assert!(matches("perm = a+r", 0o444));
+        assert!(matches("perm = +r", 0o644));
+        assert!(!matches("perm = a+r", 0o640));
+
+        assert!(matches("perm = go-w", 0o755));
+        assert!(!matches("perm = go-w", 0o775));
+        assert!(!matches("perm = go-w", 0o757));
+        assert!(matches("perm != go-w", 0o757));
+
+        assert!(matches("perm = u+s", 0o4755));
+        assert!(!matches("perm = ug+rx", 0o745));
+
+        // octal still works, and `-` followed by digits is still "all bits set"
+        assert!(matches("perm = -111", 0o755));
+        assert!(parse_attribute("perm > u+x").is_err());
+        assert!(parse_attribute("perm = o+s").is_err());
+    }
+
+    #[test]
+    fn test_parse_access() {
+        assert_eq!(
+            parse_attribute("executable"),
+            Ok((
+                "",
+                e_leaf!(Filter::Access {
+                    value: Access::Executable,
+                    comparison: Comparison::Eq
+                })
+            ))
         );
+        assert_eq!(
+            parse_attribute("writable = false"),
+            Ok((
+                "",
+                e_leaf!(Filter::Access {
+                    value: Access::Writable,
+                    comparison: Comparison::Neq
+                })
+            ))
+        );
+        assert_eq!(
+            parse_attribute("readable != false"),
+            Ok((
+                "",
+                e_leaf!(Filter::Access {
+                    value: Access::Readable,
+                    comparison: Comparison::Eq
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_device() {
+        assert_eq!(
+            parse_attribute("dev = 2049"),
+            Ok(("", e_leaf!(Filter::Device { value: 2049, comparison: Comparison::Eq })))
+        );
+        assert_eq!(
+            parse_attribute("device != 1"),
+            Ok(("", e_leaf!(Filter::Device { value: 1, comparison: Comparison::Neq })))
+        );
+    }
+
+    #[test]
+    fn test_parse_inode() {
+        assert_eq!(
+            parse_attribute("ino = 42"),
+            Ok(("", e_leaf!(Filter::Inode { value: 42, comparison: Comparison::Eq })))
+        );
+        assert_eq!(
+            parse_attribute("inode>=7"),
+            Ok(("", e_leaf!(Filter::Inode { value: 7, comparison: Comparison::Gte })))
+        );
+    }
+
+    #[test]
+    fn test_parse_newer_older() {
+        let reference = tempfile::NamedTempFile::new().unwrap();
+        let path = reference.path().to_path_buf();
+        let mtime = path.metadata().unwrap().modified().unwrap();
+
+        assert_eq!(
+            parse_attribute(&format!("newer = '{}'", path.display())),
+            Ok((
+                "",
+                e_leaf!(Filter::NewerThan {
+                    value: path.clone(),
+                    mtime,
+                    comparison: Comparison::Gt
+                })
+            ))
+        );
+        assert_eq!(
+            parse_attribute(&format!("older != {}", path.display())),
+            Ok((
+                "",
+                e_leaf!(Filter::NewerThan {
+                    value: path,
+                    mtime,
+                    comparison: Comparison::Gte
+                })
+            ))
+        );
+
+        let error = parse_root("newer = /definitely/missing/reference").unwrap_err();
+        assert!(matches!(error, GenericError::ReferenceFileError(..)), "{error:?}");
+        assert!(error.is_fatal());
     }
 
     #[test]
@@ -138,7 +499,7 @@ mod test {
             Ok((
                 "",
                 e_leaf!(Filter::ModificationTime {
-                    value: Duration::days(-2),
+                    value: Duration::days(-2).into(),
                     comparison: Comparison::Lte,
                 })
             ))
@@ -149,11 +510,35 @@ mod test {
             Ok((
                 "",
                 e_leaf!(Filter::AccessTime {
-                    value: Duration::days(-2),
+                    value: Duration::days(-2).into(),
                     comparison: Comparison::Lte,
                 })
             ))
         );
+
+        let midnight = Local.with_ymd_and_hms(2023, 1, 15, 0, 0, 0).unwrap();
+        assert_eq!(
+            parse_attribute("mtime < 2023-01-15"),
+            Ok((
+                "",
+                e_leaf!(Filter::ModificationTime {
+                    value: SystemTime::from(midnight).into(),
+                    comparison: Comparison::Lt,
+                })
+            ))
+        );
+
+        let morning = Local.with_ymd_and_hms(2023, 1, 15, 10, 30, 0).unwrap();
+        assert_eq!(
+            parse_attribute("'2023-01-15 10:30:00' < atime"),
+            Ok((
+                "",
+                e_leaf!(Filter::AccessTime {
+                    value: SystemTime::from(morning).into(),
+                    comparison: Comparison::Gt,
+                })
+            ))
+        );
     }
 
     #[test]
@@ -173,9 +558,78 @@ mod test {
             parse_attribute("contains != r'пример.json' remainder"),
             Ok((
                 " remainder",
-                e_leaf!(Filter::Contains {
-                    value: Regex::new("пример.json").unwrap().into(),
-                    comparison: Comparison::Neq,
+                e_leaf!(!contains_filter(Regex::new("пример.json").unwrap()))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let Ok(("", ExpressionNode::Leaf(Filter::Path { value, comparison }))) =
+            parse_attribute("path != 'src/*.rs'")
+        else {
+            panic!("path is not parsed");
+        };
+        assert_eq!(comparison, Comparison::Neq);
+        assert!(value.is_match("src/a.rs"));
+        assert!(!value.is_match("src/a/b.rs"));
+
+        assert!(parse_root("path = r'^src/.+\\.rs$'").is_ok());
+        assert!(parse_root("path > src").is_err());
+    }
+
+    #[test]
+    fn test_parse_negated_pattern() {
+        for (negated, inline) in [
+            ("name = !'*.rs'", "name != '*.rs'"),
+            ("name = !i\"*.rs\"", "name != i'*.rs'"),
+            ("name != !'*.rs'", "name = '*.rs'"),
+            ("ext = ! r'r.'", "ext != r'r.'"),
+        ] {
+            assert_eq!(parse_attribute(negated), parse_attribute(inline), "{negated}");
+        }
+
+        // unquoted or inside the quotes, the `!` is matched literally
+        for query in ["name = '!*.rs'", "name = !*.rs"] {
+            assert_eq!(
+                parse_attribute(query),
+                Ok((
+                    "",
+                    e_leaf!(Filter::Name {
+                        value: globset::Glob::new("!*.rs").unwrap().into(),
+                        comparison: Comparison::Eq,
+                    })
+                )),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_substring() {
+        let matches = |query: &str, name: &str| {
+            let entry = DirEntryMock::default().set_file(name.into());
+            parse_root(query).unwrap().evaluate(&entry).unwrap()
+        };
+
+        assert!(matches("name ~ cfg", "my.cfg.bak"));
+        assert!(matches("name ~ CFG", "my.cfg.bak"));
+        assert!(!matches("name ~ cfg", "config"));
+        assert!(matches("name !~ cfg", "config"));
+        assert!(matches("name ~ 'my cfg'", "old my CFG"));
+
+        // metacharacters are matched literally
+        assert!(matches("name ~ a*b?[c]{d,e}", "x_a*b?[c]{d,e}_x"));
+        assert!(!matches("name ~ a*b", "axxb"));
+        assert!(!matches("name ~ {d,e}", "d"));
+
+        assert_eq!(
+            parse_attribute("name ~ cfg and"),
+            Ok((
+                "and",
+                e_leaf!(Filter::Name {
+                    value: compile_glob("*cfg*", true).unwrap(),
+                    comparison: Comparison::Eq,
                 })
             ))
         );
@@ -198,11 +652,109 @@ mod test {
                 e_leaf!(Filter::Type {
                     value: FileType::Video,
                     comparison: Comparison::Neq,
+                    read_timeout: None,
+                    io_retries: 0,
+                    cache: ContentCache::default(),
                 })
             ))
         );
     }
 
+    #[test]
+    fn test_parse_entry_kind() {
+        let kind =
+            |value, comparison| Ok(("", e_leaf!(Filter::Kind { value, comparison })));
+
+        assert_eq!(parse_attribute("kind = dir"), kind(EntryKind::Dir, Comparison::Eq));
+        assert_eq!(
+            parse_attribute("kind=directory"),
+            kind(EntryKind::Dir, Comparison::Eq)
+        );
+        assert_eq!(
+            parse_attribute("kind != l"),
+            kind(EntryKind::Symlink, Comparison::Neq)
+        );
+        assert_eq!(parse_attribute("kind = pipe"), kind(EntryKind::Fifo, Comparison::Eq));
+        assert_eq!(parse_attribute("kind = char"), kind(EntryKind::Char, Comparison::Eq));
+        assert!(parse_root("kind > dir").is_err());
+        assert!(parse_root("kind = folder").is_err());
+    }
+
+    #[test]
+    fn test_parse_within() {
+        let since = Filter::ModificationTime {
+            value: Duration::hours(-1).into(),
+            comparison: Comparison::Gte,
+        };
+        let until = Filter::ModificationTime {
+            value: Duration::zero().into(),
+            comparison: Comparison::Lte,
+        };
+        let within = parse_root("mtime within 1h").unwrap();
+        assert_eq!(within, e_and!(e_leaf!(since.clone()), e_leaf!(until.clone())));
+        assert_eq!(
+            parse_root("atime within 30 min").unwrap().to_string(),
+            "(atime >= now - 1800s ∧ atime <= now)"
+        );
+        assert!(parse_root("size within 1h").is_err());
+
+        let not_within = parse_root("not mtime within 1h").unwrap().to_nnf();
+        assert_eq!(not_within, e_or!(e_leaf!(!since.clone()), e_leaf!(!until.clone())));
+
+        assert!(parse_root("mtime within 9999999999999999 w").is_err());
+
+        let modified = |offset: Duration| {
+            let mtime = if offset < Duration::zero() {
+                *NOW - (-offset).to_std().unwrap()
+            } else {
+                *NOW + offset.to_std().unwrap()
+            };
+            DirEntryMock::default().set_mtime(mtime)
+        };
+        for (offset, inside) in [
+            (Duration::hours(-2), false),
+            (Duration::minutes(-30), true),
+            (Duration::zero(), true),
+            (Duration::hours(1), false),
+        ] {
+            let entry = modified(offset);
+            assert_eq!(within.evaluate(&entry).unwrap(), inside, "{offset}");
+            assert_eq!(not_within.evaluate(&entry).unwrap(), !inside, "{offset}");
+        }
+    }
+
+    #[test]
+    fn test_not_precedence() {
+        let leaf = |query| parse_attribute(query).unwrap().1;
+        let (a, b) = (leaf("name = a"), leaf("name = b"));
+
+        assert_eq!(
+            parse_root("not name = a and name = b").unwrap(),
+            e_and!(e_not!(a.clone()), b.clone())
+        );
+        assert_eq!(
+            parse_root("not name = a or name = b").unwrap(),
+            e_or!(e_not!(a.clone()), b.clone())
+        );
+        assert_eq!(
+            parse_root("name = b or not name = a and name = b").unwrap(),
+            e_or!(b.clone(), e_and!(e_not!(a.clone()), b.clone()))
+        );
+        assert_eq!(
+            parse_root("not (name = a and name = b)").unwrap(),
+            e_not!(e_and!(a.clone(), b.clone()))
+        );
+        assert_eq!(
+            parse_root("not(name = a or name = b)").unwrap(),
+            e_not!(e_or!(a.clone(), b.clone()))
+        );
+        assert_eq!(parse_root("not not name = a").unwrap(), e_not!(e_not!(a.clone())));
+        assert_eq!(
+            parse_root("not not (name = a) and name = b").unwrap(),
+            e_and!(e_not!(e_not!(a)), b)
+        );
+    }
+
     #[test]
     fn parse_sample_1() {
         let input = "name = aaaa and mtime <= now - 1d and size <= 1B and not (not type = vid and size >= 2B or size != 3B) or size = 4B";
@@ -0,0 +1,22 @@
+use std::fmt::{Display, Formatter};
+
+/// How `perm` compares the requested bits with the file mode, as `-perm` in find
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum PermissionMode {
+    /// `perm = 644`: the permission bits are exactly the requested ones
+    Exact,
+    /// `perm = -644`: all the requested bits are set
+    AllSet,
+    /// `perm = /644`: any of the requested bits is set
+    AnySet,
+}
+
+impl Display for PermissionMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermissionMode::Exact => Ok(()),
+            PermissionMode::AllSet => write!(f, "-"),
+            PermissionMode::AnySet => write!(f, "/"),
+        }
+    }
+}
@@ -1,23 +1,28 @@
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::SystemTime;
 
-use chrono::Duration;
-use globset::GlobBuilder;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
 use itertools::Itertools;
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_while};
+use nom::bytes::complete::{tag, take_while, take_while1};
 use nom::character::complete::{char, one_of};
-use nom::combinator::{map, map_res, opt, recognize};
+use nom::combinator::{map, map_res, opt, recognize, value};
 use nom::error::{ErrorKind, FromExternalError};
 use nom::multi::{many0, many1};
 use nom::sequence::{delimited, preceded, terminated, tuple};
-use nom::IResult;
-use regex::RegexBuilder;
+use regex::Regex;
 
+use crate::errors::{GenericError, ParseError, ParseResult};
+use crate::evaluate::NOW;
 use crate::parse::attribute_token::AttributeToken;
 use crate::parse::comparison::Comparison;
+use crate::parse::entry_kind::EntryKind;
 use crate::parse::file_type::FileType;
-use crate::parse::match_pattern::MatchPattern;
+use crate::parse::match_pattern::{GlobPattern, MatchPattern};
 use crate::parse::size_unit::SizeUnit;
+use crate::parse::time_attribute::TimeAttribute;
+use crate::parse::time_point::TimePoint;
 use crate::parse::time_unit::TimeUnit;
 use crate::parse::util::{parse_enum_alias, ws};
 
@@ -30,7 +35,7 @@ const DOUBLE_QUOTE_BYTE: u8 = b'"';
 const BACK_SLASH_BYTE: u8 = b'\\';
 
 #[rustfmt::skip]
-pub fn parse_decimal(input: &str) -> IResult<&str, &str> {
+pub fn parse_decimal(input: &str) -> ParseResult<'_, &str> {
     recognize(
         many1(
             terminated(
@@ -42,7 +47,7 @@ pub fn parse_decimal(input: &str) -> IResult<&str, &str> {
 }
 
 #[rustfmt::skip]
-pub fn parse_positive_number(input: &str) -> IResult<&str, usize> {
+pub fn parse_positive_number(input: &str) -> ParseResult<'_, usize> {
     map_res(
         preceded(
             opt(char('+')),
@@ -53,7 +58,7 @@ pub fn parse_positive_number(input: &str) -> IResult<&str, usize> {
 }
 
 #[rustfmt::skip]
-pub fn parse_negative_number(input: &str) -> IResult<&str, isize> {
+pub fn parse_negative_number(input: &str) -> ParseResult<'_, isize> {
     map(
         preceded(
             char('-'),
@@ -64,7 +69,7 @@ pub fn parse_negative_number(input: &str) -> IResult<&str, isize> {
 }
 
 #[rustfmt::skip]
-pub fn parse_comparison(input: &str) -> IResult<&str, Comparison> {
+pub fn parse_comparison(input: &str) -> ParseResult<'_, Comparison> {
     let ops = (
         tag("<="),
         tag(">="),
@@ -80,46 +85,237 @@ pub fn parse_comparison(input: &str) -> IResult<&str, Comparison> {
     )(input)
 }
 
-fn parse_signed_delta(input: &str) -> IResult<&str, Duration> {
+/// Parses a symbolic mode clause like `u+x` or `go-w` into the permission bits it
+/// names and whether they are added (`+`) or removed (`-`). Without the `ugoa`
+/// part the clause applies to everyone, like `a`.
+pub fn parse_symbolic_mode(input: &str) -> ParseResult<'_, (u32, bool)> {
+    let (rest, (who, op, what)) = ws(tuple((
+        take_while(|c| "ugoa".contains(c)),
+        one_of("+-"),
+        take_while1(|c| "rwxst".contains(c)),
+    )))(input)?;
+
+    let who_mask = if who.is_empty() { "a" } else { who }
+        .chars()
+        .map(|who| match who {
+            'u' => 0o4700,
+            'g' => 0o2070,
+            'o' => 0o0007,
+            _ => 0o6777,
+        })
+        .fold(0, |mask, bits| mask | bits);
+    let what_bits = what
+        .chars()
+        .map(|what| match what {
+            'r' => 0o444,
+            'w' => 0o222,
+            'x' => 0o111,
+            's' => 0o6000,
+            _ => 0,
+        })
+        .fold(0, |bits, what| bits | what);
+    // the sticky bit does not belong to anyone
+    let sticky = if what.contains('t') { 0o1000 } else { 0 };
+
+    match who_mask & what_bits | sticky {
+        // e.g. `o+s`, which would match any mode
+        0 => Err(nom::Err::Error(ParseError::new(input, ErrorKind::Verify))),
+        bits => Ok((rest, (bits, op == '+'))),
+    }
+}
+
+/// Parses the optional `= true`/`!= false` suffix of a boolean attribute.
+/// A bare attribute is the same as `= true`.
+pub fn parse_flag(input: &str) -> ParseResult<'_, Comparison> {
+    let (rest, suffix) =
+        opt(tuple((parse_comparison, ws(alt((tag("true"), tag("false")))))))(input)?;
+
+    match suffix {
+        None => Ok((rest, Comparison::Eq)),
+        Some((mut comparison @ (Comparison::Eq | Comparison::Neq), value)) => {
+            if value == "false" {
+                comparison.negate();
+            }
+            Ok((rest, comparison))
+        }
+        Some(_) => Err(nom::Err::Failure(ParseError::new(input, ErrorKind::Fail))),
+    }
+}
+
+fn parse_signed_delta(input: &str) -> ParseResult<'_, Duration> {
     let (input, sign) = ws(alt((char('+'), char('-'))))(input)?;
-    let (input, number) = parse_positive_number(input)?;
-    let (input, time_unit) = parse_time_unit(input)?;
+    let (input, duration) = parse_time_span(input)?;
 
-    let mut duration = time_unit.to_duration(number as i64);
-    if sign == '-' {
-        duration = -duration;
+    match sign {
+        '-' => Ok((input, -duration)),
+        _ => Ok((input, duration)),
     }
+}
 
-    Ok((input, duration))
+/// Parses an unsigned span of time like `30s` or `2 hours`
+pub fn parse_time_span(input: &str) -> ParseResult<'_, Duration> {
+    let (rest, number) = ws(parse_positive_number)(input)?;
+    let (rest, time_unit) = parse_time_unit(rest)?;
+
+    match time_unit.to_duration(number) {
+        Some(duration) => Ok((rest, duration)),
+        None => {
+            let span = input[..input.len() - rest.len()].trim();
+            Err(ParseError::fatal(input, GenericError::InvalidDuration(span.to_string())))
+        }
+    }
+}
+
+/// Parses either `now` with optional deltas (`now - 2d`, `now - 1w + 3d`) or a quoted
+/// phrase (`"2 weeks ago"`, `"yesterday"`) into an offset from now
+pub fn parse_duration(input: &str) -> ParseResult<'_, Duration> {
+    // NOW is only read when there is a phrase, so it is initialized as late as possible
+    let now = || DateTime::<Local>::from(*NOW).naive_local();
+    alt((parse_now_delta, parse_relative_phrase(now)))(input)
+}
+
+/// Parses an absolute date (`2023-01-15`, `'2023-01-15 10:30:00'`) or a relative time
+pub fn parse_time_point(input: &str) -> ParseResult<'_, TimePoint> {
+    alt((
+        map(parse_date_time, TimePoint::Absolute),
+        map(parse_duration, TimePoint::Relative),
+    ))(input)
+}
+
+/// Parses an ISO 8601 local date with an optional time of day, midnight by default.
+/// Dates with a time of day must be quoted, the seconds may have a fraction.
+pub fn parse_date_time(input: &str) -> ParseResult<'_, SystemTime> {
+    let unquoted = take_while1(|ch: char| ch.is_ascii_digit() || ch == '-');
+    let (rest, value) = ws(alt((parse_quote_escaped_string, unquoted)))(input)?;
+
+    let date_time = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+            date.and_hms_opt(0, 0, 0)
+        })
+        .and_then(|date_time| Local.from_local_datetime(&date_time).earliest());
+
+    match date_time {
+        Some(date_time) => Ok((rest, date_time.into())),
+        None => Err(nom::Err::Error(ParseError::new(input, ErrorKind::Verify))),
+    }
+}
+
+fn parse_now_delta(input: &str) -> ParseResult<'_, Duration> {
+    let (rest, _) = ws(tag("now"))(input)?;
+    let (rest, deltas) = many0(parse_signed_delta)(rest)?;
+    let duration = deltas
+        .into_iter()
+        .try_fold(Duration::zero(), |sum, delta| sum.checked_add(&delta));
+
+    match duration {
+        Some(duration) => Ok((rest, duration)),
+        None => {
+            let deltas = input[..input.len() - rest.len()].trim();
+            Err(ParseError::fatal(
+                input,
+                GenericError::InvalidDuration(deltas.to_string()),
+            ))
+        }
+    }
 }
 
-pub fn parse_duration(input: &str) -> IResult<&str, Duration> {
-    let (input, _) = ws(tag("now"))(input)?;
-    let (input, duration) = opt(parse_signed_delta)(input)?;
-    let duration = duration.unwrap_or_else(|| TimeUnit::Second.to_duration(0));
+/// Parses a quoted phrase relative to the local time returned by `now`
+pub fn parse_relative_phrase(
+    now: impl Fn() -> NaiveDateTime,
+) -> impl FnMut(&str) -> ParseResult<'_, Duration> {
+    move |input: &str| {
+        let (rest, phrase) = ws(parse_quote_escaped_string)(input)?;
+        match phrase_to_duration(phrase, now()) {
+            Some(duration) => Ok((rest, duration)),
+            None => Err(nom::Err::Error(ParseError::new(input, ErrorKind::Verify))),
+        }
+    }
+}
 
-    Ok((input, duration))
+/// Understands `now`, `today`, `yesterday` (both meaning the start of the day) and
+/// `<count> <unit> ago`, where a month is 30 days and a year is 365 days
+fn phrase_to_duration(phrase: &str, now: NaiveDateTime) -> Option<Duration> {
+    let phrase = phrase.to_lowercase();
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    let midnight = now.date().and_hms_opt(0, 0, 0)?;
+
+    match words[..] {
+        ["now"] => Some(Duration::zero()),
+        ["today"] => Some(midnight - now),
+        ["yesterday"] => Some(midnight - Duration::days(1) - now),
+        [count, unit, "ago"] => {
+            let count: i64 = match count {
+                "a" | "an" => 1,
+                count => count.parse().ok()?,
+            };
+            let unit_secs: i64 = match unit.strip_suffix('s').unwrap_or(unit) {
+                "sec" | "second" => 1,
+                "min" | "minute" => 60,
+                "hour" => 60 * 60,
+                "day" => 24 * 60 * 60,
+                "week" => 7 * 24 * 60 * 60,
+                "month" => 30 * 24 * 60 * 60,
+                "year" => 365 * 24 * 60 * 60,
+                _ => return None,
+            };
+            Duration::try_seconds(unit_secs.checked_mul(count)?.checked_neg()?)
+        }
+        _ => None,
+    }
 }
 
-pub fn parse_size_unit(input: &str) -> IResult<&str, SizeUnit> {
+pub fn parse_size_unit(input: &str) -> ParseResult<'_, SizeUnit> {
     map_res(ws(parse_enum_alias::<SizeUnit>()), SizeUnit::from_str)(input)
 }
 
-pub fn parse_time_unit(input: &str) -> IResult<&str, TimeUnit> {
+pub fn parse_size(input: &str) -> ParseResult<'_, usize> {
+    let (input, number) = ws(parse_positive_number)(input)?;
+    let (input, unit) = parse_size_unit(input)?;
+
+    Ok((input, unit.to_bytes(number)))
+}
+
+/// Parses a size like [`parse_size`], also accepting a bare number of bytes.
+/// Returns whether the unit was left out, so the query can be linted for it.
+pub fn parse_size_or_bytes(input: &str) -> ParseResult<'_, (usize, bool)> {
+    let (input, number) = ws(parse_positive_number)(input)?;
+    let (input, unit) = opt(parse_size_unit)(input)?;
+
+    let unitless = unit.is_none();
+
+    Ok((input, (unit.unwrap_or(SizeUnit::Byte).to_bytes(number), unitless)))
+}
+
+pub fn parse_time_unit(input: &str) -> ParseResult<'_, TimeUnit> {
     map_res(ws(parse_enum_alias::<TimeUnit>()), TimeUnit::from_str)(input)
 }
 
-pub fn parse_file_type(input: &str) -> IResult<&str, FileType> {
+pub fn parse_file_type(input: &str) -> ParseResult<'_, FileType> {
     map_res(ws(parse_enum_alias::<FileType>()), FileType::from_str)(input)
 }
 
-pub fn parse_attribute_name(input: &str) -> IResult<&str, AttributeToken> {
+pub fn parse_entry_kind(input: &str) -> ParseResult<'_, EntryKind> {
+    map_res(ws(parse_enum_alias::<EntryKind>()), EntryKind::from_str)(input)
+}
+
+pub fn parse_time_attribute(input: &str) -> ParseResult<'_, TimeAttribute> {
+    ws(alt((
+        value(TimeAttribute::Access, tag("atime")),
+        value(TimeAttribute::Modification, tag("mtime")),
+        value(TimeAttribute::Birth, tag("btime")),
+    )))(input)
+}
+
+pub fn parse_attribute_name(input: &str) -> ParseResult<'_, AttributeToken> {
     map_res(ws(parse_enum_alias::<AttributeToken>()), AttributeToken::from_str)(input)
 }
 
 pub fn parse_first_non_escaped_quote(
     quote: u8,
-) -> impl FnMut(&str) -> IResult<&str, &str> {
+) -> impl FnMut(&str) -> ParseResult<'_, &str> {
     move |input: &str| {
         let bytes = input.as_bytes();
 
@@ -133,7 +329,7 @@ pub fn parse_first_non_escaped_quote(
     }
 }
 
-pub fn parse_quote_escaped_string(input: &str) -> IResult<&str, &str> {
+pub fn parse_quote_escaped_string(input: &str) -> ParseResult<'_, &str> {
     let single_quote = delimited(
         char(SINGLE_QUOTE_CHAR),
         parse_first_non_escaped_quote(SINGLE_QUOTE_BYTE),
@@ -148,22 +344,44 @@ pub fn parse_quote_escaped_string(input: &str) -> IResult<&str, &str> {
     alt((single_quote, double_quote))(input)
 }
 
-pub fn parse_pattern_till_first_space(input: &str) -> IResult<&str, (bool, &str)> {
+pub fn parse_pattern_till_first_space(input: &str) -> ParseResult<'_, (bool, &str)> {
     let (input, pattern) =
         take_while(|ch: char| !ch.is_whitespace() && !"()".contains(ch))(input)?;
 
     Ok((input, (false, pattern)))
 }
 
-pub fn parse_glob_pattern(input: &str) -> IResult<&str, MatchPattern> {
+/// Parses a quoted string or a bare word that ends at whitespace or a parenthesis
+pub fn parse_string_argument(input: &str) -> ParseResult<'_, &str> {
+    let unquoted = take_while1(|ch: char| !ch.is_whitespace() && !"()".contains(ch));
+    ws(alt((parse_quote_escaped_string, unquoted)))(input)
+}
+
+pub fn parse_path_argument(input: &str) -> ParseResult<'_, PathBuf> {
+    map(parse_string_argument, PathBuf::from)(input)
+}
+
+pub fn parse_glob_pattern(input: &str) -> ParseResult<'_, MatchPattern> {
     let (input, (ignore_case, pattern)) = alt((
         parse_ignore_case_quote_escaped_string,
         parse_pattern_till_first_space,
     ))(input)?;
+    compile_glob_pattern(input, ignore_case, pattern)
+}
 
-    match GlobBuilder::new(pattern).case_insensitive(ignore_case).build() {
-        Ok(glob) => Ok((input, glob.into())),
-        Err(err) => Err(nom::Err::Error(nom::error::Error::from_external_error(
+fn parse_quoted_glob_pattern(input: &str) -> ParseResult<'_, MatchPattern> {
+    let (input, (ignore_case, pattern)) = parse_ignore_case_quote_escaped_string(input)?;
+    compile_glob_pattern(input, ignore_case, pattern)
+}
+
+fn compile_glob_pattern<'a>(
+    input: &'a str,
+    ignore_case: bool,
+    pattern: &str,
+) -> ParseResult<'a, MatchPattern> {
+    match compile_glob(pattern, ignore_case) {
+        Ok(glob) => Ok((input, glob)),
+        Err(err) => Err(nom::Err::Error(ParseError::from_external_error(
             input,
             ErrorKind::Alt,
             err,
@@ -171,33 +389,87 @@ pub fn parse_glob_pattern(input: &str) -> IResult<&str, MatchPattern> {
     }
 }
 
+pub fn compile_glob(
+    pattern: &str,
+    ignore_case: bool,
+) -> Result<MatchPattern, globset::Error> {
+    Ok(MatchPattern::Glob(GlobPattern::new(pattern, ignore_case, false)?))
+}
+
 pub fn parse_ignore_case_quote_escaped_string(
     input: &str,
-) -> IResult<&str, (bool, &str)> {
+) -> ParseResult<'_, (bool, &str)> {
     let (input, (ignore_case, pattern)) =
         tuple((opt(char('i')), parse_quote_escaped_string))(input)?;
 
     Ok((input, (ignore_case.is_some(), pattern)))
 }
 
-pub fn parse_regex_pattern(input: &str) -> IResult<&str, MatchPattern> {
+pub fn parse_regex_pattern(input: &str) -> ParseResult<'_, MatchPattern> {
     let (input, (ignore_case, pattern)) =
         preceded(char('r'), parse_ignore_case_quote_escaped_string)(input)?;
     compile_regex(input, ignore_case, pattern)
 }
 
-pub fn parse_pattern(input: &str) -> IResult<&str, MatchPattern> {
+pub fn parse_pattern(input: &str) -> ParseResult<'_, MatchPattern> {
     alt((parse_regex_pattern, parse_glob_pattern))(input)
 }
 
-fn compile_regex<'a, 'b>(
+/// Parses `~ text` (or `!~ text`) into a case-insensitive glob matching anything
+/// containing `text`. Glob metacharacters in `text` are matched literally.
+pub fn parse_substring_pattern(
+    input: &str,
+) -> ParseResult<'_, (Comparison, MatchPattern)> {
+    let (input, operator) = ws(alt((tag("!~"), tag("~"))))(input)?;
+    let (input, text) = parse_string_argument(input)?;
+
+    let comparison = if operator == "~" { Comparison::Eq } else { Comparison::Neq };
+    match compile_glob(&format!("*{}*", escape_glob(text)), true) {
+        Ok(glob) => Ok((input, (comparison, glob))),
+        Err(err) => Err(nom::Err::Error(ParseError::from_external_error(
+            input,
+            ErrorKind::Alt,
+            err,
+        ))),
+    }
+}
+
+/// Wraps every glob metacharacter in brackets, so `a*b` matches only `a*b`
+pub fn escape_glob(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if "?*[]{}\\".contains(ch) {
+            escaped.extend(['[', ch, ']']);
+        } else {
+            escaped.push(ch);
+        }
+    }
+    escaped
+}
+
+/// Parses a pattern, returning whether it was negated with a `!` in front of its
+/// quotes (`!'*.rs'`, `!r'.+'`). An unquoted `!*.rs` is a glob matching names that
+/// start with `!`, as it always was, and a `!` inside quotes is a part of the
+/// pattern.
+pub fn parse_negatable_pattern(input: &str) -> ParseResult<'_, (bool, MatchPattern)> {
+    let quoted = alt((parse_regex_pattern, parse_quoted_glob_pattern));
+    let negated = map(preceded(ws(char('!')), quoted), |pattern| (true, pattern));
+    let plain = map(parse_pattern, |pattern| (false, pattern));
+
+    alt((negated, plain))(input)
+}
+
+fn compile_regex<'a>(
     input: &'a str,
     ignore_case: bool,
-    pattern: &'b str,
-) -> IResult<&'a str, MatchPattern> {
-    match RegexBuilder::new(pattern).case_insensitive(ignore_case).build() {
+    pattern: &str,
+) -> ParseResult<'a, MatchPattern> {
+    // The case flag is kept inline so it survives recompiling the pattern
+    let pattern =
+        if ignore_case { format!("(?i){pattern}") } else { pattern.to_string() };
+    match Regex::new(&pattern) {
         Ok(rx) => Ok((input, MatchPattern::Regex(rx))),
-        Err(err) => Err(nom::Err::Error(nom::error::Error::from_external_error(
+        Err(err) => Err(nom::Err::Error(ParseError::from_external_error(
             input,
             ErrorKind::Alt,
             err,
@@ -228,6 +500,25 @@ mod test_primitives {
         assert_eq!(parse_comparison("!="), Ok(("", Comparison::Neq)));
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1 Kb and"), Ok(("and", 1000)));
+        assert_eq!(parse_size("2M"), Ok(("", 2_000_000)));
+        assert!(parse_size("2").is_err());
+
+        assert_eq!(parse_size_or_bytes("1 Kb and"), Ok(("and", (1000, false))));
+        assert_eq!(parse_size_or_bytes("2 and"), Ok(("and", (2, true))));
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        assert_eq!(parse_flag(" and"), Ok((" and", Comparison::Eq)));
+        assert_eq!(parse_flag("= true"), Ok(("", Comparison::Eq)));
+        assert_eq!(parse_flag("=false "), Ok(("", Comparison::Neq)));
+        assert_eq!(parse_flag("!= false"), Ok(("", Comparison::Eq)));
+        assert!(parse_flag("> true").is_err());
+    }
+
     #[test]
     fn test_parse_time_unit() {
         assert_eq!(parse_time_unit("minute"), Ok(("", TimeUnit::Minute)));
@@ -252,12 +543,57 @@ mod test_primitives {
     fn test_parse_duration() {
         assert_eq!(parse_duration("now - 1d"), Ok(("", Duration::days(-1))));
         assert_eq!(parse_duration("now"), Ok(("", Duration::days(0))));
+        assert_eq!(parse_duration("now - 1d - 12h"), Ok(("", Duration::hours(-36))));
+        assert_eq!(parse_duration("now + 1h - 30m"), Ok(("", Duration::minutes(30))));
+        assert_eq!(parse_duration("now - 1w + 3d x"), Ok(("x", Duration::days(-4))));
+        assert!(parse_duration("now - 15000000000w - 15000000000w").is_err());
+    }
+
+    #[test]
+    fn test_parse_date_time() {
+        let date = |h, m, s| {
+            SystemTime::from(Local.with_ymd_and_hms(2023, 1, 15, h, m, s).unwrap())
+        };
+
+        assert_eq!(parse_date_time("2023-01-15 x"), Ok(("x", date(0, 0, 0))));
+        assert_eq!(parse_date_time("'2023-01-15 10:30:00'"), Ok(("", date(10, 30, 0))));
+        assert_eq!(parse_date_time("\"2023-01-15T10:30:05\""), Ok(("", date(10, 30, 5))));
+        assert_eq!(parse_date_time("'2023-01-15 10:30'"), Ok(("", date(10, 30, 0))));
+        assert!(parse_date_time("2023-13-15").is_err());
+        assert!(parse_date_time("'2 weeks ago'").is_err());
+
+        assert_eq!(parse_time_point("2023-01-15"), Ok(("", date(0, 0, 0).into())));
+        assert_eq!(parse_time_point("now - 1d"), Ok(("", Duration::days(-1).into())));
+    }
+
+    #[test]
+    fn test_parse_relative_phrase() {
+        let now = chrono::NaiveDate::from_ymd_opt(2024, 3, 15)
+            .unwrap()
+            .and_hms_opt(13, 30, 0)
+            .unwrap();
+        let mut parse = parse_relative_phrase(|| now);
+
+        assert_eq!(parse("\"2 weeks ago\""), Ok(("", Duration::days(-14))));
+        assert_eq!(parse("'an Hour ago' x"), Ok(("x", Duration::hours(-1))));
+        assert_eq!(
+            parse("\"yesterday\""),
+            Ok(("", -(Duration::days(1) + Duration::hours(13) + Duration::minutes(30))))
+        );
+        assert_eq!(
+            parse("'today'"),
+            Ok(("", -(Duration::hours(13) + Duration::minutes(30))))
+        );
+        assert!(parse("'2 fortnights ago'").is_err());
+        assert!(parse("'in 2 days'").is_err());
+        assert!(parse("'99999999999 years ago'").is_err());
+        assert!(parse("'2000000000 years ago'").is_err());
     }
 
     #[test]
     fn test_parse_glob_pattern() {
         fn g(pattern: &str) -> MatchPattern {
-            MatchPattern::Glob(Glob::new(pattern).unwrap().compile_matcher())
+            Glob::new(pattern).unwrap().into()
         }
 
         assert_eq!(
@@ -279,7 +615,7 @@ mod test_primitives {
     #[test]
     fn test_parse_glob_ignore_case_pattern() {
         fn g(pattern: &str) -> MatchPattern {
-            GlobBuilder::new(pattern).case_insensitive(true).build().unwrap().into()
+            compile_glob(pattern, true).unwrap()
         }
 
         assert_eq!(parse_glob_pattern(r"i'sample?*='"), Ok(("", g("sample?*="))));
@@ -302,10 +638,24 @@ mod test_primitives {
     #[test]
     fn test_parse_regex_ignore_case_pattern() {
         fn r(pattern: &str) -> MatchPattern {
-            RegexBuilder::new(pattern).case_insensitive(true).build().unwrap().into()
+            Regex::new(&format!("(?i){pattern}")).unwrap().into()
         }
 
         assert_eq!(parse_regex_pattern(r"ri'sample'"), Ok(("", r(r"sample"))));
+        assert!(parse_regex_pattern(r"ri'sample'").unwrap().1.is_match("SAMPLE"));
+    }
+
+    #[test]
+    fn test_parse_path_argument() {
+        assert_eq!(
+            parse_path_argument(" target/build.log and"),
+            Ok(("and", PathBuf::from("target/build.log")))
+        );
+        assert_eq!(
+            parse_path_argument("'my dir/ref')"),
+            Ok((")", PathBuf::from("my dir/ref")))
+        );
+        assert!(parse_path_argument(" ").is_err());
     }
 
     #[test]
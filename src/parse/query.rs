@@ -0,0 +1,317 @@
+use std::fmt::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Local};
+use nnf::parse_tree::ExpressionNode;
+
+use crate::parse::comparison::Comparison;
+use crate::parse::filter::Filter;
+use crate::parse::match_pattern::MatchPattern;
+use crate::parse::permission_mode::PermissionMode;
+use crate::parse::time_point::TimePoint;
+
+/// Writes `root` back as a query that parses to an equal expression. `None` if a
+/// filter has no query syntax, e.g. the `--names-from` set.
+///
+/// Every branch is parenthesized, sizes are in bytes and relative times in seconds
+/// and nanoseconds. The `Display` of the tree writes the same leaves with ∧ ∨ ¬. The
+/// options set by flags, like read timeouts or byte limits, are not a part of the
+/// query and are left out.
+pub fn to_query(root: &ExpressionNode<Filter>) -> Option<String> {
+    let mut query = String::new();
+    write_query(root, &mut query)?;
+    Some(query)
+}
+
+fn write_query(node: &ExpressionNode<Filter>, query: &mut String) -> Option<()> {
+    match node {
+        ExpressionNode::Leaf(filter) => query.push_str(&filter.to_query()?),
+        ExpressionNode::And(left, right) | ExpressionNode::Or(left, right) => {
+            let operator =
+                if matches!(node, ExpressionNode::And(..)) { "and" } else { "or" };
+            query.push('(');
+            write_query(left, query)?;
+            let _ = write!(query, " {operator} ");
+            write_query(right, query)?;
+            query.push(')');
+        }
+        ExpressionNode::Not(node) => {
+            query.push_str("not (");
+            write_query(node, query)?;
+            query.push(')');
+        }
+    }
+    Some(())
+}
+
+impl Filter {
+    /// This filter in query syntax, see [`to_query`]
+    pub fn to_query(&self) -> Option<String> {
+        let pattern = |name: &str, comparison: &Comparison, value: &MatchPattern| {
+            Some(format!("{name} {comparison} {}", quote_pattern(value)?))
+        };
+        let flag = |name: &str, comparison: &Comparison| match comparison {
+            Comparison::Eq => Some(name.to_string()),
+            Comparison::Neq => Some(format!("{name} = false")),
+            _ => None,
+        };
+
+        match self {
+            Self::Name { value, comparison } => pattern("name", comparison, value),
+            Self::Extension { value, comparison } => pattern("ext", comparison, value),
+            Self::Stem { value, comparison } => pattern("stem", comparison, value),
+            Self::Parent { value, comparison } => pattern("parent", comparison, value),
+            // in a query only a `~` substring lets `*` cross `/`
+            Self::Path { value: MatchPattern::Glob(glob), comparison }
+                if !glob.literal_separator() =>
+            {
+                let operator = if *comparison == Comparison::Eq { "~" } else { "!~" };
+                Some(format!(
+                    "path {operator} {}",
+                    quote_argument(&glob.as_substring()?)?
+                ))
+            }
+            Self::Path { value, comparison } => pattern("path", comparison, value),
+            Self::Contains { value, comparison, .. } => {
+                pattern("contains", comparison, value)
+            }
+            Self::SymlinkTarget { value, comparison } => {
+                pattern("target", comparison, value)
+            }
+
+            Self::Size { value, comparison, unitless } => {
+                let unit = if *unitless { "" } else { "B" };
+                Some(format!("size {comparison} {value}{unit}"))
+            }
+            Self::DirSize { value, comparison, .. } => {
+                Some(format!("dirsize {comparison} {value}B"))
+            }
+            Self::Depth { value, comparison } => {
+                Some(format!("depth {comparison} {value}"))
+            }
+            Self::ChildCount { value, comparison } => {
+                Some(format!("children {comparison} {value}"))
+            }
+            Self::Inode { value, comparison } => {
+                Some(format!("inode {comparison} {value}"))
+            }
+            Self::Device { value, comparison } => {
+                Some(format!("dev {comparison} {value}"))
+            }
+            Self::User { value, comparison } => {
+                Some(format!("user {comparison} {value}"))
+            }
+            Self::Group { value, comparison } => {
+                Some(format!("group {comparison} {value}"))
+            }
+
+            Self::Type { value, comparison, .. }
+            | Self::TypeByExtension { value, comparison } => {
+                Some(format!("type {comparison} {value}"))
+            }
+            Self::Kind { value, comparison } => {
+                Some(format!("kind {comparison} {value}"))
+            }
+
+            Self::AccessTime { value, comparison } => {
+                Some(format!("atime {comparison} {}", format_time_point(value)))
+            }
+            Self::ModificationTime { value, comparison } => {
+                Some(format!("mtime {comparison} {}", format_time_point(value)))
+            }
+            Self::CompareAttrs { left, right, comparison } => {
+                Some(format!("{left} {comparison} {right}"))
+            }
+
+            Self::Permissions { value, mode, comparison } => {
+                let prefix = match mode {
+                    PermissionMode::Exact => "",
+                    PermissionMode::AllSet => "-",
+                    PermissionMode::AnySet => "/",
+                };
+                Some(format!("perms {comparison} {prefix}{:o}", value.mode() & 0o7777))
+            }
+            Self::Xattr { name, value, comparison } => {
+                let argument = match value {
+                    Some(value) => format!("{name}:{value}"),
+                    None => name.clone(),
+                };
+                Some(format!("xattr {comparison} {}", quote_argument(&argument)?))
+            }
+            Self::NewerThan { value, comparison, .. } => {
+                let (name, comparison) = match comparison {
+                    Comparison::Gt => ("newer", Comparison::Eq),
+                    Comparison::Lte => ("newer", Comparison::Neq),
+                    Comparison::Lt => ("older", Comparison::Eq),
+                    _ => ("older", Comparison::Neq),
+                };
+                Some(format!("{name} {comparison} {}", quote_path(value)?))
+            }
+
+            Self::Empty { dirs: true, comparison } => flag("empty", comparison),
+            Self::Empty { dirs: false, .. } => None,
+            Self::Binary { text, comparison, .. } => {
+                flag(if *text { "text" } else { "binary" }, comparison)
+            }
+            Self::Mine { group, comparison } => {
+                flag(if *group { "my-group" } else { "mine" }, comparison)
+            }
+            Self::Access { value, comparison } => flag(&value.to_string(), comparison),
+            Self::NameInSet { .. } => None,
+
+            #[cfg(test)]
+            Self::Bool { value, comparison } => {
+                Some(format!("bool {comparison} {value}"))
+            }
+        }
+    }
+}
+
+/// Writes a glob or a regex with the case flag it was compiled with
+fn quote_pattern(value: &MatchPattern) -> Option<String> {
+    match value {
+        // the (?i) of a case-insensitive regex stays in the pattern itself
+        MatchPattern::Regex(regex) => Some(format!("r{}", quote(regex.as_str())?)),
+        MatchPattern::Glob(glob) => {
+            let case = if glob.ignore_case() { "i" } else { "" };
+            Some(format!("{case}{}", quote(glob.glob())?))
+        }
+    }
+}
+
+/// Quotes `text` with the quote it does not contain. The parser ends a quoted string
+/// at the first quote without a backslash before it and keeps the backslashes, so a
+/// text with both quotes, a trailing backslash or no characters cannot be quoted.
+fn quote(text: &str) -> Option<String> {
+    if text.is_empty() || text.ends_with('\\') {
+        return None;
+    }
+
+    ['\'', '"']
+        .into_iter()
+        .find(|&quote| {
+            let unescaped = text
+                .match_indices(quote)
+                .any(|(index, _)| !text[..index].ends_with('\\'));
+            !unescaped
+        })
+        .map(|quote| format!("{quote}{text}{quote}"))
+}
+
+/// Writes a string argument bare if it is a single word, quoted otherwise
+fn quote_argument(text: &str) -> Option<String> {
+    let is_word = !text.is_empty()
+        && !text.starts_with(['\'', '"'])
+        && !text.contains(|ch: char| ch.is_whitespace() || "()".contains(ch));
+    if is_word {
+        Some(text.to_string())
+    } else {
+        quote(text)
+    }
+}
+
+fn quote_path(path: &Path) -> Option<String> {
+    quote_argument(path.to_str()?)
+}
+
+/// Writes a time with all of its precision: relative ones as seconds and the
+/// nanoseconds left over, absolute ones with the fraction of the second
+fn format_time_point(value: &TimePoint) -> String {
+    match value {
+        TimePoint::Relative(duration) => {
+            let seconds = duration.num_seconds();
+            let nanos = (*duration - Duration::seconds(seconds)).num_nanoseconds();
+            let mut query = String::from("now");
+            for (value, unit) in [(seconds, "s"), (nanos.unwrap_or_default(), "ns")] {
+                let _ = match value {
+                    0 => Ok(()),
+                    value if value < 0 => write!(query, " - {}{unit}", -value),
+                    value => write!(query, " + {value}{unit}"),
+                };
+            }
+            query
+        }
+        TimePoint::Absolute(time) => {
+            let time = DateTime::<Local>::from(*time);
+            format!("'{}'", time.format("%Y-%m-%d %H:%M:%S%.f"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nnf::parse_tree::ExpressionNode;
+
+    use crate::parse::parse_root;
+    use crate::parse::query::{quote, to_query};
+
+    #[test]
+    fn test_round_trip() {
+        let expressions = [
+            "name = *.rs",
+            "name = 'a file with spaces.txt' and ext != i'JPG'",
+            r#"name = "it's here" or stem = 'say "hi"'"#,
+            r"name = r'^\d+\.log$' and path = ri'/SRC/.*'",
+            "name = !*.rs and parent = migrations",
+            "path = src/*.rs or path ~ 'Some Dir'",
+            r"contains = *needle* or contains != r'fn\s+main'",
+            "target = '../shared lib'",
+            "size > 1Mb and size <= 10Mb and dirsize >= 5K and size = 1Mb..2Mb",
+            "depth < 3 and children > 0 and inode != 42 and dev = 7",
+            "user = 0 or group != 0",
+            "type = vid and kind = dir and kind != f",
+            "mtime > now - 2d and atime <= now + 5s and mtime within 1h",
+            "mtime < 2023-01-15 and atime >= '2023-01-15 10:30:00'",
+            "mtime > now - 5s - 500ns and atime < '2023-01-15 10:30:00.25'",
+            "atime < mtime and btime != mtime",
+            "perms = 644 or perms = -755 or perms = /111 or perms = u+x or perms = go-w",
+            "xattr = user.comment or xattr != 'user.note:two words'",
+            "newer = Cargo.toml and older != Cargo.toml and newer != Cargo.toml",
+            "empty and binary = false and text and mine = false and my-group",
+            "readable and writable = false and executable",
+            "not (name = *.rs or (size > 1 and not depth = 2))",
+            "1Mb < size and 2 >= depth",
+        ];
+
+        for expression in expressions {
+            let parsed = parse_root(expression).unwrap();
+            let query = to_query(&parsed).unwrap();
+            let reparsed = parse_root(&query)
+                .unwrap_or_else(|err| panic!("{query} from {expression}: {err:?}"));
+            assert_eq!(reparsed, parsed, "{query} from {expression}");
+            assert_eq!(to_query(&reparsed).unwrap(), query);
+
+            for filter in parsed.extract_leafs() {
+                let written = filter.to_string();
+                let reparsed = parse_root(&written)
+                    .unwrap_or_else(|err| panic!("{written} from {expression}: {err:?}"));
+                assert_eq!(reparsed, ExpressionNode::Leaf(filter.clone()), "{written}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_query() {
+        let query = |expression| to_query(&parse_root(expression).unwrap()).unwrap();
+
+        assert_eq!(query("ext = i'JPG'"), "ext = i'JPG'");
+        assert_eq!(query("path ~ 'Some Dir'"), "path ~ 'Some Dir'");
+        assert_eq!(query("size > 1K"), "size > 1000B");
+        assert_eq!(query("mtime > now - 1h"), "mtime > now - 3600s");
+        assert_eq!(
+            query("depth = 1 and depth = 2 or depth = 3"),
+            "((depth = 1 and depth = 2) or depth = 3)"
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a b").unwrap(), "'a b'");
+        assert_eq!(quote("it's").unwrap(), r#""it's""#);
+        assert_eq!(quote(r"it\'s").unwrap(), r"'it\'s'");
+        assert!(quote(r#"'both" "#).is_none());
+        assert!(quote(r"trailing\").is_none());
+        assert!(quote("").is_none());
+    }
+}
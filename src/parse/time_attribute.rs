@@ -0,0 +1,19 @@
+use std::fmt::{Display, Formatter};
+
+/// A timestamp of an entry, for comparing two of them with each other
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TimeAttribute {
+    Access,
+    Modification,
+    Birth,
+}
+
+impl Display for TimeAttribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeAttribute::Access => write!(f, "atime"),
+            TimeAttribute::Modification => write!(f, "mtime"),
+            TimeAttribute::Birth => write!(f, "btime"),
+        }
+    }
+}
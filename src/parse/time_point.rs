@@ -0,0 +1,40 @@
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Duration, Local};
+
+/// A moment to compare atime and mtime against
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum TimePoint {
+    /// An offset from the moment the search started
+    Relative(Duration),
+    /// A calendar date and time
+    Absolute(SystemTime),
+}
+
+impl From<Duration> for TimePoint {
+    fn from(duration: Duration) -> Self {
+        Self::Relative(duration)
+    }
+}
+
+impl From<SystemTime> for TimePoint {
+    fn from(time: SystemTime) -> Self {
+        Self::Absolute(time)
+    }
+}
+
+impl Display for TimePoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Relative(duration) => write!(f, "{duration}"),
+            Self::Absolute(time) => {
+                write!(
+                    f,
+                    "{}",
+                    DateTime::<Local>::from(*time).format("%Y-%m-%d %H:%M:%S%.f")
+                )
+            }
+        }
+    }
+}
@@ -3,19 +3,25 @@ use chrono::Duration;
 use crate::mk_filter_enum;
 
 mk_filter_enum!(TimeUnit, TIME_UNIT_ALIASES, [
+    Nanosecond: "ns", "nanos",
     Second: "s", "secs",
     Minute: "m", "min", "mins", "minute",
     Hour: "h", "hour",
-    Day: "d", "day"
+    Day: "d", "day",
+    Week: "w", "week"
 ]);
 
 impl TimeUnit {
-    pub fn to_duration(&self, value: i64) -> Duration {
+    /// Returns `None` if the duration does not fit into [`Duration`]
+    pub fn to_duration(&self, value: usize) -> Option<Duration> {
+        let value = i64::try_from(value).ok()?;
         match self {
-            TimeUnit::Second => Duration::seconds(value),
-            TimeUnit::Minute => Duration::minutes(value),
-            TimeUnit::Hour => Duration::hours(value),
-            TimeUnit::Day => Duration::days(value),
+            TimeUnit::Nanosecond => Some(Duration::nanoseconds(value)),
+            TimeUnit::Second => Duration::try_seconds(value),
+            TimeUnit::Minute => Duration::try_minutes(value),
+            TimeUnit::Hour => Duration::try_hours(value),
+            TimeUnit::Day => Duration::try_days(value),
+            TimeUnit::Week => Duration::try_weeks(value),
         }
     }
 }
@@ -1,5 +1,4 @@
-use nom::IResult;
-
+use crate::errors::ParseResult;
 use crate::parse::filter::Filter;
 
 pub trait AliasExt {
@@ -8,5 +7,5 @@ pub trait AliasExt {
 }
 
 pub trait GenericParser {
-    fn parse(self, input: &str) -> IResult<&str, Filter>;
+    fn parse(self, input: &str) -> ParseResult<'_, Filter>;
 }
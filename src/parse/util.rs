@@ -1,12 +1,13 @@
 use std::collections::BTreeMap;
 
 use nom::character::complete::multispace0;
-use nom::error::{ErrorKind, ParseError};
+use nom::error::ErrorKind;
 use nom::sequence::delimited;
 use nom::Err::Error;
 use nom::IResult;
 use strum::IntoEnumIterator;
 
+use crate::errors::{ParseError, ParseResult};
 use crate::parse::traits::AliasExt;
 
 pub fn prepare_enum_map<Q>() -> BTreeMap<&'static str, &'static str>
@@ -44,20 +45,20 @@ pub fn split_by_longest_alias<'a>(
     None
 }
 
-pub fn parse_enum_alias<Q>() -> impl FnMut(&str) -> IResult<&str, &str>
+pub fn parse_enum_alias<Q>() -> impl FnMut(&str) -> ParseResult<'_, &str>
 where
     Q: AliasExt,
 {
     move |input| match Q::split_by_longest_alias(input) {
         Some(result) => Ok(result),
-        None => Err(Error(nom::error::Error::new(input, ErrorKind::NoneOf))),
+        None => Err(Error(ParseError::new(input, ErrorKind::NoneOf))),
     }
 }
 
 #[rustfmt::skip]
-pub fn ws<'a, F: 'a, O, E: ParseError<&'a str>>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
+pub fn ws<'a, F, O, E: nom::error::ParseError<&'a str>>(inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
     where
-        F: FnMut(&'a str) -> IResult<&'a str, O, E>,
+        F: FnMut(&'a str) -> IResult<&'a str, O, E> + 'a,
 {
     delimited(
         multispace0,
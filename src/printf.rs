@@ -0,0 +1,167 @@
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+
+use crate::errors::GenericError;
+use crate::walk::traits::DirEntryWrapperExt;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum Directive {
+    Literal(Vec<u8>),
+    Path,
+    Size,
+    Mode,
+    UserId,
+    GroupId,
+    ModificationTime,
+}
+
+/// An output template for `--printf`: `%p` path, `%s` size, `%m` octal mode, `%u` uid,
+/// `%g` gid, `%t` mtime in RFC 3339, `%%` a percent sign; `\t`, `\n`, `\0` and `\\`
+/// are unescaped
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PrintfTemplate {
+    directives: Vec<Directive>,
+}
+
+impl PrintfTemplate {
+    pub fn parse(template: &str) -> Result<Self, GenericError> {
+        let mut directives = vec![];
+        let mut literal = vec![];
+        let mut chars = template.chars();
+
+        while let Some(ch) = chars.next() {
+            let directive = match ch {
+                '%' => match chars.next() {
+                    Some('p') => Directive::Path,
+                    Some('s') => Directive::Size,
+                    Some('m') => Directive::Mode,
+                    Some('u') => Directive::UserId,
+                    Some('g') => Directive::GroupId,
+                    Some('t') => Directive::ModificationTime,
+                    Some('%') => {
+                        literal.push(b'%');
+                        continue;
+                    }
+                    other => {
+                        let directive = other.map(|ch| format!("%{ch}"));
+                        return Err(GenericError::UnknownPrintfDirective(
+                            directive.unwrap_or_else(|| "%".to_string()),
+                        ));
+                    }
+                },
+                '\\' => {
+                    match chars.next() {
+                        Some('t') => literal.push(b'\t'),
+                        Some('n') => literal.push(b'\n'),
+                        Some('0') => literal.push(b'\0'),
+                        Some('\\') | None => literal.push(b'\\'),
+                        Some(other) => {
+                            literal.push(b'\\');
+                            literal.extend(other.to_string().as_bytes());
+                        }
+                    }
+                    continue;
+                }
+                ch => {
+                    literal.extend(ch.to_string().as_bytes());
+                    continue;
+                }
+            };
+
+            if !literal.is_empty() {
+                directives.push(Directive::Literal(std::mem::take(&mut literal)));
+            }
+            directives.push(directive);
+        }
+
+        if !literal.is_empty() {
+            directives.push(Directive::Literal(literal));
+        }
+
+        Ok(Self { directives })
+    }
+
+    /// Formats the entry into `buf`, writing `path` for `%p` as raw bytes. Fields that
+    /// cannot be read are written as `?`.
+    pub fn format<E: DirEntryWrapperExt>(
+        &self,
+        entry: &E,
+        path: &Path,
+        buf: &mut Vec<u8>,
+    ) {
+        for directive in &self.directives {
+            let field = match directive {
+                Directive::Literal(literal) => {
+                    buf.extend(literal);
+                    continue;
+                }
+                Directive::Path => {
+                    buf.extend(path.as_os_str().as_bytes());
+                    continue;
+                }
+                Directive::Size => entry.get_size().map(|size| size.to_string()),
+                Directive::Mode => entry
+                    .get_permissions()
+                    .map(|permissions| format!("{:o}", permissions.mode() & 0o7777)),
+                Directive::UserId => entry.get_user_id().map(|uid| uid.to_string()),
+                Directive::GroupId => entry.get_group_id().map(|gid| gid.to_string()),
+                Directive::ModificationTime => entry
+                    .get_mtime()
+                    .map(|mtime| DateTime::<Local>::from(mtime).to_rfc3339()),
+            };
+
+            buf.extend(field.as_deref().unwrap_or("?").as_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::fs::Permissions;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    use chrono::{DateTime, Local};
+
+    use crate::errors::GenericError;
+    use crate::printf::PrintfTemplate;
+    use crate::test_utils::DirEntryMock;
+
+    #[test]
+    fn test_format() {
+        let path = PathBuf::from(OsStr::from_bytes(b"/tmp/caf\xe9"));
+        let mtime = SystemTime::now();
+        let entry = DirEntryMock::default()
+            .set_file(path.clone())
+            .set_size(42)
+            .set_permissions(Permissions::from_mode(0o104755))
+            .set_user_id(1000)
+            .set_mtime(mtime);
+
+        let template = PrintfTemplate::parse(r"%p\t%s\t%m %u:%g 100%% %t\n").unwrap();
+        let mut buf = vec![];
+        template.format(&entry, &path, &mut buf);
+
+        let mut expected = b"/tmp/caf\xe9\t42\t4755 1000:? 100% ".to_vec();
+        expected.extend(DateTime::<Local>::from(mtime).to_rfc3339().as_bytes());
+        expected.push(b'\n');
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_unknown_directive() {
+        for template in ["%x", "size %"] {
+            let error = PrintfTemplate::parse(template).unwrap_err();
+            assert!(
+                matches!(error, GenericError::UnknownPrintfDirective(_)),
+                "{error:?}"
+            );
+        }
+    }
+}
@@ -1,80 +1,630 @@
-use std::io::{LineWriter, Stderr, Stdout, Write};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::io::{BufRead, IsTerminal, LineWriter, Stderr, Stdout, Write};
+use std::ops::ControlFlow;
 use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use ignore::{DirEntry, WalkState};
+use chrono::{DateTime, Local};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use itertools::Itertools;
 use nnf::parse_tree::ExpressionNode;
+use uzers::{Groups, Users, UsersCache};
 
-use crate::config::Config;
+use crate::config::{Config, ErrorFormat};
+use crate::evaluate::filter_impl::DEFAULT_READ_TIMEOUT;
+use crate::evaluate::profile::{Profile, Recorder};
+use crate::evaluate::traits::RequiresFileExt;
 use crate::parse::filter::Filter;
-use crate::{Evaluate, GenericError};
+use crate::printf::PrintfTemplate;
+use crate::walk::archive::{for_each_member, ArchiveMember};
+use crate::walk::entry_type::EntryType;
+use crate::walk::traits::DirEntryWrapperExt;
+use crate::walk::WalkedEntry;
+use crate::GenericError;
 
 #[derive(Eq, PartialEq)]
 pub enum ProcessStatus {
     InProgress,
     SendError,
     Cancelled,
+    /// A match was found in quiet mode, nothing else needs to be walked
+    Matched,
+}
+
+/// Counters shared by the walker threads and the receiver
+#[derive(Debug)]
+pub struct WalkStats {
+    pub visited: AtomicUsize,
+    pub matched: AtomicUsize,
+    pub errors: AtomicUsize,
+    pub started: Instant,
+}
+
+impl Default for WalkStats {
+    fn default() -> Self {
+        Self {
+            visited: AtomicUsize::new(0),
+            matched: AtomicUsize::new(0),
+            errors: AtomicUsize::new(0),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl WalkStats {
+    pub fn summary(&self) -> String {
+        format!(
+            "Visited: {}\nMatched: {}\nErrors: {}\nElapsed: {:.3}s",
+            self.visited.load(Ordering::Relaxed),
+            self.matched.load(Ordering::Relaxed),
+            self.errors.load(Ordering::Relaxed),
+            self.started.elapsed().as_secs_f64()
+        )
+    }
+}
+
+/// Matched paths grouped by the `(dev, inode)` of the file they name, along with
+/// its link count
+#[derive(Debug, Default)]
+pub struct HardlinkGroups {
+    groups: BTreeMap<(u64, u64), (u64, BTreeSet<PathBuf>)>,
+}
+
+impl HardlinkGroups {
+    pub fn add(&mut self, dev: u64, inode: u64, nlink: u64, path: PathBuf) {
+        let (links, paths) = self.groups.entry((dev, inode)).or_default();
+        *links = nlink;
+        paths.insert(path);
+    }
+
+    /// Returns the sorted groups of distinct paths sharing an inode, skipping files
+    /// with a single link, even when they were matched more than once
+    pub fn into_groups(self) -> Vec<Vec<PathBuf>> {
+        self.groups
+            .into_values()
+            .filter(|(nlink, paths)| *nlink > 1 && paths.len() > 1)
+            .map(|(_, paths)| paths.into_iter().collect())
+            .collect()
+    }
+}
+
+/// Counts the matches under each immediate subdirectory of the start directories,
+/// to stop printing from one once it reaches `max` of them
+#[derive(Debug)]
+pub struct DirQuota {
+    start_dirs: Vec<PathBuf>,
+    max: usize,
+    counts: HashMap<PathBuf, usize>,
+}
+
+impl DirQuota {
+    pub fn new(start_dirs: Vec<PathBuf>, max: usize) -> Self {
+        Self { start_dirs, max, counts: HashMap::new() }
+    }
+
+    /// Whether `path` is still within the quota of its directory, counting it if so.
+    /// Paths are keyed by the first component under the most specific start
+    /// directory containing them, and by themselves outside of any
+    pub fn admit(&mut self, path: &Path) -> bool {
+        let start_dir = self
+            .start_dirs
+            .iter()
+            .filter(|dir| path.starts_with(dir))
+            .max_by_key(|dir| dir.components().count());
+
+        let key = match start_dir {
+            Some(dir) => match path.strip_prefix(dir).unwrap().components().next() {
+                Some(component) => dir.join(component),
+                None => dir.clone(),
+            },
+            None => path.to_path_buf(),
+        };
+
+        let count = self.counts.entry(key).or_default();
+        if *count >= self.max {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+/// How much of `ARG_MAX` a batched command leaves unused, like xargs does
+const ARGV_HEADROOM: usize = 2048;
+
+/// A command run for each match, like `-exec` in find. Every `{}` in its arguments
+/// is replaced by the path, which is appended instead when there is none.
+/// A batched command is run once for many matches, like `-exec ... +`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecCommand {
+    /// The program followed by its arguments, never empty
+    args: Vec<String>,
+    batched: bool,
+}
+
+impl ExecCommand {
+    pub fn new(args: Vec<String>) -> Self {
+        assert!(!args.is_empty(), "clap requires at least the program");
+        Self { args, batched: false }
+    }
+
+    pub fn batched(args: Vec<String>) -> Self {
+        Self { batched: true, ..Self::new(args) }
+    }
+
+    pub fn is_batched(&self) -> bool {
+        self.batched
+    }
+
+    /// Puts all `paths` in place of each argument that is exactly `{}`, or after the
+    /// arguments when there is none
+    pub fn batch_argv(&self, paths: &[PathBuf]) -> Vec<OsString> {
+        let paths = paths.iter().map(|path| path.as_os_str().to_owned());
+        let mut argv = vec![];
+        let mut substituted = false;
+        for arg in &self.args {
+            if arg == "{}" {
+                substituted = true;
+                argv.extend(paths.clone());
+            } else {
+                argv.push(arg.into());
+            }
+        }
+
+        if !substituted {
+            argv.extend(paths);
+        }
+        argv
+    }
+
+    /// How many bytes of argv the paths of a batch may take: `ARG_MAX` less the
+    /// environment, the other arguments and some headroom
+    pub fn batch_limit(&self) -> usize {
+        let arg_max = match unsafe { libc::sysconf(libc::_SC_ARG_MAX) } {
+            arg_max if arg_max > 0 => arg_max as usize,
+            _ => 128 * 1024,
+        };
+        let environment: usize = std::env::vars_os()
+            .map(|(name, value)| arg_cost(&name) + value.len() + 1)
+            .sum();
+        let arguments: usize = self
+            .args
+            .iter()
+            .filter(|arg| *arg != "{}")
+            .map(|arg| arg_cost(OsStr::new(arg)))
+            .sum();
+
+        arg_max.saturating_sub(environment + arguments + ARGV_HEADROOM)
+    }
+
+    /// Substitutes `path` into the arguments without converting it to utf8
+    pub fn argv(&self, path: &Path) -> Vec<OsString> {
+        let path = path.as_os_str().as_bytes();
+        let mut substituted = false;
+        let mut argv = self
+            .args
+            .iter()
+            .map(|arg| {
+                let mut parts = arg.split("{}").map(str::as_bytes);
+                let mut bytes = parts.next().unwrap_or_default().to_vec();
+                for part in parts {
+                    substituted = true;
+                    bytes.extend_from_slice(path);
+                    bytes.extend_from_slice(part);
+                }
+                OsString::from(OsStr::from_bytes(&bytes))
+            })
+            .collect_vec();
+
+        if !substituted {
+            argv.push(OsStr::from_bytes(path).to_owned());
+        }
+        argv
+    }
+}
+
+/// What an argument takes of `ARG_MAX`: its bytes, the NUL ending it and a pointer
+fn arg_cost(arg: &OsStr) -> usize {
+    arg.len() + 1 + std::mem::size_of::<usize>()
+}
+
+/// The paths waiting for a batched command, collected until the next one would
+/// not fit into `limit` bytes of argv
+#[derive(Debug)]
+pub struct ExecBatch {
+    paths: Vec<PathBuf>,
+    size: usize,
+    limit: usize,
+}
+
+impl ExecBatch {
+    pub fn new(limit: usize) -> Self {
+        Self { paths: vec![], size: 0, limit }
+    }
+
+    /// Adds `path`, returning the paths collected before it if it does not fit with
+    /// them. A path too long for any batch still gets one of its own
+    pub fn push(&mut self, path: PathBuf) -> Option<Vec<PathBuf>> {
+        let cost = arg_cost(path.as_os_str());
+        let full = (!self.paths.is_empty() && self.size + cost > self.limit)
+            .then(|| self.take());
+
+        self.size += cost;
+        self.paths.push(path);
+        full
+    }
+
+    /// Returns the collected paths, leaving the batch empty
+    pub fn take(&mut self) -> Vec<PathBuf> {
+        self.size = 0;
+        std::mem::take(&mut self.paths)
+    }
+}
+
+/// Formats `argv` as a shell command line, quoting the arguments that need it.
+/// Arguments that are not valid utf8 are shown lossily, and the line then ends with
+/// a comment saying so, since copying it would not run the same command
+pub fn format_command(argv: &[OsString]) -> String {
+    let mut lossy = false;
+    let mut line = argv
+        .iter()
+        .map(|arg| {
+            lossy |= arg.to_str().is_none();
+            shell_quote(&arg.to_string_lossy()).into_owned()
+        })
+        .join(" ");
+
+    if lossy {
+        line.push_str("  # not valid UTF-8, shown lossily");
+    }
+    line
+}
+
+/// Wraps `arg` in single quotes unless it only has characters a shell leaves alone
+fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let is_plain = |ch: char| ch.is_ascii_alphanumeric() || "_@%+=:,./-".contains(ch);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        return Cow::Borrowed(arg);
+    }
+
+    Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
 }
 
 #[derive(Debug)]
 pub enum EntryMessage {
     Success(DirEntry),
+    /// A match with the lines its `contains` filters matched, and their numbers
+    MatchLines(DirEntry, Vec<(usize, String)>),
+    /// A matching archive member, printed as `archive.zip::member/path`
+    ArchiveMember(ArchiveMember),
     Error(DirEntry, GenericError),
     Init,
 }
 
+/// How the senders match the walked entries
+#[derive(Debug, Clone)]
+pub struct SenderOptions {
+    /// Match the members of archives too
+    pub search_archives: bool,
+    /// How long a single read of an archive may block
+    pub read_timeout: Duration,
+    /// Read the metadata of symlinks with stat, describing their targets, instead
+    /// of lstat
+    pub follow_metadata: bool,
+    /// Send the lines the `contains` filters matched along with the matches
+    pub show_match_lines: bool,
+    /// Time every filter evaluation of the run
+    pub profile: Option<Profile>,
+}
+
+impl Default for SenderOptions {
+    fn default() -> Self {
+        Self {
+            search_archives: false,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            follow_metadata: true,
+            show_match_lines: false,
+            profile: None,
+        }
+    }
+}
+
+impl From<&Config> for SenderOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            search_archives: config.search_archives,
+            read_timeout: config.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT),
+            follow_metadata: config.follow_metadata,
+            show_match_lines: config.show_match_lines,
+            profile: config.profile.then(Profile::default),
+        }
+    }
+}
+
+/// The expressions a sender evaluates
+#[derive(Clone, Copy)]
+struct Query<'a> {
+    root: &'a ExpressionNode<Filter>,
+    /// Directories matching it are not descended into
+    prune: Option<&'a ExpressionNode<Filter>>,
+    /// Whether only files can match `root`, so nothing else is evaluated
+    requires_file: bool,
+}
+
+impl<'a> Query<'a> {
+    fn new(
+        root: &'a ExpressionNode<Filter>,
+        prune: Option<&'a ExpressionNode<Filter>>,
+    ) -> Self {
+        Self { root, prune, requires_file: root.requires_file() }
+    }
+}
+
+/// Walks the directories, sending the entries matching `root_node`. Directories
+/// matching `prune_node` are not descended into.
 pub fn spawn_senders(
     status: &Arc<Mutex<ProcessStatus>>,
     root_node: &Arc<ExpressionNode<Filter>>,
+    prune_node: Option<&Arc<ExpressionNode<Filter>>>,
+    stats: &Arc<WalkStats>,
     sender: kanal::Sender<EntryMessage>,
     parallel_walker: ignore::WalkParallel,
+    options: SenderOptions,
 ) {
+    let query = Query::new(root_node, prune_node.map(Arc::as_ref));
+    let options = &options;
     parallel_walker.run(|| {
-        let root = Arc::clone(root_node);
-        let status = Arc::clone(status);
         let sender = sender.clone();
+        let mut recorder = Recorder::new(options.profile.as_ref());
 
-        sender.send(EntryMessage::Init).unwrap();
+        // the receiver may already be done, e.g. after the first match in quiet
+        // mode; process_entry then stops the walk
+        let _ = sender.send(EntryMessage::Init);
 
         Box::new(move |entry| {
-            if !status.lock().unwrap().eq(&ProcessStatus::InProgress) {
-                return WalkState::Quit;
+            if let (true, Ok(entry)) = (options.search_archives, &entry) {
+                search_archive(
+                    entry,
+                    query.root,
+                    options,
+                    &mut recorder,
+                    status,
+                    stats,
+                    &sender,
+                );
             }
+            process_entry(entry, query, options, &mut recorder, status, stats, &sender)
+        })
+    })
+}
 
-            let entry = match entry {
-                Ok(entry) => entry,
-                Err(_) => {
-                    return WalkState::Continue;
-                }
-            };
+/// How many listed paths a worker of [`spawn_path_senders`] takes at once
+const PATH_BATCH_SIZE: usize = 64;
+
+/// Evaluates a list of paths instead of walking directories, spreading them over
+/// `threads` workers as they are read. Every worker takes up to [`PATH_BATCH_SIZE`]
+/// queued paths at once and reads their entries with a single walker. Returns the
+/// error that stopped reading `paths`, once the paths read before it are evaluated.
+pub fn spawn_path_senders(
+    status: &Arc<Mutex<ProcessStatus>>,
+    root_node: &Arc<ExpressionNode<Filter>>,
+    stats: &Arc<WalkStats>,
+    sender: kanal::Sender<EntryMessage>,
+    paths: impl IntoIterator<Item = Result<PathBuf, GenericError>>,
+    threads: usize,
+    options: SenderOptions,
+) -> Result<(), GenericError> {
+    let threads = threads.max(1);
+    // enough to keep every worker busy without holding the whole list
+    let (path_sender, path_receiver) = kanal::bounded(PATH_BATCH_SIZE * threads * 2);
+
+    let query = Query::new(root_node, None);
+    let options = &options;
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let path_receiver = path_receiver.clone();
+            let sender = sender.clone();
+            let mut recorder = Recorder::new(options.profile.as_ref());
 
-            let eval_result = root.evaluate(&entry);
+            scope.spawn(move || {
+                let _ = sender.send(EntryMessage::Init);
 
-            let message = match eval_result {
-                Ok(matched) if matched => EntryMessage::Success(entry),
-                Err(error) => match &error {
-                    GenericError::IoError(io_error)
-                        if io_error.kind() == std::io::ErrorKind::TimedOut =>
-                    {
-                        EntryMessage::Error(entry, error)
+                // wait for a path, then take whatever else is queued
+                while let Ok(first) = path_receiver.recv() {
+                    let mut walker = WalkBuilder::new(first);
+                    for _ in 1..PATH_BATCH_SIZE {
+                        match path_receiver.try_recv() {
+                            Ok(Some(path)) => walker.add(path),
+                            _ => break,
+                        };
                     }
-                    _ => return WalkState::Continue,
-                },
-                _ => return WalkState::Continue,
-            };
 
-            if sender.send(message).is_err() {
-                *status.lock().unwrap() = ProcessStatus::SendError;
-                return WalkState::Quit;
+                    let entries =
+                        walker.standard_filters(false).max_depth(Some(0)).build();
+                    for entry in entries {
+                        if let (true, Ok(entry)) = (options.search_archives, &entry) {
+                            search_archive(
+                                entry,
+                                root_node,
+                                options,
+                                &mut recorder,
+                                status,
+                                stats,
+                                &sender,
+                            );
+                        }
+                        if process_entry(
+                            entry,
+                            query,
+                            options,
+                            &mut recorder,
+                            status,
+                            stats,
+                            &sender,
+                        ) == WalkState::Quit
+                        {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+        // the workers that quit drop theirs, so sending fails once all of them did
+        drop(path_receiver);
+
+        let mut result = Ok(());
+        for path in paths {
+            if !status.lock().unwrap().eq(&ProcessStatus::InProgress) {
+                break;
             }
+            match path.map(|path| path_sender.send(path)) {
+                Ok(Ok(())) => {}
+                Ok(Err(_)) => break,
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+        // the workers stop once they have emptied the queue
+        drop(path_sender);
+        result
+    })
+}
 
-            WalkState::Continue
-        })
+/// Reads paths separated by `separator`, a newline or NUL, skipping empty ones. The
+/// paths are read one at a time, as the iterator is advanced.
+pub fn read_paths(
+    reader: impl BufRead,
+    separator: u8,
+) -> impl Iterator<Item = Result<PathBuf, GenericError>> {
+    reader.split(separator).filter_map(|line| match line {
+        Ok(line) if line.is_empty() => None,
+        Ok(line) => Some(Ok(PathBuf::from(OsStr::from_bytes(&line)))),
+        Err(error) => Some(Err(error.into())),
     })
 }
 
+/// Sends the members of the archive `entry` that match `root`, as they are read.
+/// Entries that are not archives have no members. An archive that cannot be read is
+/// sent as an error after the members read before it.
+fn search_archive(
+    entry: &DirEntry,
+    root: &ExpressionNode<Filter>,
+    options: &SenderOptions,
+    recorder: &mut Recorder,
+    status: &Mutex<ProcessStatus>,
+    stats: &WalkStats,
+    sender: &kanal::Sender<EntryMessage>,
+) {
+    let walked = WalkedEntry::new(entry, options.follow_metadata);
+    if walked.get_entry_type() != EntryType::File
+        || !status.lock().unwrap().eq(&ProcessStatus::InProgress)
+    {
+        return;
+    }
+
+    let result =
+        for_each_member(entry.path(), entry.depth(), options.read_timeout, |member| {
+            if !status.lock().unwrap().eq(&ProcessStatus::InProgress) {
+                return ControlFlow::Break(());
+            }
+            stats.visited.fetch_add(1, Ordering::Relaxed);
+
+            if matches!(recorder.evaluate(root, &member), Ok(true))
+                && sender.send(EntryMessage::ArchiveMember(member)).is_err()
+            {
+                *status.lock().unwrap() = ProcessStatus::SendError;
+                return ControlFlow::Break(());
+            }
+            ControlFlow::Continue(())
+        });
+
+    if let Err(error) = result {
+        stats.errors.fetch_add(1, Ordering::Relaxed);
+        if sender.send(EntryMessage::Error(entry.clone(), error)).is_err() {
+            *status.lock().unwrap() = ProcessStatus::SendError;
+        }
+    }
+}
+
+fn process_entry(
+    entry: Result<DirEntry, ignore::Error>,
+    query: Query,
+    options: &SenderOptions,
+    recorder: &mut Recorder,
+    status: &Mutex<ProcessStatus>,
+    stats: &WalkStats,
+    sender: &kanal::Sender<EntryMessage>,
+) -> WalkState {
+    if !status.lock().unwrap().eq(&ProcessStatus::InProgress) {
+        return WalkState::Quit;
+    }
+
+    let entry = match entry {
+        Ok(entry) => entry,
+        Err(_) => {
+            stats.errors.fetch_add(1, Ordering::Relaxed);
+            return WalkState::Continue;
+        }
+    };
+    stats.visited.fetch_add(1, Ordering::Relaxed);
+    let walked = WalkedEntry::new(&entry, options.follow_metadata);
+
+    let next_state = match query.prune {
+        Some(prune)
+            if walked.get_entry_type() == EntryType::Dir
+                && matches!(recorder.evaluate(prune, &walked), Ok(true)) =>
+        {
+            WalkState::Skip
+        }
+        _ => WalkState::Continue,
+    };
+
+    if query.requires_file
+        && !matches!(walked.get_entry_type(), EntryType::File | EntryType::Symlink)
+    {
+        return next_state;
+    }
+
+    let root = query.root;
+    let eval_result = match options.show_match_lines {
+        true => recorder.evaluate_match_lines(root, &walked),
+        false => {
+            recorder.evaluate(root, &walked).map(|matched| matched.then(Default::default))
+        }
+    };
+    if matches!(eval_result, Err(GenericError::IoError(_))) {
+        stats.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let message = match eval_result {
+        Ok(Some(lines)) if options.show_match_lines => {
+            EntryMessage::MatchLines(entry, lines.into_iter().collect())
+        }
+        Ok(Some(_)) => EntryMessage::Success(entry),
+        // the entry could not be evaluated, which is not the same as not matching;
+        // the receiver decides whether to report it
+        Err(error @ GenericError::IoError(_)) => EntryMessage::Error(entry, error),
+        _ => return next_state,
+    };
+
+    if sender.send(message).is_err() {
+        *status.lock().unwrap() = ProcessStatus::SendError;
+        return WalkState::Quit;
+    }
+
+    next_state
+}
+
 trait LineWriterExt {
     fn write_line(&mut self, buf: impl AsRef<[u8]>) -> Result<(), std::io::Error>;
     fn write_line_sep(
@@ -102,27 +652,188 @@ impl<T: Write> LineWriterExt for LineWriter<T> {
     }
 }
 
-pub struct EntryReceiver {
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// Returns the cursor to the start of the line and erases the line
+const CLEAR_LINE: &str = "\r\x1b[K";
+
+/// How often the `--progress` line is redrawn
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The state of the `--progress` line on stderr
+#[derive(Debug, Default)]
+struct Progress {
+    rendered_at: Option<Instant>,
+    shown: bool,
+}
+
+/// Picks the ANSI color of a matched path like `ls` does, regular files stay plain
+fn entry_color<E: DirEntryWrapperExt>(entry: &E) -> Option<&'static str> {
+    match entry.get_entry_type() {
+        EntryType::Dir => Some("\x1b[1;34m"),
+        EntryType::Symlink => Some("\x1b[1;36m"),
+        EntryType::File => entry
+            .get_permissions()
+            .is_ok_and(|permissions| permissions.mode() & 0o111 != 0)
+            .then_some("\x1b[1;32m"),
+        EntryType::Socket | EntryType::FIFO => Some("\x1b[35m"),
+        EntryType::BlockDevice | EntryType::CharDevice => Some("\x1b[1;33m"),
+        EntryType::StdIn | EntryType::Unknown => None,
+    }
+}
+
+/// Writes the `ls -l`-like columns preceding the path: mode, owner, group, size and
+/// mtime. Owners and groups without a name are written as ids, unreadable fields as `?`
+fn write_long_columns<E: DirEntryWrapperExt>(
+    entry: &E,
+    users: &UsersCache,
+    buf: &mut Vec<u8>,
+) {
+    let mode = entry
+        .get_permissions()
+        .map(|permissions| unix_mode::to_string(permissions.mode()));
+    let user = entry.get_user_id().map(|uid| match users.get_user_by_uid(uid) {
+        Some(user) => user.name().to_string_lossy().into_owned(),
+        None => uid.to_string(),
+    });
+    let group = entry.get_group_id().map(|gid| match users.get_group_by_gid(gid) {
+        Some(group) => group.name().to_string_lossy().into_owned(),
+        None => gid.to_string(),
+    });
+    let mtime = entry
+        .get_mtime()
+        .map(|mtime| DateTime::<Local>::from(mtime).format("%Y-%m-%d %H:%M").to_string());
+
+    let field =
+        |value: Result<String, GenericError>| value.unwrap_or_else(|_| "?".to_string());
+    let columns = format!(
+        "{} {:<8} {:<8} {:>10} {} ",
+        field(mode),
+        field(user),
+        field(group),
+        field(entry.get_size().map(|size| size.to_string())),
+        field(mtime)
+    );
+    buf.extend_from_slice(columns.as_bytes());
+}
+
+/// Quotes `value` as a JSON string
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char if char.is_control() => {
+                quoted.push_str(&format!("\\u{:04x}", char as u32));
+            }
+            char => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Formats an error for `--errors json`; a path that is not valid UTF-8 is
+/// converted lossily
+fn error_json(path: &Path, error: &GenericError) -> String {
+    format!(
+        r#"{{"path":{},"kind":{},"message":{}}}"#,
+        json_string(&path.to_string_lossy()),
+        json_string(error.kind()),
+        json_string(&error.to_string())
+    )
+}
+
+fn is_timeout(error: &GenericError) -> bool {
+    matches!(error, GenericError::IoError(error) if error.kind() == std::io::ErrorKind::TimedOut)
+}
+
+pub struct EntryReceiver<O: Write = Stdout, E: Write = Stderr> {
     status: Arc<Mutex<ProcessStatus>>,
     receiver: kanal::Receiver<EntryMessage>,
-    stdout: LineWriter<Stdout>,
-    stderr: LineWriter<Stderr>,
+    stdout: LineWriter<O>,
+    stderr: LineWriter<E>,
     recv_timeout: Duration,
     separator: u8,
+    stats: Arc<WalkStats>,
+    print_stats: bool,
+    hardlinks: Option<HardlinkGroups>,
+    printf: Option<PrintfTemplate>,
+    /// Resolves owner and group names for the long format
+    long: Option<UsersCache>,
+    /// Whether the metadata of symlinks describes their targets
+    follow_metadata: bool,
+    color: bool,
+    quiet: bool,
+    min_results: Option<usize>,
+    /// Exit with 1 when anything matched and with 0 otherwise
+    invert_exit: bool,
+    show_errors: bool,
+    error_format: ErrorFormat,
+    absolute_paths: bool,
+    /// The canonical paths printed so far, if duplicates are skipped
+    printed: Option<HashSet<PathBuf>>,
+    dir_quota: Option<DirQuota>,
+    /// The matches held back to be printed by path with `--sort`
+    sorted: Option<Vec<EntryMessage>>,
+    /// Print the held back matches shallowest first, then by path
+    breadth_first: bool,
+    progress: Option<Progress>,
+    exec: Option<ExecCommand>,
+    /// The matches waiting for a batched `exec`
+    exec_batch: Option<ExecBatch>,
+    /// Print the commands of `exec` instead of running them
+    dry_run: bool,
+    /// Whether a command of `exec` could not run or exited with a failure
+    command_failed: bool,
+    /// Whether a read timed out, so the results may be incomplete
+    timed_out: bool,
+    buf: Vec<u8>,
 }
 
 impl EntryReceiver {
     pub fn new(
-        config: Config,
+        mut config: Config,
         stdout_capacity: usize,
         stderr_capacity: usize,
         receiver: kanal::Receiver<EntryMessage>,
         recv_timeout: Duration,
         status: &Arc<Mutex<ProcessStatus>>,
+        stats: &Arc<WalkStats>,
     ) -> Self {
+        // the progress line would only garble redirected stderr
+        config.progress &= std::io::stderr().is_terminal();
+
         let stdout = LineWriter::with_capacity(stdout_capacity, std::io::stdout());
         let stderr = LineWriter::with_capacity(stderr_capacity, std::io::stderr());
 
+        EntryReceiver::with_writers(
+            config,
+            stdout,
+            stderr,
+            receiver,
+            recv_timeout,
+            status,
+            stats,
+        )
+    }
+}
+
+impl<O: Write + Send + 'static, E: Write + Send + 'static> EntryReceiver<O, E> {
+    pub fn with_writers(
+        config: Config,
+        stdout: LineWriter<O>,
+        stderr: LineWriter<E>,
+        receiver: kanal::Receiver<EntryMessage>,
+        recv_timeout: Duration,
+        status: &Arc<Mutex<ProcessStatus>>,
+        stats: &Arc<WalkStats>,
+    ) -> Self {
         let separator = if config.print0 { b'\0' } else { b'\n' };
 
         Self {
@@ -132,29 +843,74 @@ impl EntryReceiver {
             receiver,
             recv_timeout,
             status: Arc::clone(status),
+            stats: Arc::clone(stats),
+            print_stats: config.print_stats,
+            hardlinks: config.find_hardlinks.then(HardlinkGroups::default),
+            printf: config.printf,
+            long: config.long.then(UsersCache::new),
+            follow_metadata: config.follow_metadata,
+            color: config.color,
+            quiet: config.quiet,
+            min_results: config.min_results,
+            invert_exit: config.invert_exit,
+            show_errors: config.show_errors,
+            error_format: config.error_format,
+            absolute_paths: config.absolute_paths,
+            printed: config.unique.then(HashSet::new),
+            dir_quota: config
+                .max_results_per_dir
+                .map(|max| DirQuota::new(config.start_dirs.clone(), max)),
+            // a quiet search stops at the first match, whatever its depth
+            sorted: ((config.sort || config.breadth_first) && !config.quiet)
+                .then(Vec::new),
+            breadth_first: config.breadth_first,
+            progress: config.progress.then(Progress::default),
+            exec_batch: config
+                .exec
+                .as_ref()
+                .filter(|exec| exec.is_batched())
+                .map(|exec| ExecBatch::new(exec.batch_limit())),
+            exec: config.exec,
+            dry_run: config.dry_run,
+            command_failed: false,
+            timed_out: false,
+            buf: vec![],
         }
     }
 
     fn receive(&mut self) -> Result<(), kanal::ReceiveErrorTimeout> {
         match self.receiver.recv_timeout(self.recv_timeout) {
-            Ok(EntryMessage::Success(entry)) => {
-                // write the name without converting it to utf8
-                let write_result = self
-                    .stdout
-                    .write_line_sep(entry.path().as_os_str().as_bytes(), self.separator);
-
-                if write_result.is_err() {
-                    let _ = self.stderr.write_line("Failed to write to stdout");
-                    *self.status.lock().unwrap() = ProcessStatus::SendError;
-                }
-            }
+            Ok(
+                message @ (EntryMessage::Success(_)
+                | EntryMessage::MatchLines(..)
+                | EntryMessage::ArchiveMember(_)),
+            ) => match &mut self.sorted {
+                Some(buffer) => buffer.push(message),
+                None => self.write_match(message),
+            },
             Ok(EntryMessage::Init) => {
                 self.stdout.flush().unwrap();
             }
+            Ok(EntryMessage::Error(_, error)) if self.quiet => {
+                self.timed_out |= is_timeout(&error);
+            }
+            // timeouts are always reported, other errors only when asked for
+            Ok(EntryMessage::Error(_, error @ GenericError::IoError(_)))
+                if !self.show_errors && !is_timeout(&error) => {}
             Ok(EntryMessage::Error(entry, error)) => {
-                // write the name without converting it to utf8
-                let _ = self.stderr.write_line(entry.path().as_os_str().as_bytes());
-                let _ = self.stderr.write_line(format!("\t{:?}", error));
+                self.timed_out |= is_timeout(&error);
+                self.clear_progress();
+                match self.error_format {
+                    ErrorFormat::Text => {
+                        // write the name without converting it to utf8
+                        let path = entry.path().as_os_str().as_bytes();
+                        let _ = self.stderr.write_line(path);
+                        let _ = self.stderr.write_line(format!("\t{:?}", error));
+                    }
+                    ErrorFormat::Json => {
+                        let _ = self.stderr.write_line(error_json(entry.path(), &error));
+                    }
+                }
             }
             Err(kanal::ReceiveErrorTimeout::Timeout) => {
                 let _ = self.stdout.flush();
@@ -168,22 +924,425 @@ impl EntryReceiver {
         Ok(())
     }
 
+    /// Whether `path` was already received with `--unique`, and remembers it if not.
+    /// Paths are compared canonicalized, or as they are if that fails, e.g. for
+    /// archive members
+    fn is_printed(&mut self, path: &Path) -> bool {
+        let Some(printed) = &mut self.printed else {
+            return false;
+        };
+
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        !printed.insert(path)
+    }
+
+    fn is_within_quota(&mut self, path: &Path) -> bool {
+        self.dir_quota.as_mut().is_none_or(|quota| quota.admit(path))
+    }
+
+    /// Prints a `Success`, `MatchLines` or `ArchiveMember` message, unless it is
+    /// filtered out by `--unique` or `--max-results-per-dir`
+    fn write_match(&mut self, message: EntryMessage) {
+        match message {
+            // matched by something other than contents, e.g. `name = *.rs or ...`
+            EntryMessage::MatchLines(entry, lines) if lines.is_empty() => {
+                self.write_match(EntryMessage::Success(entry));
+            }
+            EntryMessage::MatchLines(entry, lines) => {
+                if self.is_printed(entry.path()) || !self.is_within_quota(entry.path()) {
+                    return;
+                }
+                self.stats.matched.fetch_add(1, Ordering::Relaxed);
+
+                if self.quiet {
+                    *self.status.lock().unwrap() = ProcessStatus::Matched;
+                    return;
+                }
+
+                let path = if self.absolute_paths {
+                    self.absolute_path(entry.path())
+                } else {
+                    Cow::Borrowed(entry.path())
+                };
+
+                self.clear_progress();
+                for (number, line) in lines {
+                    // like grep -n: path:lineno:line
+                    self.buf.clear();
+                    self.buf.extend_from_slice(path.as_os_str().as_bytes());
+                    self.buf.extend_from_slice(format!(":{number}:").as_bytes());
+                    self.buf.extend_from_slice(line.as_bytes());
+                    if self.stdout.write_line_sep(&self.buf, self.separator).is_err() {
+                        let _ = self.stderr.write_line("Failed to write to stdout");
+                        *self.status.lock().unwrap() = ProcessStatus::SendError;
+                        return;
+                    }
+                }
+            }
+            EntryMessage::Success(entry) => {
+                if self.is_printed(entry.path()) || !self.is_within_quota(entry.path()) {
+                    return;
+                }
+                self.stats.matched.fetch_add(1, Ordering::Relaxed);
+
+                if self.quiet {
+                    *self.status.lock().unwrap() = ProcessStatus::Matched;
+                    return;
+                }
+
+                let path = if self.absolute_paths {
+                    self.absolute_path(entry.path())
+                } else {
+                    Cow::Borrowed(entry.path())
+                };
+
+                if let Some(hardlinks) = &mut self.hardlinks {
+                    // a symlink is not a link of its target, whatever the metadata follows
+                    if let Ok(metadata) = entry.path().symlink_metadata() {
+                        hardlinks.add(
+                            metadata.dev(),
+                            metadata.ino(),
+                            metadata.nlink(),
+                            path.into_owned(),
+                        );
+                    }
+                    return;
+                }
+
+                if self.exec.is_some() {
+                    self.execute(&path);
+                    return;
+                }
+
+                self.clear_progress();
+                let walked = WalkedEntry::new(&entry, self.follow_metadata);
+                self.write_entry(&walked, &path);
+            }
+            EntryMessage::ArchiveMember(member) => {
+                // members have no inode, and cannot be opened by a command
+                if self.hardlinks.is_some() || self.exec.is_some() {
+                    return;
+                }
+                let path = member.display_path();
+                if self.is_printed(path) || !self.is_within_quota(path) {
+                    return;
+                }
+                self.stats.matched.fetch_add(1, Ordering::Relaxed);
+
+                if self.quiet {
+                    *self.status.lock().unwrap() = ProcessStatus::Matched;
+                    return;
+                }
+
+                let path = if self.absolute_paths {
+                    self.absolute_path(path)
+                } else {
+                    Cow::Borrowed(path)
+                };
+
+                self.clear_progress();
+                self.write_entry(&member, &path);
+            }
+            _ => {}
+        }
+    }
+
+    /// Prints a match as `path`, formatted by `--printf`, `--long` and `--color`.
+    /// The fields an entry does not know, like the owner of an archive member, are
+    /// written as `?`
+    fn write_entry<D: DirEntryWrapperExt>(&mut self, entry: &D, path: &Path) {
+        let write_result = if let Some(printf) = &self.printf {
+            self.buf.clear();
+            printf.format(entry, path, &mut self.buf);
+            self.stdout.write_line_sep(&self.buf, self.separator)
+        } else if self.long.is_some() || self.color {
+            self.buf.clear();
+            if let Some(users) = &self.long {
+                write_long_columns(entry, users, &mut self.buf);
+            }
+
+            // write the name without converting it to utf8
+            let path = path.as_os_str().as_bytes();
+            match self.color.then(|| entry_color(entry)).flatten() {
+                Some(color) => {
+                    self.buf.extend_from_slice(color.as_bytes());
+                    self.buf.extend_from_slice(path);
+                    self.buf.extend_from_slice(COLOR_RESET.as_bytes());
+                }
+                None => self.buf.extend_from_slice(path),
+            }
+            self.stdout.write_line_sep(&self.buf, self.separator)
+        } else {
+            // write the name without converting it to utf8
+            self.stdout.write_line_sep(path.as_os_str().as_bytes(), self.separator)
+        };
+
+        if write_result.is_err() {
+            let _ = self.stderr.write_line("Failed to write to stdout");
+            *self.status.lock().unwrap() = ProcessStatus::SendError;
+        }
+    }
+
+    /// Runs the `--exec` command for `path`. A batched command only runs once the
+    /// batch is full
+    fn execute(&mut self, path: &Path) {
+        let Some(exec) = &self.exec else {
+            return;
+        };
+
+        let argv = match &mut self.exec_batch {
+            Some(batch) => match batch.push(path.to_path_buf()) {
+                Some(paths) => exec.batch_argv(&paths),
+                None => return,
+            },
+            None => exec.argv(path),
+        };
+        self.run_command(argv);
+    }
+
+    /// Runs the batched command for the matches not passed to it yet
+    fn execute_rest(&mut self) {
+        let (Some(exec), Some(batch)) = (&self.exec, &mut self.exec_batch) else {
+            return;
+        };
+
+        let paths = batch.take();
+        if !paths.is_empty() {
+            let argv = exec.batch_argv(&paths);
+            self.run_command(argv);
+        }
+    }
+
+    /// Runs a command, or prints it with `--dry-run`. The command inherits stdout
+    /// and stderr, so the results are flushed first
+    fn run_command(&mut self, argv: Vec<OsString>) {
+        self.clear_progress();
+        if self.dry_run {
+            if self.stdout.write_line(format_command(&argv)).is_err() {
+                let _ = self.stderr.write_line("Failed to write to stdout");
+                *self.status.lock().unwrap() = ProcessStatus::SendError;
+            }
+            return;
+        }
+
+        let _ = self.stdout.flush();
+        let _ = self.stderr.flush();
+        match Command::new(&argv[0]).args(&argv[1..]).status() {
+            Ok(status) => self.command_failed |= !status.success(),
+            Err(error) => {
+                self.command_failed = true;
+                let _ = self.stderr.write_line(format!(
+                    "Failed to run {}: {error}",
+                    argv[0].to_string_lossy()
+                ));
+            }
+        }
+    }
+
+    /// Prints the matches collected with `--sort` by path, or shallowest first with
+    /// `--breadth-first`
+    fn write_sorted(&mut self) {
+        let Some(mut buffer) = self.sorted.take() else {
+            return;
+        };
+
+        let breadth_first = self.breadth_first;
+        buffer.sort_by(|left, right| {
+            match_order_key(left, breadth_first)
+                .cmp(&match_order_key(right, breadth_first))
+        });
+        for message in buffer {
+            self.write_match(message);
+        }
+    }
+
+    /// Redraws the progress line if it is enabled and was not drawn recently
+    fn render_progress(&mut self) {
+        let Some(progress) = &mut self.progress else {
+            return;
+        };
+        if progress.rendered_at.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+
+        // results are line buffered, so they must be out before the line is drawn
+        let _ = self.stdout.flush();
+        let line = format!(
+            "{CLEAR_LINE}Visited: {}, matched: {}",
+            self.stats.visited.load(Ordering::Relaxed),
+            self.stats.matched.load(Ordering::Relaxed)
+        );
+        let _ = self.stderr.write_all(line.as_bytes());
+        let _ = self.stderr.flush();
+
+        progress.rendered_at = Some(Instant::now());
+        progress.shown = true;
+    }
+
+    /// Erases the progress line, so that results and errors start on a clean line
+    fn clear_progress(&mut self) {
+        let Some(progress) = &mut self.progress else {
+            return;
+        };
+        if !std::mem::take(&mut progress.shown) {
+            return;
+        }
+
+        let _ = self.stderr.write_all(CLEAR_LINE.as_bytes());
+        let _ = self.stderr.flush();
+    }
+
+    /// Makes the path absolute without resolving symlinks, so a matched link is
+    /// printed as the link. Falls back to the path as it is if that fails.
+    fn absolute_path<'a>(&mut self, path: &'a Path) -> Cow<'a, Path> {
+        match std::path::absolute(path) {
+            Ok(absolute) => Cow::Owned(absolute),
+            Err(error) => {
+                let _ = self.stderr.write_line(path.as_os_str().as_bytes());
+                let _ =
+                    self.stderr.write_line(format!("\tcannot make absolute: {error}"));
+                Cow::Borrowed(path)
+            }
+        }
+    }
+
+    /// Prints the `--stats` summary to stderr, after everything on stdout
+    fn write_stats(&mut self) {
+        if self.print_stats {
+            let _ = self.stdout.flush();
+            let _ = self.stderr.write_line(self.stats.summary());
+            let _ = self.stderr.flush();
+        }
+    }
+
+    /// Prints each group of hard links followed by an empty line
+    fn write_hardlinks(&mut self) {
+        let Some(hardlinks) = self.hardlinks.take() else {
+            return;
+        };
+
+        for group in hardlinks.into_groups() {
+            for path in group {
+                let _ = self
+                    .stdout
+                    .write_line_sep(path.as_os_str().as_bytes(), self.separator);
+            }
+            let _ = self.stdout.write_all(&[self.separator]);
+        }
+        let _ = self.stdout.flush();
+    }
+
+    /// Receives until the walk is over and returns the exit code: 0 when the search
+    /// completed, 1 when it was interrupted, e.g. by Ctrl-C or `--timeout`, and 2 when
+    /// it completed but some files could not be read in time, so matches may be
+    /// missing. In quiet mode, it is 0 when anything matched, otherwise 2 after read
+    /// timeouts and 1 if not. A search that was not interrupted but matched fewer
+    /// entries than `min_results` exits with 3. `invert_exit` swaps 0 and 1 for a
+    /// completed search: it is 1 when anything matched.
     pub fn receive_all(mut self) -> JoinHandle<i32> {
         std::thread::spawn(move || {
             loop {
                 if !self.status.lock().unwrap().eq(&ProcessStatus::InProgress) {
-                    break 1;
+                    break;
                 }
 
+                self.render_progress();
+
                 // TODO: check for other errors
                 if self.receive().is_err() {
-                    break 0;
+                    break;
                 }
             }
+            self.clear_progress();
+
+            // cancelled senders close the channel too, so only the status tells whether
+            // the search was interrupted
+            let code = match *self.status.lock().unwrap() {
+                ProcessStatus::InProgress | ProcessStatus::Matched => 0,
+                ProcessStatus::Cancelled | ProcessStatus::SendError => 1,
+            };
+
+            if self.quiet {
+                self.write_stats();
+                return match self.stats.matched.load(Ordering::Relaxed) {
+                    // an interrupted search cannot tell that nothing matched
+                    _ if code != 0 => code,
+                    0 if self.timed_out => 2,
+                    0 if self.invert_exit => 0,
+                    0 => 1,
+                    _ if self.invert_exit => 1,
+                    _ => 0,
+                };
+            }
+
+            // print whatever was found before a cancellation, but do not run commands
+            // for it
+            if code == 0 || self.exec.is_none() {
+                self.write_sorted();
+            }
+            if code == 0 {
+                self.execute_rest();
+            }
+            let _ = self.stdout.flush();
+            let _ = self.stderr.flush();
+
+            self.write_hardlinks();
+
+            self.write_stats();
+
+            let matched = self.stats.matched.load(Ordering::Relaxed);
+            if let Some(min) = self.min_results.filter(|&min| code == 0 && matched < min)
+            {
+                let message = format!("Expected at least {min} matches, found {matched}");
+                let _ = self.stderr.write_line(message);
+                let _ = self.stderr.flush();
+                return 3;
+            }
+            if self.invert_exit && code == 0 && matched > 0 {
+                return 1;
+            }
+            if code == 0 && self.command_failed {
+                return 1;
+            }
+
+            if code == 0 && self.timed_out {
+                2
+            } else {
+                code
+            }
         })
     }
 }
 
+/// Orders the buffered matches by path, after their depth if `breadth_first`
+fn match_order_key(message: &EntryMessage, breadth_first: bool) -> (usize, &Path) {
+    let (depth, path) = match message {
+        EntryMessage::Success(entry) | EntryMessage::MatchLines(entry, _) => {
+            (entry.depth(), entry.path())
+        }
+        EntryMessage::ArchiveMember(member) => {
+            (member.get_depth(), member.display_path())
+        }
+        // only matches are buffered
+        EntryMessage::Error(..) | EntryMessage::Init => (0, Path::new("")),
+    };
+
+    (if breadth_first { depth } else { 0 }, path)
+}
+
+/// Cancels the search once `timeout` has passed, unless it is already over
+pub fn spawn_watchdog(status: &Arc<Mutex<ProcessStatus>>, timeout: Duration) {
+    let status = Arc::clone(status);
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+
+        let mut status = status.lock().unwrap();
+        if *status == ProcessStatus::InProgress {
+            *status = ProcessStatus::Cancelled;
+        }
+    });
+}
+
 pub fn set_int_handler(status: &Arc<Mutex<ProcessStatus>>) {
     let status = Arc::clone(status);
     ctrlc::set_handler(move || {
@@ -195,3 +1354,1113 @@ pub fn set_int_handler(status: &Arc<Mutex<ProcessStatus>>) {
     })
     .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ffi::OsStr;
+    use std::fs::{File, Permissions};
+    use std::io::{BufReader, LineWriter, Write};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use chrono::{DateTime, Local};
+    use clap::Parser;
+    use ignore::WalkBuilder;
+    use itertools::Itertools;
+
+    use crate::config::{Args, Config};
+    use crate::errors::GenericError;
+    use crate::evaluate::profile::{Profile, Recorder};
+    use crate::parse::parse_root;
+    use crate::run::{
+        format_command, process_entry, read_paths, spawn_path_senders, spawn_senders,
+        spawn_watchdog, DirQuota, EntryMessage, EntryReceiver, ExecBatch, ExecCommand,
+        ProcessStatus, Query, SenderOptions, WalkStats,
+    };
+
+    /// Only keeps what was flushed, so the bytes a dropped `LineWriter` writes out
+    /// on its own do not count
+    #[derive(Clone, Default)]
+    struct FlushedBuffer {
+        pending: Vec<u8>,
+        flushed: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Write for FlushedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushed.lock().unwrap().append(&mut self.pending);
+            Ok(())
+        }
+    }
+
+    fn config_from(args: &[&str]) -> Config {
+        let args = Args::try_parse_from(["fgr"].iter().chain(args)).unwrap();
+        Config::from_args(args).unwrap()
+    }
+
+    /// Runs the receiver of `config` while `send` gives it messages, until the sender
+    /// is dropped, and returns its exit code with what it flushed to stdout and stderr.
+    /// The receive timeout is long, so only the flushes of the receiver itself count.
+    fn receive(
+        config: Config,
+        send: impl FnOnce(
+            &Arc<Mutex<ProcessStatus>>,
+            &Arc<WalkStats>,
+            kanal::Sender<EntryMessage>,
+        ),
+    ) -> (i32, String, String) {
+        let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+        let stats = Arc::new(WalkStats::default());
+        let (sender, receiver) = kanal::unbounded();
+        let stdout = FlushedBuffer::default();
+        let stderr = FlushedBuffer::default();
+
+        let handle = EntryReceiver::with_writers(
+            config,
+            LineWriter::new(stdout.clone()),
+            LineWriter::new(stderr.clone()),
+            receiver,
+            Duration::from_secs(10),
+            &status,
+            &stats,
+        )
+        .receive_all();
+        send(&status, &stats, sender);
+        let code = handle.join().unwrap();
+
+        let output = |buffer: FlushedBuffer| {
+            String::from_utf8(buffer.flushed.lock().unwrap().clone()).unwrap()
+        };
+        (code, output(stdout), output(stderr))
+    }
+
+    /// Searches like `fgr ARGS` does, reading `--files-from` or walking the start
+    /// directories, and returns the exit code, stdout and stderr
+    fn search(args: &[&str]) -> (i32, String, String) {
+        let config = config_from(args);
+        let root = Arc::new(config.root.clone());
+        let prune = config.prune.clone().map(Arc::new);
+        let options = SenderOptions::from(&config);
+        let files_from = config.files_from.clone().map(|path| {
+            let reader = BufReader::new(File::open(path).unwrap());
+            read_paths(reader, config.input_separator)
+        });
+        let walks = config.walk_builders();
+        let threads = config.threads;
+
+        receive(config, |status, stats, sender| match files_from {
+            Some(paths) => {
+                spawn_path_senders(status, &root, stats, sender, paths, threads, options)
+                    .unwrap();
+            }
+            None => {
+                for walk in walks {
+                    spawn_senders(
+                        status,
+                        &root,
+                        prune.as_ref(),
+                        stats,
+                        sender.clone(),
+                        walk.build_parallel(),
+                        options.clone(),
+                    );
+                }
+            }
+        })
+    }
+
+    /// Returns the exit code, stdout and stderr of the receiver of
+    /// `fgr -e name=* ARGS` for `messages`
+    fn receive_messages(
+        args: &[&str],
+        messages: Vec<EntryMessage>,
+    ) -> (i32, String, String) {
+        let args = ["-e", "name=*"].iter().chain(args).copied().collect_vec();
+        receive(config_from(&args), |_, _, sender| {
+            for message in messages {
+                sender.send(message).unwrap();
+            }
+        })
+    }
+
+    /// The entries of each of `roots` in turn, as matches, walked in name order
+    fn walked(roots: &[&Path]) -> Vec<EntryMessage> {
+        roots
+            .iter()
+            .flat_map(|root| WalkBuilder::new(root).sort_by_file_name(Ord::cmp).build())
+            .map(|entry| EntryMessage::Success(entry.unwrap()))
+            .collect()
+    }
+
+    /// Returns what the receiver writes to stdout for the entries of `dir`
+    fn receive_output(dir: &Path, args: &[&str]) -> String {
+        receive_messages(args, walked(&[dir])).1
+    }
+
+    #[test]
+    fn test_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        File::create(dir.path().join("file.txt")).unwrap();
+
+        let (_, stdout, stderr) =
+            receive_messages(&["--progress"], walked(&[dir.path()]));
+        assert_eq!(stdout, receive_output(dir.path(), &[]));
+        assert!(stderr.starts_with("\r\x1b[KVisited: 0, matched: 0"), "{stderr:?}");
+        // the line is erased before the results are printed and never left behind
+        assert!(stderr.ends_with("\r\x1b[K"), "{stderr:?}");
+
+        let (_, _, stderr) = receive_messages(&[], walked(&[dir.path()]));
+        assert!(stderr.is_empty());
+    }
+
+    #[test]
+    fn test_color() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("script.sh");
+        let text = dir.path().join("text.txt");
+        File::create(&script).unwrap();
+        File::create(&text).unwrap();
+        std::fs::set_permissions(&script, Permissions::from_mode(0o755)).unwrap();
+
+        let root = dir.path().display();
+        assert_eq!(
+            receive_output(dir.path(), &["--color", "always"]),
+            format!(
+                "\x1b[1;34m{root}\x1b[0m\n\x1b[1;32m{}\x1b[0m\n{}\n",
+                script.display(),
+                text.display()
+            )
+        );
+
+        let plain = format!("{root}\n{}\n{}\n", script.display(), text.display());
+        assert_eq!(receive_output(dir.path(), &["--color", "never"]), plain);
+        assert_eq!(
+            receive_output(dir.path(), &["--color", "always", "-p"]),
+            plain.replace('\n', "\0")
+        );
+    }
+
+    #[test]
+    fn test_long() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample.txt");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"hello").unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        file.set_modified(mtime).unwrap();
+        std::fs::set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+
+        let output = receive_output(&path, &["-l"]);
+        let columns = output.split_whitespace().collect_vec();
+        let mtime = DateTime::<Local>::from(mtime);
+        assert_eq!(
+            columns,
+            [
+                "-rw-r-----",
+                uzers::get_current_username().unwrap().to_str().unwrap(),
+                uzers::get_current_groupname().unwrap().to_str().unwrap(),
+                "5",
+                &mtime.format("%Y-%m-%d").to_string(),
+                &mtime.format("%H:%M").to_string(),
+                path.to_str().unwrap(),
+            ]
+        );
+        assert_eq!(receive_output(&path, &[]), format!("{}\n", path.display()));
+    }
+
+    #[test]
+    fn test_absolute_paths() {
+        let cwd = std::env::current_dir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        // reach the temporary directory from the working directory without changing it
+        let relative: PathBuf = cwd
+            .components()
+            .skip(1)
+            .map(|_| Path::new(".."))
+            .chain(dir.path().strip_prefix("/"))
+            .collect();
+        let relative = relative.as_path();
+        File::create(dir.path().join("file.txt")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.path().join("link")).unwrap();
+
+        let paths = |prefix: &Path| {
+            ["", "file.txt", "link"]
+                .iter()
+                .map(|name| format!("{}\n", prefix.join(relative).join(name).display()))
+                .join("")
+                .replace("/\n", "\n")
+        };
+        assert_eq!(receive_output(relative, &[]), paths(Path::new("")));
+        // the dangling link is printed as the link, not resolved
+        assert_eq!(receive_output(relative, &["--absolute-paths"]), paths(&cwd));
+    }
+
+    #[test]
+    fn test_exec_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        File::create(dir.path().join("it's.txt")).unwrap();
+
+        let root = dir.path().display();
+        assert_eq!(
+            receive_output(dir.path(), &["--exec", "mv", "{}", "{}.bak", ";", "--dry-run"]),
+            format!(
+                "mv {root} {root}.bak\nmv '{root}/it'\\''s.txt' '{root}/it'\\''s.txt.bak'\n"
+            )
+        );
+        // without `{}` the path is appended
+        assert_eq!(
+            receive_output(dir.path(), &["--dry-run", "--exec", "ls", "-l"])
+                .lines()
+                .next(),
+            Some(format!("ls -l {root}").as_str())
+        );
+
+        let exec = ExecCommand::new(vec!["echo".to_string(), "--file={}".to_string()]);
+        let path = Path::new(OsStr::from_bytes(b"/tmp/\xff"));
+        assert_eq!(
+            format_command(&exec.argv(path)),
+            "echo '--file=/tmp/\u{FFFD}'  # not valid UTF-8, shown lossily"
+        );
+        assert_eq!(exec.argv(path)[1].as_bytes(), b"--file=/tmp/\xff");
+    }
+
+    #[test]
+    fn test_exec_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        File::create(dir.path().join("a.txt")).unwrap();
+        File::create(dir.path().join("b.txt")).unwrap();
+
+        let root = dir.path().display();
+        assert_eq!(
+            receive_output(
+                dir.path(),
+                &["--dry-run", "--exec-batch", "ls", "{}", "-d", "+"]
+            ),
+            format!("ls {root} {root}/a.txt {root}/b.txt -d\n")
+        );
+
+        // every path takes its 13 bytes, a NUL and a pointer
+        let cost = 14 + std::mem::size_of::<usize>();
+        let mut batch = ExecBatch::new(cost * 100);
+        let mut batches = vec![];
+        for index in 0..1050 {
+            batches.extend(batch.push(PathBuf::from(format!("/tmp/file{index:04}"))));
+        }
+        batches.push(batch.take());
+
+        assert_eq!(batches.len(), 11);
+        assert!(batches[..10].iter().all(|paths| paths.len() == 100));
+        assert_eq!(batches[10].len(), 50);
+        assert!(batch.take().is_empty());
+
+        let exec = ExecCommand::batched(vec!["rm".to_string()]);
+        assert!(exec.batch_limit() > 0);
+        assert_eq!(exec.batch_argv(&batches[10][..1]), ["rm", "/tmp/file1000"]);
+    }
+
+    #[test]
+    fn test_exec() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        File::create(&file).unwrap();
+
+        let args = ["--exec", "touch", "{}.done", ";"];
+        assert_eq!(receive_output(dir.path(), &args), "");
+        assert!(file.with_extension("txt.done").exists());
+    }
+
+    #[test]
+    fn test_exec_exit_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        File::create(&file).unwrap();
+
+        let run = |args: &[&str], cancel: bool| {
+            let args = ["-e", "name=*"].iter().chain(args).copied().collect_vec();
+            let (code, _, _) = receive(config_from(&args), |status, _, sender| {
+                for message in walked(&[&file]) {
+                    sender.send(message).unwrap();
+                }
+                if cancel {
+                    while !sender.is_empty() {
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                    *status.lock().unwrap() = ProcessStatus::Cancelled;
+                }
+            });
+            code
+        };
+
+        assert_eq!(run(&["--exec", "true", ";"], false), 0);
+        assert_eq!(run(&["--exec", "false", ";"], false), 1);
+        assert_eq!(run(&["--exec-batch", "false", "+"], false), 1);
+        assert_eq!(run(&["--exec", "fgr-missing-command", ";"], false), 1);
+
+        // nothing runs for the matches held back when the search is cancelled
+        let batched = ["--exec-batch", "rm", "+"];
+        for args in [&batched[..], &["--breadth-first", "--exec", "rm", ";"]] {
+            assert_eq!(run(args, true), 1);
+            assert!(file.exists());
+        }
+        assert_eq!(run(&batched, false), 0);
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn test_unique() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        File::create(dir.path().join("top.txt")).unwrap();
+        File::create(nested.join("inner.txt")).unwrap();
+
+        let received = |unique: bool| {
+            let root = dir.path().to_str().unwrap();
+            let mut args = vec![root, nested.to_str().unwrap(), "-e", "name=*"];
+            if unique {
+                args.push("--unique");
+            }
+            let (_, output, _) = search(&args);
+            output.lines().map(|line| line.to_string()).sorted().collect_vec()
+        };
+
+        let root = dir.path().display().to_string();
+        let inner = nested.join("inner.txt").display().to_string();
+        let top = dir.path().join("top.txt").display().to_string();
+        let nested = nested.display().to_string();
+
+        // the nested start dir itself and its file are walked twice
+        assert_eq!(received(false), [&*root, &nested, &nested, &inner, &inner, &top]);
+        assert_eq!(received(true), [&*root, &nested, &inner, &top]);
+    }
+
+    #[test]
+    fn test_breadth_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let deep = dir.path().join("a/b");
+        std::fs::create_dir_all(&deep).unwrap();
+        File::create(deep.join("deep.txt")).unwrap();
+        File::create(dir.path().join("z.txt")).unwrap();
+
+        // the walk is sorted by name, so the deep file comes first
+        let output = receive_output(dir.path(), &[]);
+        let paths = output.lines().collect_vec();
+        let position = |name| paths.iter().position(|path| path.ends_with(name)).unwrap();
+        assert!(position("deep.txt") < position("z.txt"));
+
+        let output = receive_output(dir.path(), &["--breadth-first"]);
+        let relative = output
+            .lines()
+            .map(|path| Path::new(path).strip_prefix(dir.path()).unwrap())
+            .collect_vec();
+        assert_eq!(relative, ["", "a", "z.txt", "a/b", "a/b/deep.txt"].map(Path::new));
+    }
+
+    #[test]
+    fn test_cancelled_receiver_flushes() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..1000 {
+            File::create(dir.path().join(format!("{index}.txt"))).unwrap();
+        }
+
+        let config = config_from(&["-e", "name=*", "-p"]);
+        let (code, output, _) = receive(config, |status, _, sender| {
+            for message in walked(&[dir.path()]) {
+                sender.send(message).unwrap();
+            }
+            while !sender.is_empty() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            *status.lock().unwrap() = ProcessStatus::Cancelled;
+        });
+        assert_eq!(code, 1);
+        // the start directory is among the entries
+        assert_eq!(output.split('\0').filter(|path| !path.is_empty()).count(), 1001);
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let pruned = dir.path().join("node_modules");
+        std::fs::create_dir_all(pruned.join("nested")).unwrap();
+        File::create(pruned.join("nested").join("a.js")).unwrap();
+        File::create(dir.path().join("b.js")).unwrap();
+
+        let root = Arc::new(parse_root("depth > 0").unwrap());
+        let prune = Arc::new(parse_root("name = node_modules").unwrap());
+        let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+        let stats = Arc::new(WalkStats::default());
+        let (sender, receiver) = kanal::unbounded();
+
+        let walker = WalkBuilder::new(dir.path()).threads(2).build_parallel();
+        spawn_senders(
+            &status,
+            &root,
+            Some(&prune),
+            &stats,
+            sender,
+            walker,
+            SenderOptions::default(),
+        );
+
+        let mut matched: Vec<PathBuf> = receiver
+            .filter_map(|message| match message {
+                EntryMessage::Success(entry) => Some(entry.into_path()),
+                _ => None,
+            })
+            .collect();
+        matched.sort();
+
+        assert_eq!(matched, [dir.path().join("b.js"), pruned]);
+        assert_eq!(stats.visited.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_follow_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        std::os::unix::fs::symlink("target", dir.path().join("link")).unwrap();
+
+        let matched = |query: &str, follow_metadata| {
+            let root = Arc::new(parse_root(query).unwrap());
+            let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+            let stats = Arc::new(WalkStats::default());
+            let (sender, receiver) = kanal::unbounded();
+
+            let walker = WalkBuilder::new(dir.path()).build_parallel();
+            let options = SenderOptions { follow_metadata, ..SenderOptions::default() };
+            spawn_senders(&status, &root, None, &stats, sender, walker, options);
+
+            receiver
+                .filter_map(|message| match message {
+                    EntryMessage::Success(entry) => Some(entry.into_path()),
+                    _ => None,
+                })
+                .sorted()
+                .collect_vec()
+        };
+
+        let (link, target) = (dir.path().join("link"), dir.path().join("target"));
+        assert_eq!(matched("kind = symlink", false), std::slice::from_ref(&link));
+        assert_eq!(matched("kind = symlink", true), [] as [PathBuf; 0]);
+        assert_eq!(
+            matched("kind = dir and depth > 0", false),
+            std::slice::from_ref(&target)
+        );
+        assert_eq!(matched("kind = dir and depth > 0", true), [link, target]);
+    }
+
+    #[test]
+    fn test_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        File::create(dir.path().join("a.rs")).unwrap();
+        File::create(dir.path().join("b.txt")).unwrap();
+
+        let root = Arc::new(parse_root("name = *.rs or depth = 0").unwrap());
+        let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+        let stats = Arc::new(WalkStats::default());
+        let (sender, receiver) = kanal::unbounded();
+
+        let profile = Profile::default();
+        let options =
+            SenderOptions { profile: Some(profile.clone()), ..Default::default() };
+        let walker = WalkBuilder::new(dir.path()).threads(2).build_parallel();
+        spawn_senders(&status, &root, None, &stats, sender, walker, options);
+        drop(receiver);
+
+        // every walker thread is done, so all of them were merged
+        let timings = profile.timings().into_iter().collect::<HashMap<_, _>>();
+        assert_eq!(timings["Name"].evaluations, 3);
+        assert_eq!(timings["Depth"].evaluations, 2);
+        assert!(Profile::default().timings().is_empty());
+    }
+
+    #[test]
+    fn test_search_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("sources.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        for name in ["src/main.rs", "README.md"] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"fn main() {}").unwrap();
+        }
+        zip.finish().unwrap();
+        File::create(dir.path().join("lib.rs")).unwrap();
+
+        let matched = |search_archives| {
+            let root = Arc::new(parse_root("ext = rs").unwrap());
+            let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+            let stats = Arc::new(WalkStats::default());
+            let (sender, receiver) = kanal::unbounded();
+
+            let walker = WalkBuilder::new(dir.path()).build_parallel();
+            let options = SenderOptions { search_archives, ..SenderOptions::default() };
+            spawn_senders(&status, &root, None, &stats, sender, walker, options);
+
+            let matched = receiver
+                .filter_map(|message| match message {
+                    EntryMessage::Success(entry) => Some(entry.into_path()),
+                    EntryMessage::ArchiveMember(member) => {
+                        Some(member.into_display_path())
+                    }
+                    _ => None,
+                })
+                .sorted()
+                .collect_vec();
+            (matched, stats.visited.load(Ordering::Relaxed))
+        };
+
+        let lib = dir.path().join("lib.rs");
+        let member = PathBuf::from(format!("{}::src/main.rs", archive.display()));
+        // the members are visited along with the directory, the archive and lib.rs
+        assert_eq!(matched(true), (vec![lib.clone(), member], 5));
+        assert_eq!(matched(false), (vec![lib], 3));
+    }
+
+    #[test]
+    fn test_archive_member_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("docs.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.add_directory("guide", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.start_file("guide/intro.md", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"hello").unwrap();
+        zip.finish().unwrap();
+
+        let output = |args: &[&str]| {
+            let dir_arg = dir.path().to_str().unwrap();
+            let query = [dir_arg, "-e", "name = guide or ext = md"];
+            let (_, output, _) = search(&query.iter().chain(args).copied().collect_vec());
+            output.lines().sorted().join("\n")
+        };
+
+        let guide = format!("{}::guide", archive.display());
+        let intro = format!("{}::guide/intro.md", archive.display());
+        assert_eq!(output(&["--search-archives"]), format!("{guide}\n{intro}"));
+        assert_eq!(
+            output(&["--search-archives", "--printf", "%s %m %p"]),
+            format!("0 ? {guide}\n5 ? {intro}")
+        );
+        assert_eq!(
+            output(&["--search-archives", "--color", "always"]),
+            format!("\x1b[1;34m{guide}\x1b[0m\n{intro}")
+        );
+        // only the size of a member is known
+        assert_eq!(
+            output(&["--search-archives", "--long"]),
+            format!("? ? ? 0 ? {guide}\n? ? ? 5 ? {intro}")
+                .replace("? ? ? ", "? ?        ?        ")
+                .replace(" 0 ", "          0 ")
+                .replace(" 5 ", "          5 ")
+        );
+        assert_eq!(output(&[]), "");
+
+        // members would be counted as matches without a command or an inode to show
+        for args in [
+            &["--exec", "cat", "{}", ";"][..],
+            &["--exec-batch", "cat", "+"],
+            &["--find-hardlinks"],
+        ] {
+            let query = ["fgr", "-e", "ext = md", "--search-archives"];
+            assert!(Args::try_parse_from(query.iter().chain(args)).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn test_dir_quota() {
+        let mut quota = DirQuota::new(vec!["/data".into(), "/data/nested".into()], 2);
+        let admitted = [
+            "/data/big/1",
+            "/data/big/2",
+            "/data/big/deep/3",
+            "/data/big",
+            "/data/small/1",
+            "/data/top.txt",
+            "/data/nested/a/1",
+            "/data/nested/a/2",
+            "/data/nested/a/3",
+            "/elsewhere",
+            "/elsewhere",
+            "/elsewhere",
+        ]
+        .into_iter()
+        .filter(|path| quota.admit(Path::new(path)))
+        .collect_vec();
+
+        assert_eq!(
+            admitted,
+            [
+                "/data/big/1",
+                "/data/big/2",
+                "/data/small/1",
+                "/data/top.txt",
+                "/data/nested/a/1",
+                "/data/nested/a/2",
+                "/elsewhere",
+                "/elsewhere",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hardlink_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("original");
+        File::create(&original).unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        File::create(dir.path().join("sub/single")).unwrap();
+        for name in ["link1", "sub/link2"] {
+            std::fs::hard_link(&original, dir.path().join(name)).unwrap();
+        }
+        std::os::unix::fs::symlink(&original, dir.path().join("symlink")).unwrap();
+
+        // the overlapping roots match everything in sub twice
+        let sub = dir.path().join("sub");
+        let args = ["-e", "kind=f or kind=l", "--find-hardlinks"];
+        let (_, stdout, _) = receive_messages(&args, walked(&[dir.path(), &sub]));
+
+        let expected: String = ["link1", "original", "sub/link2"]
+            .iter()
+            .map(|name| format!("{}\n", dir.path().join(name).display()))
+            .chain(["\n".to_string()])
+            .collect();
+        assert_eq!(stdout, expected);
+    }
+
+    #[test]
+    fn test_path_senders() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = vec![];
+        for index in 0..1000 {
+            let path = dir.path().join(format!("{index}.{}", ["txt", "rs"][index % 2]));
+            File::create(&path).unwrap();
+            paths.push(path);
+        }
+        let listing = paths.iter().map(|path| path.to_str().unwrap()).join("\n\n");
+        let read = |listing: &str, separator| {
+            read_paths(listing.as_bytes(), separator).collect::<Result<Vec<_>, _>>()
+        };
+        assert_eq!(read(&listing, b'\n').unwrap(), paths);
+        assert_eq!(read(&listing.replace('\n', "\0"), b'\0').unwrap(), paths);
+
+        let root = Arc::new(parse_root("ext = txt").unwrap());
+        let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+        let stats = Arc::new(WalkStats::default());
+        let (sender, receiver) = kanal::unbounded();
+
+        spawn_path_senders(
+            &status,
+            &root,
+            &stats,
+            sender,
+            read_paths(listing.as_bytes(), b'\n'),
+            4,
+            SenderOptions::default(),
+        )
+        .unwrap();
+
+        let mut matched: Vec<PathBuf> = receiver
+            .filter_map(|message| match message {
+                EntryMessage::Success(entry) => Some(entry.into_path()),
+                _ => None,
+            })
+            .collect();
+        matched.sort();
+
+        let mut expected: Vec<PathBuf> = (0..1000)
+            .step_by(2)
+            .map(|index| dir.path().join(format!("{index}.txt")))
+            .collect();
+        expected.sort();
+
+        assert_eq!(matched, expected);
+        assert_eq!(stats.visited.load(Ordering::Relaxed), 1000);
+
+        // a failed read stops the list, but the paths before it are evaluated
+        let (sender, receiver) = kanal::unbounded();
+        let listed =
+            [Ok(dir.path().join("0.txt")), Err(std::io::Error::other("").into())];
+        let result = spawn_path_senders(
+            &status,
+            &root,
+            &stats,
+            sender,
+            listed.into_iter().chain([Ok(dir.path().join("2.txt"))]),
+            4,
+            SenderOptions::default(),
+        );
+        assert!(result.is_err());
+        let matched = receiver
+            .filter_map(|message| match message {
+                EntryMessage::Success(entry) => Some(entry.into_path()),
+                _ => None,
+            })
+            .collect_vec();
+        assert_eq!(matched, [dir.path().join("0.txt")]);
+    }
+
+    #[test]
+    fn test_sort() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut listing = vec![];
+        for index in (0..1000).rev() {
+            let path = dir.path().join(format!("{index:04}.txt"));
+            File::create(&path).unwrap();
+            listing.push(path);
+        }
+
+        // the paths are read in reverse
+        let files_from = dir.path().join("listing");
+        let listing = listing.iter().map(|path| path.display().to_string()).join("\n");
+        std::fs::write(&files_from, listing).unwrap();
+
+        let output = |sort: &[&str]| {
+            let files_from = files_from.to_str().unwrap();
+            let args = ["-e", "ext = txt", "--threads", "4", "--files-from", files_from];
+            let (_, output, _) = search(&args.iter().chain(sort).copied().collect_vec());
+            output.lines().map(PathBuf::from).collect_vec()
+        };
+
+        let expected = (0..1000)
+            .map(|index| dir.path().join(format!("{index:04}.txt")))
+            .collect_vec();
+        assert_eq!(output(&["--sort"]), expected);
+        assert_eq!(output(&["--sort", "--breadth-first"]), expected);
+        assert_eq!(output(&[]).into_iter().sorted().collect_vec(), expected);
+    }
+
+    #[test]
+    fn test_show_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("unreadable.txt");
+        std::fs::write(&path, "needle").unwrap();
+        std::fs::set_permissions(&path, Permissions::from_mode(0o000)).unwrap();
+        if File::open(&path).is_ok() {
+            // permissions are not enforced, e.g. when running as root
+            return;
+        }
+
+        let run = |flags: &[&str]| {
+            let entry = WalkBuilder::new(&path).build().next().unwrap();
+            let root = parse_root("contains = *needle*").unwrap();
+            let args = ["-e", "name=*"].iter().chain(flags).copied().collect_vec();
+            let (options, mut recorder) = (SenderOptions::default(), Recorder::default());
+            let mut errors = 0;
+            let config = config_from(&args);
+            let (_, stdout, stderr) = receive(config, |status, stats, sender| {
+                let query = Query::new(&root, None);
+                let recorder = &mut recorder;
+                process_entry(entry, query, &options, recorder, status, stats, &sender);
+                errors = stats.errors.load(Ordering::Relaxed);
+            });
+
+            assert!(stdout.is_empty());
+            assert_eq!(errors, 1);
+            stderr
+        };
+
+        assert_eq!(run(&[]), "");
+        let reported = run(&["--show-errors"]);
+        assert!(reported.starts_with(&format!("{}\n\t", path.display())));
+        assert!(reported.contains("PermissionDenied"));
+    }
+
+    #[test]
+    fn test_errors_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a \"quoted\"\tname.txt");
+        File::create(&path).unwrap();
+
+        let run = |flags: &[&str]| {
+            let entry = WalkBuilder::new(&path).build().next().unwrap().unwrap();
+            let error = std::io::Error::new(std::io::ErrorKind::TimedOut, "too slow");
+            receive_messages(flags, vec![EntryMessage::Error(entry, error.into())]).2
+        };
+
+        let escaped_path =
+            path.display().to_string().replace('"', "\\\"").replace('\t', "\\t");
+        assert_eq!(
+            run(&["--errors", "json"]),
+            format!(
+                r#"{{"path":"{escaped_path}","kind":"io_error","message":"IO Error: too slow"}}"#
+            ) + "\n"
+        );
+
+        let text = run(&[]);
+        assert!(text.starts_with(&format!("{}\n\t", path.display())), "{text}");
+        assert!(Args::try_parse_from(["fgr", "-e", "name=*", "--errors", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let exit_code = |error_kind: Option<std::io::ErrorKind>,
+                         matched,
+                         flags: &[&str]| {
+            let entry = || WalkBuilder::new(file.path()).build().next().unwrap().unwrap();
+            let mut messages = vec![];
+            if let Some(kind) = error_kind {
+                let error = GenericError::IoError(kind.into());
+                messages.push(EntryMessage::Error(entry(), error));
+            }
+            if matched {
+                messages.push(EntryMessage::Success(entry()));
+            }
+            receive_messages(flags, messages).0
+        };
+        let timed_out = Some(std::io::ErrorKind::TimedOut);
+        let denied = Some(std::io::ErrorKind::PermissionDenied);
+
+        assert_eq!(exit_code(None, true, &[]), 0);
+        assert_eq!(exit_code(denied, true, &[]), 0);
+        assert_eq!(exit_code(timed_out, true, &[]), 2);
+        assert_eq!(exit_code(timed_out, false, &["--show-errors"]), 2);
+
+        assert_eq!(exit_code(timed_out, true, &["-Q"]), 0);
+        assert_eq!(exit_code(timed_out, false, &["-Q"]), 2);
+        assert_eq!(exit_code(denied, false, &["-Q"]), 1);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut deep = dir.path().to_path_buf();
+        for index in 0..50 {
+            deep.push(index.to_string());
+            std::fs::create_dir(&deep).unwrap();
+            for file in 0..20 {
+                File::create(deep.join(format!("{file}.txt"))).unwrap();
+            }
+        }
+
+        let dir = dir.path().to_str().unwrap();
+        let config = config_from(&[dir, "-e", "name=*", "--timeout", "0s"]);
+        assert_eq!(config.timeout, Some(Duration::ZERO));
+
+        let root = Arc::new(config.root.clone());
+        let timeout = config.timeout.unwrap();
+        let walker = config.walk_builder().threads(2).build_parallel();
+        let mut visited = 0;
+        let (code, _, _) = receive(config, |status, stats, sender| {
+            spawn_watchdog(status, timeout);
+            std::thread::sleep(Duration::from_millis(50));
+            let options = SenderOptions::default();
+            spawn_senders(status, &root, None, stats, sender, walker, options);
+            assert!(*status.lock().unwrap() == ProcessStatus::Cancelled);
+            visited = stats.visited.load(Ordering::Relaxed);
+        });
+
+        assert_eq!(code, 1);
+        assert!(visited < 50 * 21);
+    }
+
+    #[test]
+    fn test_timeout_after_senders_finish() {
+        // the receive timeout keeps the receiver waiting for the channel to close
+        let config = config_from(&["-e", "name=*", "--timeout", "1s"]);
+        let (code, _, _) = receive(config, |status, _, _sender| {
+            std::thread::sleep(Duration::from_millis(50));
+            // the senders stop walking on a timeout and close the channel
+            *status.lock().unwrap() = ProcessStatus::Cancelled;
+        });
+
+        assert_eq!(code, 1);
+    }
+
+    #[test]
+    fn test_quiet() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..100 {
+            File::create(dir.path().join(format!("{index}.txt"))).unwrap();
+        }
+
+        let run = |query: &str| {
+            let dir = dir.path().to_str().unwrap();
+            let (code, stdout, stderr) = search(&[dir, "-Q", "--stats", "-e", query]);
+            assert!(stdout.is_empty());
+            assert!(stderr.starts_with("Visited: "), "{stderr}");
+            (code, stderr)
+        };
+
+        let (code, stderr) = run("ext = txt");
+        assert_eq!(code, 0);
+        assert!(stderr.contains("Matched: 1\n"), "{stderr}");
+        let (code, stderr) = run("ext = rs");
+        assert_eq!(code, 1);
+        assert!(stderr.contains("Matched: 0\n"), "{stderr}");
+
+        let args = ["fgr", "-Q", "--find-hardlinks", "-e", "ext = txt"];
+        assert!(Args::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_min_results() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..3 {
+            File::create(dir.path().join(format!("{index}.txt"))).unwrap();
+        }
+
+        let run = |min_results: &str| {
+            let dir = dir.path().to_str().unwrap();
+            let (code, _, stderr) =
+                search(&[dir, "-e", "ext = txt", "--min-results", min_results]);
+            (code, stderr)
+        };
+
+        assert_eq!(run("3"), (0, String::new()));
+        assert_eq!(run("4"), (3, "Expected at least 4 matches, found 3\n".to_string()));
+    }
+
+    #[test]
+    fn test_show_match_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "first\nthe needle\nlast\nneedles\n").unwrap();
+        std::fs::write(dir.path().join("notes.bin"), "needle\0").unwrap();
+        File::create(dir.path().join("needle.md")).unwrap();
+
+        let dir_arg = dir.path().to_str().unwrap();
+        let query = "contains = *needle* or name = needle.*";
+        let (_, output, _) = search(&[dir_arg, "-e", query, "--show-match-lines"]);
+        let notes = path.display();
+        assert_eq!(
+            output.lines().sorted().collect_vec(),
+            [
+                format!("{dir_arg}/needle.md"),
+                format!("{dir_arg}/notes.bin"),
+                format!("{notes}:2:the needle"),
+                format!("{notes}:4:needles"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invert_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        File::create(dir.path().join("main.rs")).unwrap();
+
+        let run = |query: &str, quiet: bool, cancelled: bool| {
+            let dir_arg = dir.path().to_str().unwrap();
+            let mut args = vec![dir_arg, "-e", query, "--invert-exit"];
+            if quiet {
+                args.push("-Q");
+            }
+            if !cancelled {
+                return search(&args).0;
+            }
+            // nothing is searched
+            let (code, _, _) = receive(config_from(&args), |status, _, _| {
+                *status.lock().unwrap() = ProcessStatus::Cancelled;
+            });
+            code
+        };
+
+        for quiet in [false, true] {
+            assert_eq!(run("ext = rs", quiet, false), 1);
+            assert_eq!(run("ext = py", quiet, false), 0);
+            // nothing was searched, so the missing matches prove nothing
+            assert_eq!(run("ext = py", quiet, true), 1);
+        }
+    }
+
+    #[test]
+    fn test_walk_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        File::create(dir.path().join("a.txt")).unwrap();
+        File::create(dir.path().join("b.rs")).unwrap();
+
+        let root = Arc::new(parse_root("ext = txt").unwrap());
+        let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+        let stats = Arc::new(WalkStats::default());
+        let (sender, receiver) = kanal::unbounded();
+
+        let walker = WalkBuilder::new(dir.path()).threads(2).build_parallel();
+        spawn_senders(
+            &status,
+            &root,
+            None,
+            &stats,
+            sender,
+            walker,
+            SenderOptions::default(),
+        );
+
+        let matched = receiver
+            .filter(|message| matches!(message, EntryMessage::Success(_)))
+            .count();
+
+        assert_eq!(matched, 1);
+        assert_eq!(stats.visited.load(Ordering::Relaxed), 3);
+        assert_eq!(stats.errors.load(Ordering::Relaxed), 0);
+
+        stats.matched.store(matched, Ordering::Relaxed);
+        let summary = stats.summary();
+        assert!(summary.starts_with("Visited: 3\nMatched: 1\nErrors: 0\nElapsed: "));
+    }
+
+    #[test]
+    fn test_ordered_roots() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        for dir in [&first, &second] {
+            for index in 0..50 {
+                File::create(dir.path().join(format!("{index}.txt"))).unwrap();
+            }
+        }
+
+        let args = Args::try_parse_from([
+            "fgr",
+            first.path().to_str().unwrap(),
+            second.path().to_str().unwrap(),
+            "-e",
+            "ext = txt",
+            "-t",
+            "4",
+            "--ordered-roots",
+        ])
+        .unwrap();
+        let config = Config::from_args(args).unwrap();
+        let root = Arc::new(config.root.clone());
+        let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+        let stats = Arc::new(WalkStats::default());
+        let (sender, receiver) = kanal::unbounded();
+
+        let walks = config.walk_builders();
+        assert_eq!(walks.len(), 2);
+        for walk in &walks {
+            let walk = walk.build_parallel();
+            spawn_senders(
+                &status,
+                &root,
+                None,
+                &stats,
+                sender.clone(),
+                walk,
+                SenderOptions::default(),
+            );
+        }
+        drop(sender);
+
+        let paths = receiver
+            .filter_map(|message| match message {
+                EntryMessage::Success(entry) => Some(entry.into_path()),
+                _ => None,
+            })
+            .collect_vec();
+        assert_eq!(paths.len(), 100);
+        let first_count =
+            paths.iter().take_while(|path| path.starts_with(first.path())).count();
+        assert_eq!(first_count, 50, "{paths:?}");
+    }
+}
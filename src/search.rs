@@ -0,0 +1,213 @@
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ignore::WalkBuilder;
+use nnf::parse_tree::ExpressionNode;
+
+use crate::errors::GenericError;
+use crate::parse::filter::Filter;
+use crate::parse::parse_root;
+use crate::run::{spawn_senders, EntryMessage, ProcessStatus, SenderOptions, WalkStats};
+
+/// The number of matches [`search_each`] buffers before the walker threads wait
+pub const SEARCH_BUFFER_SIZE: usize = 1024;
+
+/// Walker settings for [`search`]; the defaults visit every entry, like find
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Number of threads to use, 0 picks one per CPU
+    pub threads: usize,
+    /// Skip hidden files and entries ignored by .gitignore, .ignore and friends
+    pub standard_filters: bool,
+    /// Do not cross file system boundaries
+    pub same_filesystem: bool,
+    /// Do not descend deeper than this many levels below the start directories
+    pub max_depth: Option<usize>,
+    /// Also match the members of zip, tar and tar.gz archives, found as
+    /// `ARCHIVE::MEMBER`
+    pub search_archives: bool,
+}
+
+impl SearchOptions {
+    pub fn walk_builder(&self, dirs: &[PathBuf]) -> Option<WalkBuilder> {
+        let (first, rest) = dirs.split_first()?;
+
+        let mut builder = WalkBuilder::new(first);
+        for dir in rest {
+            builder.add(dir);
+        }
+        builder
+            .standard_filters(self.standard_filters)
+            .same_file_system(self.same_filesystem)
+            .max_depth(self.max_depth)
+            .threads(if self.threads == 0 { num_cpus::get() } else { self.threads });
+
+        Some(builder)
+    }
+}
+
+/// Parses `expression` and prepares it for evaluation, see [`prepare`]
+pub fn compile(expression: &str) -> Result<ExpressionNode<Filter>, GenericError> {
+    Ok(prepare(parse_root(expression)?))
+}
+
+/// Pushes the negations of a parsed tree down to its filters and puts the cheapest
+/// operands first, so evaluating it short-circuits before the expensive ones
+pub fn prepare(root: ExpressionNode<Filter>) -> ExpressionNode<Filter> {
+    let mut root = root.to_nnf();
+    root.sort_by_key(|filter| filter.weight());
+    root
+}
+
+/// Walks `dirs` and returns the sorted paths of the entries matching `expression`
+pub fn search(
+    dirs: &[PathBuf],
+    expression: &str,
+    options: SearchOptions,
+) -> Result<Vec<PathBuf>, GenericError> {
+    let mut paths = vec![];
+    search_each(dirs, expression, options, |path| {
+        paths.push(path.to_path_buf());
+        ControlFlow::Continue(())
+    })?;
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Walks `dirs` and calls `f` with each entry matching `expression` as soon as it is
+/// found, in no particular order. At most [`SEARCH_BUFFER_SIZE`] matches are buffered,
+/// and returning [`ControlFlow::Break`] from `f` stops the walk.
+pub fn search_each(
+    dirs: &[PathBuf],
+    expression: &str,
+    options: SearchOptions,
+    mut f: impl FnMut(&Path) -> ControlFlow<()>,
+) -> Result<(), GenericError> {
+    let root = compile(expression)?;
+
+    let Some(builder) = options.walk_builder(dirs) else {
+        return Ok(());
+    };
+
+    let root = Arc::new(root);
+    let status = Arc::new(Mutex::new(ProcessStatus::InProgress));
+    let stats = Arc::new(WalkStats::default());
+    let (sender, receiver) = kanal::bounded(SEARCH_BUFFER_SIZE);
+
+    std::thread::scope(|scope| {
+        let walker = builder.build_parallel();
+        let (root, status, stats) = (&root, &status, &stats);
+        scope.spawn(move || {
+            spawn_senders(
+                status,
+                root,
+                None,
+                stats,
+                sender,
+                walker,
+                SenderOptions {
+                    search_archives: options.search_archives,
+                    ..SenderOptions::default()
+                },
+            )
+        });
+
+        let mut stopped = false;
+        // keep draining after a break, so the walker threads never block on a full
+        // channel before they notice the cancellation
+        for message in receiver {
+            let flow = match (message, stopped) {
+                (EntryMessage::Success(entry), false) => f(entry.path()),
+                (EntryMessage::ArchiveMember(member), false) => f(member.display_path()),
+                _ => continue,
+            };
+            if flow.is_break() {
+                stopped = true;
+                *status.lock().unwrap() = ProcessStatus::Cancelled;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::ops::ControlFlow;
+    use std::path::PathBuf;
+
+    use crate::search::{search, search_each, SearchOptions};
+
+    #[test]
+    fn test_search() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a").join("b")).unwrap();
+        for path in ["one.rs", "a/two.rs", "a/b/three.rs", "a/b/four.txt", ".hidden.rs"] {
+            File::create(dir.path().join(path)).unwrap();
+        }
+        let dirs = [dir.path().to_path_buf()];
+
+        let found = search(&dirs, "ext = rs", SearchOptions::default()).unwrap();
+        let expected: Vec<_> = [".hidden.rs", "a/b/three.rs", "a/two.rs", "one.rs"]
+            .iter()
+            .map(|path| dir.path().join(path))
+            .collect();
+        assert_eq!(found, expected);
+
+        let options = SearchOptions {
+            standard_filters: true,
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let found = search(&dirs, "ext = rs", options).unwrap();
+        assert_eq!(found, [dir.path().join("a/two.rs"), dir.path().join("one.rs")]);
+
+        assert!(search(&[], "ext = rs", SearchOptions::default()).unwrap().is_empty());
+
+        let error = search(&dirs, "colour = red", SearchOptions::default()).unwrap_err();
+        assert!(error.is_fatal(), "{error:?}");
+
+        let archive = dir.path().join("a/sources.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("src/main.rs", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.finish().unwrap();
+        let options = SearchOptions { search_archives: true, ..Default::default() };
+        let found = search(&dirs, "name = main.rs", options).unwrap();
+        assert_eq!(found, [PathBuf::from(format!("{}::src/main.rs", archive.display()))]);
+        assert!(search(&dirs, "name = main.rs", SearchOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_search_each_stops_early() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..5000 {
+            File::create(dir.path().join(format!("{index}.txt"))).unwrap();
+        }
+        let dirs = [dir.path().to_path_buf()];
+
+        let mut found = vec![];
+        search_each(&dirs, "ext = txt", SearchOptions::default(), |path| {
+            found.push(path.to_path_buf());
+            if found.len() == 10 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+        assert_eq!(found.len(), 10);
+
+        let mut count = 0;
+        search_each(&dirs, "ext = txt", SearchOptions::default(), |_| {
+            count += 1;
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(count, 5000);
+    }
+}
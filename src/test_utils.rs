@@ -1,14 +1,37 @@
 #![allow(dead_code)]
 
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::Permissions;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+use crate::evaluate::content_cache::ContentCache;
+use crate::evaluate::decode::ContentEncoding;
+use crate::evaluate::literal_finder::LiteralFinder;
+use crate::parse::comparison::Comparison;
+use crate::parse::contains_skip::ContainsSkip;
+use crate::parse::filter::Filter;
+use crate::parse::match_pattern::MatchPattern;
 use crate::walk::entry_type::EntryType;
 use crate::walk::traits::DirEntryWrapperExt;
 use crate::GenericError;
 
+/// A `contains = <pattern>` filter with the options of a plain query
+pub(crate) fn contains_filter(pattern: impl Into<MatchPattern>) -> Filter {
+    Filter::Contains {
+        value: pattern.into(),
+        comparison: Comparison::Eq,
+        max_bytes: None,
+        read_timeout: None,
+        io_retries: 0,
+        encoding: ContentEncoding::Utf8,
+        skip: ContainsSkip::default(),
+        cache: ContentCache::default(),
+        finder: LiteralFinder::default(),
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct DirEntryMock {
     pub(crate) entry_type: Option<EntryType>,
@@ -25,6 +48,12 @@ pub(crate) struct DirEntryMock {
 
     pub(crate) permissions: Option<Permissions>,
 
+    pub(crate) dev: Option<u64>,
+    pub(crate) inode: Option<u64>,
+
+    pub(crate) symlink_target: Option<PathBuf>,
+    pub(crate) xattrs: HashMap<String, Vec<u8>>,
+
     pub(crate) bool: Option<bool>,
 }
 
@@ -69,6 +98,24 @@ impl DirEntryMock {
         self.permissions = permissions.into();
         self
     }
+    pub(crate) fn set_dev(mut self, dev: u64) -> Self {
+        self.dev = dev.into();
+        self
+    }
+    pub(crate) fn set_inode(mut self, inode: u64) -> Self {
+        self.inode = inode.into();
+        self
+    }
+
+    pub(crate) fn set_symlink_target(mut self, target: PathBuf) -> Self {
+        self.symlink_target = target.into();
+        self
+    }
+
+    pub(crate) fn set_xattr(mut self, name: &str, value: &[u8]) -> Self {
+        self.xattrs.insert(name.to_string(), value.to_vec());
+        self
+    }
 
     pub(crate) fn set_bool(mut self, value: bool) -> Self {
         self.bool = value.into();
@@ -89,8 +136,12 @@ impl DirEntryWrapperExt for DirEntryMock {
         self.file.as_ref().unwrap().as_path()
     }
 
-    fn get_size(&self) -> usize {
-        self.size.unwrap_or(0)
+    fn get_disk_path(&self) -> Result<&Path, GenericError> {
+        Ok(self.get_path())
+    }
+
+    fn get_size(&self) -> Result<usize, GenericError> {
+        Ok(self.size.unwrap_or(0))
     }
 
     fn get_depth(&self) -> usize {
@@ -145,6 +196,30 @@ impl DirEntryWrapperExt for DirEntryMock {
         }
     }
 
+    fn get_dev(&self) -> Result<u64, GenericError> {
+        if let Some(dev) = self.dev {
+            Ok(dev)
+        } else {
+            Err(GenericError::UnknownCommand("sample".to_string()))
+        }
+    }
+
+    fn get_inode(&self) -> Result<u64, GenericError> {
+        if let Some(inode) = self.inode {
+            Ok(inode)
+        } else {
+            Err(GenericError::UnknownCommand("sample".to_string()))
+        }
+    }
+
+    fn get_symlink_target(&self) -> Result<Option<PathBuf>, GenericError> {
+        Ok(self.symlink_target.clone())
+    }
+
+    fn get_xattr(&self, name: &str) -> Result<Option<Vec<u8>>, GenericError> {
+        Ok(self.xattrs.get(name).cloned())
+    }
+
     #[cfg(test)]
     fn get_bool(&self) -> bool {
         unimplemented!()
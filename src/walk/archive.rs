@@ -0,0 +1,313 @@
+use std::ffi::OsStr;
+use std::fs::{File, Permissions};
+use std::io::{BufReader, Read};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use flate2::read::GzDecoder;
+use timeout_readwrite::TimeoutReader;
+
+use crate::errors::GenericError;
+use crate::walk::entry_type::EntryType;
+use crate::walk::traits::DirEntryWrapperExt;
+
+/// A file or directory inside a zip or tar archive. Only its name, path, size and
+/// depth are known; every other attribute is an `Unsupported` error.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ArchiveMember {
+    /// The member path under the archive path, as if the archive was a directory
+    path: PathBuf,
+    /// `archive.zip::member/path`, what is printed for a match
+    display_path: PathBuf,
+    size: usize,
+    depth: usize,
+    is_dir: bool,
+}
+
+impl ArchiveMember {
+    fn new(
+        archive: &Path,
+        archive_depth: usize,
+        member: &Path,
+        size: u64,
+        is_dir: bool,
+    ) -> Self {
+        // drops the trailing slash of directories
+        let member = member.components().collect::<PathBuf>();
+        let mut display_path = archive.as_os_str().to_owned();
+        display_path.push("::");
+        display_path.push(member.as_os_str());
+
+        Self {
+            path: archive.join(&member),
+            display_path: display_path.into(),
+            size: size as usize,
+            depth: archive_depth + member.components().count(),
+            is_dir,
+        }
+    }
+
+    pub fn display_path(&self) -> &Path {
+        &self.display_path
+    }
+
+    pub fn into_display_path(self) -> PathBuf {
+        self.display_path
+    }
+}
+
+/// The archive formats whose members can be searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Tells the format by the extension, so other files are never opened
+    fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Calls `f` with each member of a zip, tar or tar.gz archive, told apart by the
+/// extension, until it breaks. Anything else has no members. A single read may
+/// block for `read_timeout`.
+pub fn for_each_member(
+    archive: &Path,
+    archive_depth: usize,
+    read_timeout: Duration,
+    f: impl FnMut(ArchiveMember) -> ControlFlow<()>,
+) -> Result<(), GenericError> {
+    let Some(kind) = ArchiveKind::from_path(archive) else {
+        return Ok(());
+    };
+    let reader = BufReader::new(TimeoutReader::new(File::open(archive)?, read_timeout));
+
+    match kind {
+        ArchiveKind::Zip => for_each_zip_member(archive, archive_depth, reader, f),
+        ArchiveKind::Tar => for_each_tar_member(archive, archive_depth, reader, f),
+        ArchiveKind::TarGz => {
+            for_each_tar_member(archive, archive_depth, GzDecoder::new(reader), f)
+        }
+    }
+}
+
+fn for_each_zip_member(
+    archive: &Path,
+    archive_depth: usize,
+    reader: BufReader<TimeoutReader<File>>,
+    mut f: impl FnMut(ArchiveMember) -> ControlFlow<()>,
+) -> Result<(), GenericError> {
+    let mut zip = zip::ZipArchive::new(reader).map_err(std::io::Error::other)?;
+
+    for index in 0..zip.len() {
+        let member = zip.by_index_raw(index).map_err(std::io::Error::other)?;
+        let Some(name) = member.enclosed_name() else {
+            continue;
+        };
+        let member = ArchiveMember::new(
+            archive,
+            archive_depth,
+            &name,
+            member.size(),
+            member.is_dir(),
+        );
+        if f(member).is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn for_each_tar_member(
+    archive: &Path,
+    archive_depth: usize,
+    reader: impl Read,
+    mut f: impl FnMut(ArchiveMember) -> ControlFlow<()>,
+) -> Result<(), GenericError> {
+    let mut tar = tar::Archive::new(reader);
+
+    for member in tar.entries()? {
+        let member = member?;
+        let header = member.header();
+        let member = ArchiveMember::new(
+            archive,
+            archive_depth,
+            &member.path()?,
+            header.size()?,
+            header.entry_type().is_dir(),
+        );
+        if f(member).is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn unsupported<T>() -> Result<T, GenericError> {
+    let error = std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "not available for archive members",
+    );
+    Err(error.into())
+}
+
+impl DirEntryWrapperExt for ArchiveMember {
+    fn get_entry_type(&self) -> EntryType {
+        if self.is_dir {
+            EntryType::Dir
+        } else {
+            EntryType::File
+        }
+    }
+
+    fn get_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    fn get_disk_path(&self) -> Result<&Path, GenericError> {
+        unsupported()
+    }
+
+    fn get_size(&self) -> Result<usize, GenericError> {
+        Ok(self.size)
+    }
+
+    fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    fn get_mtime(&self) -> Result<SystemTime, GenericError> {
+        unsupported()
+    }
+
+    fn get_atime(&self) -> Result<SystemTime, GenericError> {
+        unsupported()
+    }
+
+    fn get_btime(&self) -> Result<SystemTime, GenericError> {
+        unsupported()
+    }
+
+    fn get_user_id(&self) -> Result<u32, GenericError> {
+        unsupported()
+    }
+
+    fn get_group_id(&self) -> Result<u32, GenericError> {
+        unsupported()
+    }
+
+    fn get_permissions(&self) -> Result<Permissions, GenericError> {
+        unsupported()
+    }
+
+    fn get_dev(&self) -> Result<u64, GenericError> {
+        unsupported()
+    }
+
+    fn get_inode(&self) -> Result<u64, GenericError> {
+        unsupported()
+    }
+
+    fn get_symlink_target(&self) -> Result<Option<PathBuf>, GenericError> {
+        Ok(None)
+    }
+
+    fn get_xattr(&self, _name: &str) -> Result<Option<Vec<u8>>, GenericError> {
+        Ok(None)
+    }
+
+    #[cfg(test)]
+    fn get_bool(&self) -> bool {
+        unimplemented!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use crate::parse::parse_root;
+    use crate::walk::archive::for_each_member;
+    use crate::walk::traits::DirEntryWrapperExt;
+    use crate::Evaluate;
+
+    #[test]
+    fn test_tar_gz_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("sources.tar.gz");
+
+        let encoder = GzEncoder::new(
+            std::fs::File::create(&archive).unwrap(),
+            Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for name in ["src/main.rs", "src/lib.rs"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(5);
+            header.set_cksum();
+            builder.append_data(&mut header, name, &b"hello"[..]).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let first_member = |archive: &Path| {
+            let mut members = vec![];
+            for_each_member(archive, 1, Duration::from_secs(1), |member| {
+                members.push(member);
+                ControlFlow::Break(())
+            })
+            .map(|_| members)
+        };
+        // streamed, so nothing is read past the first member
+        let members = first_member(&archive).unwrap();
+        assert_eq!(members.len(), 1);
+
+        let member = &members[0];
+        assert_eq!(member.get_name(), "main.rs");
+        assert_eq!(member.get_size().unwrap(), 5);
+        assert_eq!(member.get_depth(), 3);
+        assert_eq!(
+            member.display_path(),
+            Path::new(&format!("{}::src/main.rs", archive.display()))
+        );
+        assert!(member.get_mtime().is_err());
+        // the member path is not a file on disk, nothing may open it
+        assert!(parse_root("readable").unwrap().evaluate(member).is_err());
+
+        // not an archive
+        assert!(first_member(Path::new("Cargo.toml")).unwrap().is_empty());
+        // not a tar inside, though the extension says so
+        let gz = dir.path().join("notes.tar.gz");
+        let mut encoder =
+            GzEncoder::new(std::fs::File::create(&gz).unwrap(), Compression::default());
+        std::io::Write::write_all(&mut encoder, b"plain text").unwrap();
+        encoder.finish().unwrap();
+        assert!(first_member(&gz).is_err());
+        // only the extension tells an archive, the contents are not sniffed
+        std::fs::copy(&archive, dir.path().join("sources")).unwrap();
+        assert!(first_member(&dir.path().join("sources")).unwrap().is_empty());
+    }
+}
@@ -1,31 +1,58 @@
 use std::ffi::OsStr;
-use std::fs::Permissions;
+use std::fs::{Metadata, Permissions};
 use std::os::unix::prelude::{FileTypeExt, MetadataExt};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use ignore::DirEntry;
 
 use crate::errors::GenericError;
 use crate::walk::entry_type::EntryType;
-use crate::walk::traits::DirEntryWrapperExt;
+use crate::walk::traits::{DirEntryWrapperExt, FileIdentity};
 
+pub mod archive;
 pub mod entry_type;
 pub mod traits;
+pub mod vfs;
 
-impl DirEntryWrapperExt for DirEntry {
+/// An entry of a walk. Its metadata is read with stat, describing the target of a
+/// symlink, if `follow_metadata` is set, and with lstat, describing the link itself,
+/// otherwise.
+#[derive(Debug, Clone, Copy)]
+pub struct WalkedEntry<'a> {
+    entry: &'a DirEntry,
+    follow_metadata: bool,
+}
+
+impl<'a> WalkedEntry<'a> {
+    pub fn new(entry: &'a DirEntry, follow_metadata: bool) -> Self {
+        Self { entry, follow_metadata }
+    }
+
+    fn metadata(&self) -> std::io::Result<Metadata> {
+        if self.follow_metadata {
+            self.entry.path().metadata()
+        } else {
+            self.entry.path().symlink_metadata()
+        }
+    }
+}
+
+impl DirEntryWrapperExt for WalkedEntry<'_> {
     fn get_entry_type(&self) -> EntryType {
-        let path = self.path();
-        if path.is_dir() {
+        let (entry, path) = (self.entry, self.entry.path());
+        if !self.follow_metadata && entry.path_is_symlink() {
+            EntryType::Symlink
+        } else if path.is_dir() {
             EntryType::Dir
         } else if path.is_file() {
             EntryType::File
         } else if path.is_symlink() {
             EntryType::Symlink
-        } else if self.is_stdin() {
+        } else if entry.is_stdin() {
             EntryType::StdIn
         } else {
-            match self.file_type() {
+            match entry.file_type() {
                 None => EntryType::Unknown,
                 Some(ft) if ft.is_socket() => EntryType::Socket,
                 Some(ft) if ft.is_block_device() => EntryType::BlockDevice,
@@ -37,43 +64,84 @@ impl DirEntryWrapperExt for DirEntry {
     }
 
     fn get_name(&self) -> &OsStr {
-        self.file_name()
+        self.entry.file_name()
     }
 
     fn get_path(&self) -> &Path {
-        self.path()
+        self.entry.path()
     }
 
-    fn get_size(&self) -> usize {
-        self.path().metadata().map(|metadata| metadata.len() as usize).unwrap_or(0)
+    fn get_disk_path(&self) -> Result<&Path, GenericError> {
+        Ok(self.entry.path())
+    }
+
+    /// A dangling symlink has no target to describe, so it reports its own size
+    fn get_size(&self) -> Result<usize, GenericError> {
+        let metadata =
+            self.metadata().or_else(|_| self.entry.path().symlink_metadata())?;
+        Ok(metadata.len() as usize)
     }
 
     fn get_depth(&self) -> usize {
-        self.depth()
+        self.entry.depth()
     }
 
     fn get_mtime(&self) -> Result<SystemTime, GenericError> {
-        Ok(self.path().metadata()?.modified()?)
+        Ok(self.metadata()?.modified()?)
     }
 
     fn get_atime(&self) -> Result<SystemTime, GenericError> {
-        Ok(self.path().metadata()?.accessed()?)
+        Ok(self.metadata()?.accessed()?)
     }
 
     fn get_btime(&self) -> Result<SystemTime, GenericError> {
-        Ok(self.path().metadata()?.created()?)
+        Ok(self.metadata()?.created()?)
     }
 
     fn get_user_id(&self) -> Result<u32, GenericError> {
-        Ok(self.path().metadata()?.uid())
+        Ok(self.metadata()?.uid())
     }
 
     fn get_group_id(&self) -> Result<u32, GenericError> {
-        Ok(self.path().metadata()?.gid())
+        Ok(self.metadata()?.gid())
     }
 
     fn get_permissions(&self) -> Result<Permissions, GenericError> {
-        Ok(self.path().metadata()?.permissions())
+        Ok(self.metadata()?.permissions())
+    }
+
+    fn get_dev(&self) -> Result<u64, GenericError> {
+        Ok(self.metadata()?.dev())
+    }
+
+    fn get_inode(&self) -> Result<u64, GenericError> {
+        Ok(self.metadata()?.ino())
+    }
+
+    fn get_identity(&self) -> Result<FileIdentity, GenericError> {
+        let metadata = self.metadata()?;
+        Ok(FileIdentity {
+            dev: metadata.dev(),
+            inode: metadata.ino(),
+            mtime: metadata.modified()?,
+            size: metadata.len() as usize,
+        })
+    }
+
+    fn get_symlink_target(&self) -> Result<Option<PathBuf>, GenericError> {
+        if !self.entry.path_is_symlink() {
+            return Ok(None);
+        }
+
+        Ok(Some(std::fs::read_link(self.entry.path())?))
+    }
+
+    fn get_xattr(&self, name: &str) -> Result<Option<Vec<u8>>, GenericError> {
+        match xattr::get(self.entry.path(), name) {
+            Ok(value) => Ok(value),
+            Err(error) if error.raw_os_error() == Some(libc::ENOTSUP) => Ok(None),
+            Err(error) => Err(error.into()),
+        }
     }
 
     #[cfg(test)]
@@ -1,16 +1,28 @@
 use std::ffi::OsStr;
 use std::fs::Permissions;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use crate::errors::GenericError;
 use crate::walk::entry_type::EntryType;
 
+/// What tells a file apart and whether it changed: its `(dev, inode)`, mtime and size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIdentity {
+    pub dev: u64,
+    pub inode: u64,
+    pub mtime: SystemTime,
+    pub size: usize,
+}
+
 pub trait DirEntryWrapperExt {
     fn get_entry_type(&self) -> EntryType;
     fn get_name(&self) -> &OsStr;
     fn get_path(&self) -> &Path;
-    fn get_size(&self) -> usize;
+    /// The path filters that read the contents, the children or the access rights
+    /// of the entry open, an `Unsupported` error for entries that are not on disk
+    fn get_disk_path(&self) -> Result<&Path, GenericError>;
+    fn get_size(&self) -> Result<usize, GenericError>;
     fn get_depth(&self) -> usize;
 
     fn get_mtime(&self) -> Result<SystemTime, GenericError>;
@@ -21,6 +33,25 @@ pub trait DirEntryWrapperExt {
     fn get_group_id(&self) -> Result<u32, GenericError>;
     fn get_permissions(&self) -> Result<Permissions, GenericError>;
 
+    fn get_dev(&self) -> Result<u64, GenericError>;
+    fn get_inode(&self) -> Result<u64, GenericError>;
+    /// The identity of the entry, read at once where the getters would stat it once
+    /// each
+    fn get_identity(&self) -> Result<FileIdentity, GenericError> {
+        Ok(FileIdentity {
+            dev: self.get_dev()?,
+            inode: self.get_inode()?,
+            mtime: self.get_mtime()?,
+            size: self.get_size()?,
+        })
+    }
+    /// Where the entry points to without following the link, `None` for anything
+    /// but a symlink
+    fn get_symlink_target(&self) -> Result<Option<PathBuf>, GenericError>;
+    /// The value of the extended attribute `name`, `None` when it is not set or the
+    /// file system has no extended attributes
+    fn get_xattr(&self, name: &str) -> Result<Option<Vec<u8>>, GenericError>;
+
     #[cfg(test)]
     fn get_bool(&self) -> bool;
 }
@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs::Permissions;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::errors::GenericError;
+use crate::search::compile;
+use crate::walk::entry_type::EntryType;
+use crate::walk::traits::DirEntryWrapperExt;
+use crate::Evaluate;
+
+/// A directory tree kept in memory, so queries can be evaluated against synthetic
+/// entries without touching the disk. Only the attributes stored in the entries are
+/// known: filters that read the disk, like `contains`, `dirsize` or `children`, fail
+/// for them with an `Unsupported` error, even if a file exists at the same path.
+#[derive(Debug, Clone)]
+pub struct VfsTree {
+    root: PathBuf,
+    /// The entries by their path under the root, the root itself is the empty path
+    entries: BTreeMap<PathBuf, VfsEntry>,
+    /// The inode of the next entry, never reused, even when an entry is replaced
+    next_inode: u64,
+}
+
+impl VfsTree {
+    /// An empty directory at `root`; nothing under it has to exist on disk
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let entry = VfsEntry::new(root.clone(), 0, EntryType::Dir, 1);
+        Self { root, entries: BTreeMap::from([(PathBuf::new(), entry)]), next_inode: 2 }
+    }
+
+    /// Adds a directory at `path` under the root
+    pub fn add_dir(&mut self, path: impl AsRef<Path>) -> &mut VfsEntry {
+        self.insert(path.as_ref(), EntryType::Dir)
+    }
+
+    /// Adds a file of `size` bytes at `path` under the root
+    pub fn add_file(&mut self, path: impl AsRef<Path>, size: usize) -> &mut VfsEntry {
+        self.insert(path.as_ref(), EntryType::File).set_size(size)
+    }
+
+    /// Adds a symlink at `path` under the root, pointing to `target`
+    pub fn add_symlink(
+        &mut self,
+        path: impl AsRef<Path>,
+        target: impl Into<PathBuf>,
+    ) -> &mut VfsEntry {
+        let entry = self.insert(path.as_ref(), EntryType::Symlink);
+        entry.symlink_target = Some(target.into());
+        entry
+    }
+
+    /// Replaces the entry at `path`, creating the directories above it that are
+    /// missing. Only the normal components of `path` are used, so it always stays
+    /// under the root.
+    fn insert(&mut self, path: &Path, entry_type: EntryType) -> &mut VfsEntry {
+        let relative = path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect::<PathBuf>();
+
+        let mut parent = PathBuf::new();
+        for component in relative.parent().into_iter().flat_map(Path::components) {
+            parent.push(component);
+            if !self.entries.contains_key(&parent) {
+                let depth = parent.components().count();
+                let inode = self.take_inode();
+                let entry =
+                    VfsEntry::new(self.root.join(&parent), depth, EntryType::Dir, inode);
+                self.entries.insert(parent.clone(), entry);
+            }
+        }
+
+        let inode = self.take_inode();
+        let depth = relative.components().count();
+        let entry = VfsEntry::new(self.root.join(&relative), depth, entry_type, inode);
+        self.entries.insert(relative.clone(), entry);
+        self.entries.get_mut(&relative).unwrap()
+    }
+
+    fn take_inode(&mut self) -> u64 {
+        let inode = self.next_inode;
+        self.next_inode += 1;
+        inode
+    }
+
+    /// Iterates over the entries depth-first, like a walk sorted by file name: the
+    /// root, then every directory followed by its contents
+    pub fn walk(&self) -> impl Iterator<Item = &VfsEntry> {
+        self.entries.values()
+    }
+
+    /// Returns the entries matching `expression` in walk order. Entries that cannot
+    /// be evaluated do not match, like in a search on disk.
+    pub fn search(&self, expression: &str) -> Result<Vec<&VfsEntry>, GenericError> {
+        let root = compile(expression)?;
+
+        Ok(self
+            .walk()
+            .filter(|entry| matches!(root.evaluate(*entry), Ok(true)))
+            .collect())
+    }
+}
+
+/// An entry of a [`VfsTree`]. Its times default to the Unix epoch, its owner to
+/// root, and its mode to 755 for directories and 644 for anything else.
+#[derive(Debug, Clone)]
+pub struct VfsEntry {
+    path: PathBuf,
+    depth: usize,
+    entry_type: EntryType,
+    size: usize,
+
+    mtime: SystemTime,
+    atime: SystemTime,
+    btime: SystemTime,
+
+    user_id: u32,
+    group_id: u32,
+    mode: u32,
+
+    dev: u64,
+    inode: u64,
+
+    symlink_target: Option<PathBuf>,
+    xattrs: HashMap<String, Vec<u8>>,
+}
+
+impl VfsEntry {
+    fn new(path: PathBuf, depth: usize, entry_type: EntryType, inode: u64) -> Self {
+        let mode = if entry_type == EntryType::Dir { 0o755 } else { 0o644 };
+        Self {
+            path,
+            depth,
+            entry_type,
+            size: 0,
+            mtime: SystemTime::UNIX_EPOCH,
+            atime: SystemTime::UNIX_EPOCH,
+            btime: SystemTime::UNIX_EPOCH,
+            user_id: 0,
+            group_id: 0,
+            mode,
+            dev: 0,
+            inode,
+            symlink_target: None,
+            xattrs: HashMap::new(),
+        }
+    }
+
+    pub fn set_size(&mut self, size: usize) -> &mut Self {
+        self.size = size;
+        self
+    }
+    pub fn set_mtime(&mut self, mtime: SystemTime) -> &mut Self {
+        self.mtime = mtime;
+        self
+    }
+    pub fn set_atime(&mut self, atime: SystemTime) -> &mut Self {
+        self.atime = atime;
+        self
+    }
+    pub fn set_btime(&mut self, btime: SystemTime) -> &mut Self {
+        self.btime = btime;
+        self
+    }
+    pub fn set_user_id(&mut self, user_id: u32) -> &mut Self {
+        self.user_id = user_id;
+        self
+    }
+    pub fn set_group_id(&mut self, group_id: u32) -> &mut Self {
+        self.group_id = group_id;
+        self
+    }
+    /// Sets the permission bits, e.g. `0o600`
+    pub fn set_mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+    pub fn set_dev(&mut self, dev: u64) -> &mut Self {
+        self.dev = dev;
+        self
+    }
+    pub fn set_inode(&mut self, inode: u64) -> &mut Self {
+        self.inode = inode;
+        self
+    }
+    pub fn set_xattr(&mut self, name: &str, value: &[u8]) -> &mut Self {
+        self.xattrs.insert(name.to_string(), value.to_vec());
+        self
+    }
+}
+
+impl DirEntryWrapperExt for VfsEntry {
+    fn get_entry_type(&self) -> EntryType {
+        self.entry_type.clone()
+    }
+
+    fn get_name(&self) -> &OsStr {
+        // the root may be `/`, which has no file name
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The tree is never backed by the disk, even if a file exists at the same path
+    fn get_disk_path(&self) -> Result<&Path, GenericError> {
+        let error = std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "not available for in-memory entries",
+        );
+        Err(error.into())
+    }
+
+    fn get_size(&self) -> Result<usize, GenericError> {
+        Ok(self.size)
+    }
+
+    fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    fn get_mtime(&self) -> Result<SystemTime, GenericError> {
+        Ok(self.mtime)
+    }
+
+    fn get_atime(&self) -> Result<SystemTime, GenericError> {
+        Ok(self.atime)
+    }
+
+    fn get_btime(&self) -> Result<SystemTime, GenericError> {
+        Ok(self.btime)
+    }
+
+    fn get_user_id(&self) -> Result<u32, GenericError> {
+        Ok(self.user_id)
+    }
+
+    fn get_group_id(&self) -> Result<u32, GenericError> {
+        Ok(self.group_id)
+    }
+
+    fn get_permissions(&self) -> Result<Permissions, GenericError> {
+        Ok(Permissions::from_mode(self.mode))
+    }
+
+    fn get_dev(&self) -> Result<u64, GenericError> {
+        Ok(self.dev)
+    }
+
+    fn get_inode(&self) -> Result<u64, GenericError> {
+        Ok(self.inode)
+    }
+
+    fn get_symlink_target(&self) -> Result<Option<PathBuf>, GenericError> {
+        Ok(self.symlink_target.clone())
+    }
+
+    fn get_xattr(&self, name: &str) -> Result<Option<Vec<u8>>, GenericError> {
+        Ok(self.xattrs.get(name).cloned())
+    }
+
+    #[cfg(test)]
+    fn get_bool(&self) -> bool {
+        unimplemented!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    use crate::errors::GenericError;
+    use crate::parse::parse_root;
+    use crate::walk::traits::DirEntryWrapperExt;
+    use crate::walk::vfs::VfsTree;
+    use crate::Evaluate;
+
+    fn sample_tree() -> VfsTree {
+        let mut tree = VfsTree::new("/vfs");
+        tree.add_file("src/main.rs", 2048).set_mtime(SystemTime::now());
+        tree.add_file("src/lib.rs", 100);
+        tree.add_file("src-old/main.rs", 4096).set_user_id(1000);
+        tree.add_file("README.md", 10).set_mode(0o600);
+        tree.add_symlink("docs", "README.md");
+        tree.add_dir("target/debug");
+        tree
+    }
+
+    #[test]
+    fn test_walk() {
+        let tree = sample_tree();
+        let paths = tree
+            .walk()
+            .map(|entry| (entry.get_path(), entry.get_depth()))
+            .collect::<Vec<_>>();
+
+        let expected = [
+            ("/vfs", 0),
+            ("/vfs/README.md", 1),
+            ("/vfs/docs", 1),
+            ("/vfs/src", 1),
+            ("/vfs/src/lib.rs", 2),
+            ("/vfs/src/main.rs", 2),
+            ("/vfs/src-old", 1),
+            ("/vfs/src-old/main.rs", 2),
+            ("/vfs/target", 1),
+            ("/vfs/target/debug", 2),
+        ];
+        assert_eq!(paths, expected.map(|(path, depth)| (Path::new(path), depth)));
+        assert_eq!(tree.walk().next().unwrap().get_name(), "vfs");
+    }
+
+    #[test]
+    fn test_inodes_are_not_reused() {
+        let mut tree = VfsTree::new("/vfs");
+        tree.add_file("a", 1);
+        tree.add_file("a", 2);
+        tree.add_file("b/c", 3);
+
+        let mut inodes =
+            tree.walk().map(|entry| entry.get_inode().unwrap()).collect::<Vec<_>>();
+        assert_eq!(inodes.len(), 4);
+        inodes.sort();
+        inodes.dedup();
+        assert_eq!(inodes.len(), 4, "{inodes:?}");
+
+        let found = tree.search("inode = 3").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].get_path(), Path::new("/vfs/a"));
+    }
+
+    #[test]
+    fn test_search() {
+        let tree = sample_tree();
+        let search = |expression| {
+            tree.search(expression)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.get_path().to_path_buf())
+                .collect::<Vec<_>>()
+        };
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            search("ext = rs and size > 1K"),
+            paths(&["/vfs/src/main.rs", "/vfs/src-old/main.rs"])
+        );
+        assert_eq!(
+            search("name = main.rs and mtime > now - 1h"),
+            paths(&["/vfs/src/main.rs"])
+        );
+        assert_eq!(search("user = 1000"), paths(&["/vfs/src-old/main.rs"]));
+        assert_eq!(search("perms = 600"), paths(&["/vfs/README.md"]));
+        assert_eq!(search("target = README.md"), paths(&["/vfs/docs"]));
+        assert_eq!(
+            search("kind = dir and depth = 1"),
+            paths(&["/vfs/src", "/vfs/src-old", "/vfs/target"])
+        );
+        // nothing is on disk, so reading the contents fails and never matches
+        assert!(search("contains = *fn*").is_empty());
+
+        let mut tree = tree;
+        tree.add_file("src/lib.rs", 5000)
+            .set_mtime(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        assert_eq!(tree.walk().count(), 10);
+        assert!(tree.search("(").is_err());
+    }
+
+    #[test]
+    fn test_disk_is_not_read() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\0").unwrap();
+
+        // the same paths as on disk, but an empty directory and an empty file
+        let mut tree = VfsTree::new(dir.path());
+        tree.add_file("src/main.rs", 0);
+
+        for query in
+            ["contains = *fn*", "binary", "type = text", "children > 0", "dirsize > 0"]
+        {
+            assert!(tree.search(query).unwrap().is_empty(), "{query}");
+
+            let root = parse_root(query).unwrap();
+            let error = tree.walk().find_map(|entry| root.evaluate(entry).err());
+            let Some(GenericError::IoError(error)) = error else {
+                panic!("{query}: {error:?}");
+            };
+            assert_eq!(error.kind(), ErrorKind::Unsupported, "{query}");
+        }
+
+        let empty = tree.search("empty").unwrap();
+        assert_eq!(empty.len(), 1);
+        assert_eq!(empty[0].get_path(), dir.path().join("src/main.rs"));
+    }
+}